@@ -0,0 +1,539 @@
+//! Typed builders for common formats, so formats can be generated from
+//! settings instead of assembled from AST nodes
+//!
+//! ```
+//! use luscinia::builder::{CurrencyPosition, DateFormat, NumberFormat};
+//!
+//! let format = NumberFormat::decimals(2)
+//!     .grouping()
+//!     .negative_red_parens()
+//!     .currency("€", CurrencyPosition::After);
+//! assert_eq!(format.to_format_string(), "#,##0.00\" €\"_);[Red](#,##0.00\" €\")");
+//! assert_eq!(DateFormat::ymd().sep('-').to_format_string(), "yyyy-mm-dd");
+//! ```
+
+use crate::parser::split_locale_id;
+use crate::types::*;
+
+/// Most decimals Excel lets a number show
+pub(crate) const MAX_DECIMALS: u8 = 30;
+
+/// Side of the number a currency symbol is written on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurrencyPosition {
+    Before,
+    /// Before the number with a space between them, like `€ 123`
+    BeforeSpaced,
+    After,
+}
+
+/// How negative numbers are shown, the four choices of the Format Cells
+/// dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NegativeStyle {
+    /// `-1234.10`
+    #[default]
+    Minus,
+    /// `1234.10` in red
+    Red,
+    /// `(1234.10)`
+    Parens,
+    /// `(1234.10)` in red
+    RedParens,
+}
+
+/// Builder for number, currency, percent and scientific formats
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NumberFormat {
+    decimals: u8,
+    grouping: bool,
+    negative: NegativeStyle,
+    currency: Option<(String, CurrencyPosition)>,
+    percent: bool,
+    scientific: bool,
+}
+
+impl NumberFormat {
+    /// Whole numbers, `0`
+    pub fn new() -> Self {
+        NumberFormat::default()
+    }
+
+    /// Numbers with a fixed count of decimals, at most 30 like in Excel
+    pub fn decimals(decimals: u8) -> Self {
+        NumberFormat {
+            decimals: decimals.min(MAX_DECIMALS),
+            ..NumberFormat::default()
+        }
+    }
+
+    /// Group thousands with `,`
+    pub fn grouping(mut self) -> Self {
+        self.grouping = true;
+        self
+    }
+
+    pub fn negative(mut self, style: NegativeStyle) -> Self {
+        self.negative = style;
+        self
+    }
+
+    pub fn negative_red(self) -> Self {
+        self.negative(NegativeStyle::Red)
+    }
+
+    pub fn negative_parens(self) -> Self {
+        self.negative(NegativeStyle::Parens)
+    }
+
+    pub fn negative_red_parens(self) -> Self {
+        self.negative(NegativeStyle::RedParens)
+    }
+
+    /// Show a currency symbol before or after the number, separated by a
+    /// space when written after it. The symbol may carry its locale, like
+    /// `[$€-407]`.
+    pub fn currency(mut self, symbol: impl Into<String>, position: CurrencyPosition) -> Self {
+        self.currency = Some((symbol.into(), position));
+        self
+    }
+
+    /// Multiply by 100 and show a `%`
+    pub fn percent(mut self) -> Self {
+        self.percent = true;
+        self
+    }
+
+    /// Scientific notation, `0.00E+00`. Thousands are never grouped in
+    /// scientific notation.
+    pub fn scientific(mut self) -> Self {
+        self.scientific = true;
+        self
+    }
+
+    pub fn build(&self) -> NumFormat {
+        let positive = self.section(false);
+        match self.negative {
+            NegativeStyle::Minus => NumFormat::AnyNoCond(section(None, positive)),
+            NegativeStyle::Red => NumFormat::TwoParts(
+                any(None, positive.clone()),
+                any(Some(NFPartColor::Intl(DefinedColor::Red)), positive),
+            ),
+            NegativeStyle::Parens | NegativeStyle::RedParens => {
+                let color = (self.negative == NegativeStyle::RedParens)
+                    .then_some(NFPartColor::Intl(DefinedColor::Red));
+                NumFormat::TwoParts(any(None, positive), any(color, self.section(true)))
+            }
+        }
+    }
+
+    pub fn to_format_string(&self) -> String {
+        self.build().to_format_string()
+    }
+
+    /// The number of a section, between parentheses for negative numbers
+    /// or padded to line up with them
+    fn section(&self, in_parens: bool) -> FormatComponent {
+        let mut num_part = Vec::new();
+        if in_parens {
+            num_part.push(DigitPosOrOther::LiteralString("(".to_string()));
+        }
+        match &self.currency {
+            Some((symbol, CurrencyPosition::Before)) => num_part.extend(currency_elements(symbol)),
+            Some((symbol, CurrencyPosition::BeforeSpaced)) => {
+                let mut elements = currency_elements(symbol);
+                match elements.last_mut() {
+                    Some(DigitPosOrOther::LiteralString(text)) => text.push(' '),
+                    _ => elements.push(DigitPosOrOther::LiteralString(" ".to_string())),
+                }
+                num_part.extend(elements);
+            }
+            _ => {}
+        }
+        if self.grouping && !self.scientific {
+            num_part.extend([
+                digit(NumPlaceholder::Lazy),
+                DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::NumberGroup)),
+                digit(NumPlaceholder::Lazy),
+                digit(NumPlaceholder::Lazy),
+            ]);
+        }
+        num_part.push(digit(NumPlaceholder::Zero));
+        if self.decimals > 0 {
+            num_part.push(DigitPosOrOther::Digit(DigitPos::Separator(
+                NumSeparator::Decimal,
+            )));
+            num_part.extend((0..self.decimals).map(|_| digit(NumPlaceholder::Zero)));
+        }
+        let exp_part = self.scientific.then(|| {
+            let exponent = vec![digit(NumPlaceholder::Zero), digit(NumPlaceholder::Zero)];
+            (Sign::Plus, exponent)
+        });
+
+        let mut suffix = Vec::new();
+        if self.percent {
+            suffix.push(DigitPosOrOther::Other(Percent));
+        }
+        if let Some((symbol, CurrencyPosition::After)) = &self.currency {
+            let mut elements = currency_elements(symbol);
+            match elements.first_mut() {
+                Some(DigitPosOrOther::LiteralString(text)) => text.insert(0, ' '),
+                _ => elements.insert(0, DigitPosOrOther::LiteralString(" ".to_string())),
+            }
+            suffix.extend(elements);
+        }
+        match self.negative {
+            NegativeStyle::Parens | NegativeStyle::RedParens if in_parens => {
+                suffix.push(DigitPosOrOther::LiteralString(")".to_string()))
+            }
+            NegativeStyle::Parens | NegativeStyle::RedParens => {
+                suffix.push(DigitPosOrOther::LiteralCharSpace(')'))
+            }
+            NegativeStyle::Minus | NegativeStyle::Red => {}
+        }
+        // The exponent runs to the end of the section
+        let exp_part = match exp_part {
+            Some((sign, mut exponent)) => {
+                exponent.append(&mut suffix);
+                Some((sign, exponent))
+            }
+            None => {
+                num_part.append(&mut suffix);
+                None
+            }
+        };
+        FormatComponent::Number(NFNumber {
+            num_part,
+            exp_part,
+            has_percent: self.percent,
+        })
+    }
+}
+
+impl From<NumberFormat> for NumFormat {
+    fn from(builder: NumberFormat) -> Self {
+        builder.build()
+    }
+}
+
+/// Order of the year, month and day in a date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    YearMonthDay,
+    DayMonthYear,
+    MonthDayYear,
+}
+
+/// How the month of a date is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonthStyle {
+    /// `5` or `05`
+    Number,
+    /// `Jan`
+    ShortName,
+    /// `January`
+    LongName,
+}
+
+/// Builder for dates, optionally followed by a time
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateFormat {
+    order: DateOrder,
+    separator: char,
+    padded: bool,
+    short_year: bool,
+    month: MonthStyle,
+    time: Option<TimeFormat>,
+}
+
+impl DateFormat {
+    /// `yyyy/mm/dd`
+    pub fn ymd() -> Self {
+        DateFormat::with_order(DateOrder::YearMonthDay)
+    }
+
+    /// `dd/mm/yyyy`
+    pub fn dmy() -> Self {
+        DateFormat::with_order(DateOrder::DayMonthYear)
+    }
+
+    /// `mm/dd/yyyy`
+    pub fn mdy() -> Self {
+        DateFormat::with_order(DateOrder::MonthDayYear)
+    }
+
+    pub fn with_order(order: DateOrder) -> Self {
+        DateFormat {
+            order,
+            separator: '/',
+            padded: true,
+            short_year: false,
+            month: MonthStyle::Number,
+            time: None,
+        }
+    }
+
+    /// Character between the year, month and day
+    pub fn sep(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Write days and month numbers without a leading zero
+    pub fn unpadded(mut self) -> Self {
+        self.padded = false;
+        self
+    }
+
+    /// Two digit years
+    pub fn short_year(mut self) -> Self {
+        self.short_year = true;
+        self
+    }
+
+    pub fn month(mut self, style: MonthStyle) -> Self {
+        self.month = style;
+        self
+    }
+
+    /// Show a time after the date, separated by a space
+    pub fn time(mut self, time: TimeFormat) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn build(&self) -> NumFormat {
+        let mut components = Vec::new();
+        let digits = if self.padded { 2 } else { 1 };
+        let year = token(NFDateTimeToken::Year(if self.short_year {
+            YearFormat::TwoDigit
+        } else {
+            YearFormat::FourDigit
+        }));
+        let month = token(NFDateTimeToken::Month(MonthFormat(match self.month {
+            MonthStyle::Number => digits,
+            MonthStyle::ShortName => 3,
+            MonthStyle::LongName => 4,
+        })));
+        let day = token(NFDateTimeToken::Day(DayFormat(digits)));
+        let fields = match self.order {
+            DateOrder::YearMonthDay => [year, month, day],
+            DateOrder::DayMonthYear => [day, month, year],
+            DateOrder::MonthDayYear => [month, day, year],
+        };
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                components.push(match self.separator {
+                    '/' | '-' => NFDatetimeComponent::DateSeparator(self.separator),
+                    c => NFDatetimeComponent::Literal(c.to_string()),
+                });
+            }
+            components.push(field);
+        }
+        if let Some(time) = &self.time {
+            components.push(NFDatetimeComponent::Literal(" ".to_string()));
+            components.extend(time.components());
+        }
+        datetime(components)
+    }
+
+    pub fn to_format_string(&self) -> String {
+        self.build().to_format_string()
+    }
+}
+
+impl From<DateFormat> for NumFormat {
+    fn from(builder: DateFormat) -> Self {
+        builder.build()
+    }
+}
+
+/// Builder for times of day and durations
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeFormat {
+    seconds: bool,
+    am_pm: bool,
+    elapsed: bool,
+    subsecond_digits: u8,
+}
+
+impl TimeFormat {
+    /// `h:mm`
+    pub fn hm() -> Self {
+        TimeFormat {
+            seconds: false,
+            am_pm: false,
+            elapsed: false,
+            subsecond_digits: 0,
+        }
+    }
+
+    /// `h:mm:ss`
+    pub fn hms() -> Self {
+        TimeFormat {
+            seconds: true,
+            ..TimeFormat::hm()
+        }
+    }
+
+    /// 12-hour clock followed by `AM/PM`
+    pub fn am_pm(mut self) -> Self {
+        self.am_pm = true;
+        self
+    }
+
+    /// Count the hours past 24, `[h]:mm`, for durations. A duration has no
+    /// AM/PM marker.
+    pub fn elapsed(mut self) -> Self {
+        self.elapsed = true;
+        self
+    }
+
+    /// Fractions of a second, at most 3 digits. Shows the seconds too.
+    pub fn sub_seconds(mut self, digits: u8) -> Self {
+        self.seconds = true;
+        self.subsecond_digits = digits.min(3);
+        self
+    }
+
+    pub fn build(&self) -> NumFormat {
+        datetime(self.components())
+    }
+
+    pub fn to_format_string(&self) -> String {
+        self.build().to_format_string()
+    }
+
+    fn components(&self) -> Vec<NFDatetimeComponent> {
+        let mut components = vec![
+            token(if self.elapsed {
+                NFDateTimeToken::Abs(AbsTimeToken::AbsHour(AbsHourFormat(1)))
+            } else {
+                NFDateTimeToken::Hour(HourFormat::OneChar)
+            }),
+            NFDatetimeComponent::TimeSeparator(':'),
+            token(NFDateTimeToken::Minute(MinuteFormat::TwoChar)),
+        ];
+        if self.seconds {
+            components.push(NFDatetimeComponent::TimeSeparator(':'));
+            components.push(token(NFDateTimeToken::Second(SecondFormat::TwoChar)));
+        }
+        if self.subsecond_digits > 0 {
+            components.push(token(NFDateTimeToken::SubSecond(SubSecondFormat(
+                self.subsecond_digits,
+            ))));
+        }
+        if self.am_pm && !self.elapsed {
+            components.push(NFDatetimeComponent::Literal(" ".to_string()));
+            components.push(NFDatetimeComponent::AMPM(AmPm::Full("AM/PM".to_string())));
+        }
+        components
+    }
+}
+
+impl From<TimeFormat> for NumFormat {
+    fn from(builder: TimeFormat) -> Self {
+        builder.build()
+    }
+}
+
+/// Builder for fractions after a whole number, such as `# ?/?`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FractionFormat {
+    denominator: FractionDenominator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FractionDenominator {
+    UpTo(u8),
+    Fixed(u32),
+}
+
+impl FractionFormat {
+    /// Denominators of up to `digits` digits, at least one
+    pub fn up_to(digits: u8) -> Self {
+        FractionFormat {
+            denominator: FractionDenominator::UpTo(digits.max(1)),
+        }
+    }
+
+    /// A fixed denominator such as halves or hundredths, at least 1
+    pub fn denominator(denominator: u32) -> Self {
+        FractionFormat {
+            denominator: FractionDenominator::Fixed(denominator.max(1)),
+        }
+    }
+
+    pub fn build(&self) -> NumFormat {
+        let placeholders =
+            |count: usize| vec![FracToken::Placeholder(NumPlaceholder::Space); count];
+        let (numerator, denominator) = match self.denominator {
+            FractionDenominator::UpTo(digits) => {
+                (placeholders(digits as usize), placeholders(digits as usize))
+            }
+            FractionDenominator::Fixed(denominator) => (
+                placeholders(denominator.to_string().len()),
+                vec![FracToken::Number(denominator)],
+            ),
+        };
+        let fraction = NFFraction {
+            prefix: vec![],
+            integer_part: Some(vec![digit(NumPlaceholder::Lazy)]),
+            separator: Some(vec![DigitPosOrOther::LiteralCharSpace(' ')]),
+            numerator,
+            denominator,
+            suffix: vec![],
+            ampm_part: vec![],
+        };
+        NumFormat::AnyNoCond(section(None, FormatComponent::Fraction(fraction)))
+    }
+
+    pub fn to_format_string(&self) -> String {
+        self.build().to_format_string()
+    }
+}
+
+impl From<FractionFormat> for NumFormat {
+    fn from(builder: FractionFormat) -> Self {
+        builder.build()
+    }
+}
+
+pub(crate) fn digit(placeholder: NumPlaceholder) -> DigitPosOrOther<Percent> {
+    DigitPosOrOther::Digit(DigitPos::Digit(placeholder))
+}
+
+/// A currency symbol as number elements, keeping a leading `[$€-407]` as
+/// a locale
+pub(crate) fn currency_elements(symbol: &str) -> Vec<DigitPosOrOther<Percent>> {
+    let Some((locale, rest)) = split_locale_id(symbol) else {
+        return vec![DigitPosOrOther::LiteralString(symbol.to_string())];
+    };
+    let mut elements = vec![DigitPosOrOther::Currency(locale)];
+    if !rest.is_empty() {
+        elements.push(DigitPosOrOther::LiteralString(rest.to_string()));
+    }
+    elements
+}
+
+fn token(token: NFDateTimeToken) -> NFDatetimeComponent {
+    NFDatetimeComponent::Token(token)
+}
+
+fn datetime(components: Vec<NFDatetimeComponent>) -> NumFormat {
+    let datetime = DatetimeTuple(Some(NFDatetime { components }), None, None);
+    NumFormat::AnyNoCond(section(None, FormatComponent::Datetime(datetime)))
+}
+
+pub(crate) fn section<T>(color: Option<NFPartColor>, inner: T) -> SectionWrapper<T> {
+    SectionWrapper {
+        locale: None,
+        color,
+        special_prefix: vec![],
+        inner,
+    }
+}
+
+pub(crate) fn any(color: Option<NFPartColor>, component: FormatComponent) -> Any {
+    section(color, AnyInner::ConditionalData(None, component))
+}
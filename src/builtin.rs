@@ -13,10 +13,7 @@ pub fn builtin_formats() -> &'static BTreeMap<u8, NumFormat> {
                 let mut parts = line.split('\t');
                 let id = parts.next().unwrap().parse::<u8>().unwrap();
                 let str_format = parts.next().unwrap();
-                println!("\n{}", str_format);
-                let format = NumfmtParser::new(str_format).parse().unwrap();
-                println!("{}", serde_json::to_string_pretty(&format).unwrap());
-                (id, format)
+                (id, NumfmtParser::new(str_format).parse().unwrap())
             })
             .collect()
     })
@@ -25,3 +22,11 @@ pub fn builtin_formats() -> &'static BTreeMap<u8, NumFormat> {
 pub fn builtin_format(id: u8) -> Option<&'static NumFormat> {
     builtin_formats().get(&id)
 }
+
+/// The id of the built-in format equal to `format`, the lowest if several
+/// are
+pub fn builtin_id(format: &NumFormat) -> Option<u8> {
+    builtin_formats()
+        .iter()
+        .find_map(|(id, builtin)| (builtin == format).then_some(*id))
+}
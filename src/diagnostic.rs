@@ -0,0 +1,436 @@
+//! Diagnostics for format strings, with stable codes and caret rendering
+
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::parser::{Dialect, PResult};
+use crate::types::NumFormat;
+
+/// Excel accepts at most a positive, negative, zero and text section
+const MAX_SECTIONS: usize = 4;
+
+/// What is wrong with a format string. The codes are stable and can be
+/// matched on by tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DiagnosticCode {
+    /// A `"` without its closing quote
+    UnbalancedQuote,
+    /// A `[` without its closing bracket
+    UnclosedBracket,
+    /// `\`, `_` or `*` with no character after it
+    DanglingEscape,
+    /// More than four sections
+    TooManySections,
+    /// `[ColorN]` with N outside 1 to 56
+    BadColorIndex,
+    /// A condition in a section that cannot have one
+    ConditionNotAllowed,
+    /// `[$-xxx]` with a malformed locale ID
+    InvalidLocale,
+    /// Bracketed text that is not a color, condition, locale or prefix
+    UnknownBracket,
+    /// Any other syntax error
+    UnexpectedToken,
+    /// A construct the chosen dialect does not accept
+    NotInDialect,
+}
+
+impl DiagnosticCode {
+    /// The stable identifier of the code, such as `NF001`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::UnbalancedQuote => "NF001",
+            DiagnosticCode::UnclosedBracket => "NF002",
+            DiagnosticCode::DanglingEscape => "NF003",
+            DiagnosticCode::TooManySections => "NF004",
+            DiagnosticCode::BadColorIndex => "NF005",
+            DiagnosticCode::ConditionNotAllowed => "NF006",
+            DiagnosticCode::InvalidLocale => "NF007",
+            DiagnosticCode::UnknownBracket => "NF008",
+            DiagnosticCode::UnexpectedToken => "NF009",
+            DiagnosticCode::NotInDialect => "NF010",
+        }
+    }
+}
+
+impl Display for DiagnosticCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    /// The format cannot be parsed
+    Error,
+    /// The format parses, but not the way it was probably meant to
+    Warning,
+}
+
+/// A problem found in a format string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseDiagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    pub message: String,
+    /// Byte range of the source the diagnostic points at
+    pub span: Range<usize>,
+}
+
+impl ParseDiagnostic {
+    fn new(
+        code: DiagnosticCode,
+        severity: Severity,
+        message: impl Into<String>,
+        span: Range<usize>,
+    ) -> Self {
+        ParseDiagnostic {
+            code,
+            severity,
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Render the diagnostic with the source underlined, as in
+    ///
+    /// ```text
+    /// error[NF005]: color index must be between 1 and 56
+    ///   |
+    ///   | [Color99]0.00
+    ///   | ^^^^^^^^^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let start = self.span.start.min(source.len());
+        let end = self.span.end.clamp(start, source.len());
+        let offset = source[..start].chars().count();
+        // an empty span, like the end of the input, still gets a caret
+        let width = source[start..end].chars().count().max(1);
+        format!(
+            "{}[{}]: {}\n  |\n  | {}\n  | {}{}",
+            self.severity,
+            self.code,
+            self.message,
+            source,
+            " ".repeat(offset),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] at {}..{}: {}",
+            self.severity, self.code, self.span.start, self.span.end, self.message
+        )
+    }
+}
+
+/// Bracketed text found by the scanner
+struct Bracket {
+    span: Range<usize>,
+    section: usize,
+    /// Only brackets before the body of a section are colors, conditions or
+    /// locales, later ones can be elapsed times or currencies
+    is_prefix: bool,
+}
+
+/// Layout of a format string as far as it can be told without parsing:
+/// where sections start and end, and what is inside brackets
+#[derive(Default)]
+struct Scan {
+    sections: Vec<Range<usize>>,
+    brackets: Vec<Bracket>,
+    warnings: Vec<ParseDiagnostic>,
+}
+
+fn scan(source: &str) -> Scan {
+    let mut result = Scan::default();
+    let mut section_start = 0;
+    let mut in_prefix = true;
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ';' => {
+                result.sections.push(section_start..i);
+                section_start = i + 1;
+                in_prefix = true;
+            }
+            '"' => {
+                in_prefix = false;
+                if !chars.by_ref().any(|(_, c)| c == '"') {
+                    result.warnings.push(ParseDiagnostic::new(
+                        DiagnosticCode::UnbalancedQuote,
+                        Severity::Warning,
+                        "quoted text is never closed, the quote is shown as is",
+                        i..source.len(),
+                    ));
+                }
+            }
+            '\\' | '_' | '*' => {
+                in_prefix = false;
+                if chars.next().is_none() {
+                    result.warnings.push(ParseDiagnostic::new(
+                        DiagnosticCode::DanglingEscape,
+                        Severity::Warning,
+                        format!("`{}` needs a character after it", c),
+                        i..i + 1,
+                    ));
+                }
+            }
+            '[' => match source[i..].find(']') {
+                Some(len) => {
+                    let end = i + len + 1;
+                    while chars.next_if(|&(j, _)| j < end).is_some() {}
+                    result.brackets.push(Bracket {
+                        span: i..end,
+                        section: result.sections.len(),
+                        is_prefix: in_prefix,
+                    });
+                }
+                None => {
+                    result.warnings.push(ParseDiagnostic::new(
+                        DiagnosticCode::UnclosedBracket,
+                        Severity::Warning,
+                        "`[` is never closed, the bracket is shown as is",
+                        i..source.len(),
+                    ));
+                    in_prefix = false;
+                }
+            },
+            // the `t` prefix of Thai formats
+            't' if in_prefix => {}
+            _ => in_prefix = false,
+        }
+    }
+    result.sections.push(section_start..source.len());
+    result
+}
+
+/// What a bracket holds, judged from its text
+enum BracketKind {
+    Color(Option<u32>),
+    Condition,
+    Locale { valid: bool },
+    SpecialPrefix,
+    Elapsed,
+    Unknown,
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "blue", "cyan", "green", "magenta", "red", "white", "yellow",
+];
+const SPECIAL_PREFIXES: [&str; 7] = ["eng", "dbnum1", "dbnum2", "dbnum3", "hij", "jpn", "twn"];
+
+fn bracket_kind(inner: &str) -> BracketKind {
+    let lower = inner.to_ascii_lowercase();
+    if COLOR_NAMES.contains(&lower.as_str()) {
+        return BracketKind::Color(None);
+    }
+    // `[Colorful]` is not a color, only digits make an indexed one
+    if let Some(index) = lower
+        .strip_prefix("color")
+        .or_else(|| inner.strip_prefix("颜色"))
+        && !index.is_empty()
+        && index.chars().all(|c| c.is_ascii_digit())
+    {
+        return BracketKind::Color(index.parse().ok().or(Some(0)));
+    }
+    if inner.starts_with(['<', '>', '=']) {
+        return BracketKind::Condition;
+    }
+    if let Some(locale) = inner.strip_prefix('$') {
+        let valid = match locale.split_once('-') {
+            Some((_, hex)) => {
+                (3..=8).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => true,
+        };
+        return BracketKind::Locale { valid };
+    }
+    if SPECIAL_PREFIXES.contains(&lower.as_str()) {
+        return BracketKind::SpecialPrefix;
+    }
+    let elapsed = |letter: char| !lower.is_empty() && lower.chars().all(|c| c == letter);
+    if elapsed('h') || elapsed('m') || elapsed('s') {
+        return BracketKind::Elapsed;
+    }
+    BracketKind::Unknown
+}
+
+/// Check a format string, returning the parsed format when it is valid
+/// along with every diagnostic found. When parsing fails, the first
+/// diagnostic is an error explaining why.
+pub(crate) fn diagnose(
+    source: &str,
+    dialect: Dialect,
+    parsed: PResult<NumFormat>,
+) -> (Option<NumFormat>, Vec<ParseDiagnostic>) {
+    match parsed {
+        Ok(format) => (Some(format), diagnose_failures(source, dialect, &[])),
+        Err(err) => (
+            None,
+            diagnose_failures(source, dialect, &[(None, err.location.offset)]),
+        ),
+    }
+}
+
+/// Split a format string into sections, skipping quoted text and escaped
+/// characters
+pub(crate) fn split_sections(source: &str) -> Vec<Range<usize>> {
+    scan(source).sections
+}
+
+/// Explain parse failures, given as the section that failed when sections
+/// were parsed one by one and the offset in the whole format, followed by
+/// the warnings of the format
+pub(crate) fn diagnose_failures(
+    source: &str,
+    dialect: Dialect,
+    failures: &[(Option<usize>, usize)],
+) -> Vec<ParseDiagnostic> {
+    let scan = scan(source);
+    let mut errors = Vec::new();
+    for &(section, offset) in failures {
+        let error = explain_error(source, dialect, &scan, section, offset);
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    }
+
+    let unknown_brackets = scan.brackets.iter().filter_map(|bracket| {
+        let inner = &source[bracket.span.start + 1..bracket.span.end - 1];
+        let reading = if bracket.is_prefix {
+            "ignored"
+        } else {
+            "shown as text"
+        };
+        matches!(bracket_kind(inner), BracketKind::Unknown).then(|| {
+            ParseDiagnostic::new(
+                DiagnosticCode::UnknownBracket,
+                Severity::Warning,
+                format!("unknown bracket `[{}]` is {}", inner, reading),
+                bracket.span.clone(),
+            )
+        })
+    });
+    // a warning that explains an error is only reported once
+    let warnings = unknown_brackets
+        .chain(scan.warnings.iter().cloned())
+        .filter(|w| !errors.iter().any(|e| e.span == w.span))
+        .collect::<Vec<_>>();
+    errors.extend(warnings);
+    errors
+}
+
+/// Find the most likely reason a format, or one of its sections, failed to
+/// parse
+fn explain_error(
+    source: &str,
+    dialect: Dialect,
+    scan: &Scan,
+    section: Option<usize>,
+    offset: usize,
+) -> ParseDiagnostic {
+    if section.is_none_or(|index| index >= MAX_SECTIONS)
+        && let Some(extra) = scan.sections.get(MAX_SECTIONS)
+    {
+        return ParseDiagnostic::new(
+            DiagnosticCode::TooManySections,
+            Severity::Error,
+            format!("a format has at most {} sections", MAX_SECTIONS),
+            extra.start.saturating_sub(1)..source.len(),
+        );
+    }
+
+    let brackets = scan
+        .brackets
+        .iter()
+        .filter(|b| b.is_prefix && section.is_none_or(|index| b.section == index));
+    for bracket in brackets {
+        let inner = &source[bracket.span.start + 1..bracket.span.end - 1];
+        let error = |code, message: &str| {
+            ParseDiagnostic::new(code, Severity::Error, message, bracket.span.clone())
+        };
+        match bracket_kind(inner) {
+            BracketKind::Color(Some(index)) if !(1..=56).contains(&index) => {
+                return error(
+                    DiagnosticCode::BadColorIndex,
+                    "color index must be between 1 and 56",
+                );
+            }
+            BracketKind::Condition if bracket.section >= 2 => {
+                return error(
+                    DiagnosticCode::ConditionNotAllowed,
+                    "only the first two sections can have a condition",
+                );
+            }
+            BracketKind::Condition if scan.sections.len() == 1 => {
+                return error(
+                    DiagnosticCode::ConditionNotAllowed,
+                    "a condition needs a second section for the other values",
+                );
+            }
+            BracketKind::Locale { valid: false } => {
+                return error(
+                    DiagnosticCode::InvalidLocale,
+                    "locale ID must be 3 to 8 hexadecimal digits",
+                );
+            }
+            BracketKind::Color(_) if inner.starts_with("颜色") && !dialect.localized_colors() => {
+                return error(
+                    DiagnosticCode::NotInDialect,
+                    &format!("localized color names are not accepted by {:?}", dialect),
+                );
+            }
+            BracketKind::SpecialPrefix if !dialect.special_prefixes() => {
+                return error(
+                    DiagnosticCode::NotInDialect,
+                    &format!("`[{}]` is not accepted by {:?}", inner, dialect),
+                );
+            }
+            BracketKind::Unknown if !dialect.unknown_brackets() => {
+                return error(DiagnosticCode::UnknownBracket, "unknown bracket");
+            }
+            _ => {}
+        }
+    }
+
+    // a failure inside an unclosed quote or bracket is caused by it
+    if let Some(warning) = scan
+        .warnings
+        .iter()
+        .find(|w| w.span.start <= offset && offset <= w.span.end)
+    {
+        return ParseDiagnostic {
+            severity: Severity::Error,
+            ..warning.clone()
+        };
+    }
+
+    let message = match source[offset..].chars().next() {
+        Some(c) => format!("unexpected `{}`", c),
+        None => "unexpected end of format".to_string(),
+    };
+    let end = offset + source[offset..].chars().next().map_or(0, char::len_utf8);
+    ParseDiagnostic::new(
+        DiagnosticCode::UnexpectedToken,
+        Severity::Error,
+        message,
+        offset..end,
+    )
+}
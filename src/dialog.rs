@@ -0,0 +1,513 @@
+//! The Format Cells dialog of spreadsheet apps as a data model
+//!
+//! A [`FormatCells`] holds the category and options picked in the dialog.
+//! It turns into a [`NumFormat`], and a parsed format turns back into the
+//! dialog settings when it is exactly what one of them produces; anything
+//! else is a custom format.
+//!
+//! ```
+//! use luscinia::builder::NegativeStyle;
+//! use luscinia::dialog::FormatCells;
+//! use luscinia::NumFormat;
+//!
+//! let number = FormatCells::Number {
+//!     decimals: 2,
+//!     grouping: true,
+//!     negative: NegativeStyle::Red,
+//! };
+//! assert_eq!(number.to_format_string(), "#,##0.00;[Red]#,##0.00");
+//!
+//! let parsed: NumFormat = "#,##0.00;[Red]#,##0.00".parse().unwrap();
+//! assert_eq!(FormatCells::from_format(&parsed), Some(number));
+//! ```
+
+use crate::builder::{
+    CurrencyPosition, FractionFormat, NegativeStyle, NumberFormat, any, currency_elements, digit,
+    section,
+};
+use crate::parser::{NumfmtParser, split_locale_id};
+use crate::types::*;
+use crate::visit::Visit;
+
+/// Category and options of the Format Cells dialog
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatCells {
+    General,
+    Number {
+        decimals: u8,
+        grouping: bool,
+        negative: NegativeStyle,
+    },
+    Currency {
+        decimals: u8,
+        symbol: String,
+        /// Windows locale id the symbol is written with, 0x407 for
+        /// `[$€-407]`
+        locale: Option<u16>,
+        position: CurrencyPosition,
+        negative: NegativeStyle,
+    },
+    /// Currency symbols and decimal points lined up in a column, zero shown
+    /// as a dash. Without a symbol the numbers are still lined up.
+    Accounting {
+        decimals: u8,
+        symbol: Option<String>,
+        /// Windows locale id the symbol is written with
+        locale: Option<u16>,
+    },
+    Date(DatePreset),
+    Time(TimePreset),
+    Percentage {
+        decimals: u8,
+    },
+    Fraction(FractionStyle),
+    Scientific {
+        decimals: u8,
+    },
+    Text,
+    Special(SpecialFormat),
+}
+
+/// Denominators offered by the Fraction category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractionStyle {
+    /// Denominators of up to 1, 2 or 3 digits
+    UpTo(u8),
+    /// Halves, quarters, eighths, sixteenths, tenths or hundredths
+    Denominator(u32),
+}
+
+impl FractionStyle {
+    pub const ALL: [FractionStyle; 9] = [
+        FractionStyle::UpTo(1),
+        FractionStyle::UpTo(2),
+        FractionStyle::UpTo(3),
+        FractionStyle::Denominator(2),
+        FractionStyle::Denominator(4),
+        FractionStyle::Denominator(8),
+        FractionStyle::Denominator(16),
+        FractionStyle::Denominator(10),
+        FractionStyle::Denominator(100),
+    ];
+}
+
+/// Entries of the Date category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatePreset {
+    /// `3/14/2012`
+    Short,
+    /// `Wednesday, March 14, 2012`
+    Long,
+    /// `3/14`
+    MonthDay,
+    /// `3/14/12`
+    ShortYear,
+    /// `03/14/12`
+    PaddedShortYear,
+    /// `14-Mar`
+    DayMonthName,
+    /// `14-Mar-12`
+    DayMonthNameYear,
+    /// `14-Mar-2012`
+    DayMonthNameLongYear,
+    /// `Mar-12`
+    MonthNameYear,
+    /// `March-12`
+    LongMonthNameYear,
+    /// `March 14, 2012`
+    LongMonthNameDayYear,
+    /// `M`
+    MonthInitial,
+    /// `M-12`
+    MonthInitialYear,
+    /// `3/14/12 1:30 PM`
+    DateTimeAmPm,
+    /// `2012-03-14`
+    Iso,
+}
+
+impl DatePreset {
+    pub const ALL: [DatePreset; 15] = [
+        DatePreset::Short,
+        DatePreset::Long,
+        DatePreset::MonthDay,
+        DatePreset::ShortYear,
+        DatePreset::PaddedShortYear,
+        DatePreset::DayMonthName,
+        DatePreset::DayMonthNameYear,
+        DatePreset::DayMonthNameLongYear,
+        DatePreset::MonthNameYear,
+        DatePreset::LongMonthNameYear,
+        DatePreset::LongMonthNameDayYear,
+        DatePreset::MonthInitial,
+        DatePreset::MonthInitialYear,
+        DatePreset::DateTimeAmPm,
+        DatePreset::Iso,
+    ];
+
+    pub fn format_str(self) -> &'static str {
+        match self {
+            DatePreset::Short => "m/d/yyyy",
+            DatePreset::Long => "dddd, mmmm d, yyyy",
+            DatePreset::MonthDay => "m/d",
+            DatePreset::ShortYear => "m/d/yy",
+            DatePreset::PaddedShortYear => "mm/dd/yy",
+            DatePreset::DayMonthName => "d-mmm",
+            DatePreset::DayMonthNameYear => "d-mmm-yy",
+            DatePreset::DayMonthNameLongYear => "d-mmm-yyyy",
+            DatePreset::MonthNameYear => "mmm-yy",
+            DatePreset::LongMonthNameYear => "mmmm-yy",
+            DatePreset::LongMonthNameDayYear => "mmmm d, yyyy",
+            DatePreset::MonthInitial => "mmmmm",
+            DatePreset::MonthInitialYear => "mmmmm-yy",
+            DatePreset::DateTimeAmPm => "m/d/yy h:mm AM/PM",
+            DatePreset::Iso => "yyyy-mm-dd",
+        }
+    }
+}
+
+/// Entries of the Time category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimePreset {
+    /// `13:30`
+    HourMinute,
+    /// `1:30 PM`
+    HourMinuteAmPm,
+    /// `13:30:55`
+    HourMinuteSecond,
+    /// `1:30:55 PM`
+    HourMinuteSecondAmPm,
+    /// `30:55`
+    MinuteSecond,
+    /// `30:55.2`
+    MinuteSecondTenths,
+    /// `37:30:55`, hours past 24 included
+    Elapsed,
+    /// `3/14/12 13:30`
+    DateTime,
+}
+
+impl TimePreset {
+    pub const ALL: [TimePreset; 8] = [
+        TimePreset::HourMinute,
+        TimePreset::HourMinuteAmPm,
+        TimePreset::HourMinuteSecond,
+        TimePreset::HourMinuteSecondAmPm,
+        TimePreset::MinuteSecond,
+        TimePreset::MinuteSecondTenths,
+        TimePreset::Elapsed,
+        TimePreset::DateTime,
+    ];
+
+    pub fn format_str(self) -> &'static str {
+        match self {
+            TimePreset::HourMinute => "h:mm",
+            TimePreset::HourMinuteAmPm => "h:mm AM/PM",
+            TimePreset::HourMinuteSecond => "h:mm:ss",
+            TimePreset::HourMinuteSecondAmPm => "h:mm:ss AM/PM",
+            TimePreset::MinuteSecond => "mm:ss",
+            TimePreset::MinuteSecondTenths => "mm:ss.0",
+            TimePreset::Elapsed => "[h]:mm:ss",
+            TimePreset::DateTime => "m/d/yy h:mm",
+        }
+    }
+}
+
+/// Entries of the Special category, for the United States
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialFormat {
+    /// `98052`
+    ZipCode,
+    /// `98052-6399`
+    ZipCodePlus4,
+    /// `(425) 555-0100`, or `555-0100` without an area code
+    PhoneNumber,
+    /// `078-05-1120`
+    SocialSecurityNumber,
+}
+
+impl SpecialFormat {
+    pub const ALL: [SpecialFormat; 4] = [
+        SpecialFormat::ZipCode,
+        SpecialFormat::ZipCodePlus4,
+        SpecialFormat::PhoneNumber,
+        SpecialFormat::SocialSecurityNumber,
+    ];
+
+    pub fn format_str(self) -> &'static str {
+        match self {
+            SpecialFormat::ZipCode => "00000",
+            SpecialFormat::ZipCodePlus4 => "00000-0000",
+            SpecialFormat::PhoneNumber => "[<=9999999]###-####;(###) ###-####",
+            SpecialFormat::SocialSecurityNumber => "000-00-0000",
+        }
+    }
+}
+
+const NEGATIVE_STYLES: [NegativeStyle; 4] = [
+    NegativeStyle::Minus,
+    NegativeStyle::Red,
+    NegativeStyle::Parens,
+    NegativeStyle::RedParens,
+];
+
+impl FormatCells {
+    pub fn to_num_format(&self) -> NumFormat {
+        match self {
+            FormatCells::General => NumFormat::AnyNoCond(section(None, FormatComponent::General())),
+            FormatCells::Number {
+                decimals,
+                grouping,
+                negative,
+            } => {
+                let number = NumberFormat::decimals(*decimals).negative(*negative);
+                if *grouping { number.grouping() } else { number }.build()
+            }
+            FormatCells::Currency {
+                decimals,
+                symbol,
+                locale,
+                position,
+                negative,
+            } => NumberFormat::decimals(*decimals)
+                .grouping()
+                .currency(written_symbol(symbol, *locale), *position)
+                .negative(*negative)
+                .build(),
+            FormatCells::Accounting {
+                decimals,
+                symbol,
+                locale,
+            } => {
+                let symbol = symbol
+                    .as_ref()
+                    .map(|symbol| written_symbol(symbol, *locale));
+                accounting(*decimals, symbol.as_deref())
+            }
+            FormatCells::Date(preset) => preset_format(preset.format_str()),
+            FormatCells::Time(preset) => preset_format(preset.format_str()),
+            FormatCells::Percentage { decimals } => {
+                NumberFormat::decimals(*decimals).percent().build()
+            }
+            FormatCells::Fraction(FractionStyle::UpTo(digits)) => {
+                FractionFormat::up_to(*digits).build()
+            }
+            FormatCells::Fraction(FractionStyle::Denominator(denominator)) => {
+                FractionFormat::denominator(*denominator).build()
+            }
+            FormatCells::Scientific { decimals } => {
+                NumberFormat::decimals(*decimals).scientific().build()
+            }
+            FormatCells::Text => NumFormat::AnyNoCond(section(
+                None,
+                FormatComponent::Text(NFText {
+                    elements: vec![TextFormatElement::AtPlaceholder],
+                }),
+            )),
+            FormatCells::Special(special) => preset_format(special.format_str()),
+        }
+    }
+
+    pub fn to_format_string(&self) -> String {
+        self.to_num_format().to_format_string()
+    }
+
+    /// Dialog settings producing exactly this format, `None` for a custom
+    /// format
+    pub fn from_format(format: &NumFormat) -> Option<FormatCells> {
+        FormatCells::candidates(format)
+            .into_iter()
+            .find(|candidate| {
+                let built = candidate.to_num_format();
+                // A locale leading a section reads back as the section's locale
+                built == *format || built.to_format_string().parse().ok().as_ref() == Some(format)
+            })
+    }
+
+    /// Every setting the format could come from, guessed from what the
+    /// format shows
+    fn candidates(format: &NumFormat) -> Vec<FormatCells> {
+        let decimals = format.decimal_places().min(u8::MAX as usize) as u8;
+        let mut candidates = vec![FormatCells::General, FormatCells::Text];
+        for negative in NEGATIVE_STYLES {
+            for grouping in [false, true] {
+                candidates.push(FormatCells::Number {
+                    decimals,
+                    grouping,
+                    negative,
+                });
+            }
+        }
+        let symbol = format.currency();
+        // Symbols written with their locale, then the plain one
+        let mut symbols: Vec<(String, Option<u16>)> = CurrencyLocales::of(format);
+        symbols.extend(symbol.clone().map(|symbol| (symbol, None)));
+        for (symbol, locale) in &symbols {
+            for negative in NEGATIVE_STYLES {
+                for position in [
+                    CurrencyPosition::Before,
+                    CurrencyPosition::BeforeSpaced,
+                    CurrencyPosition::After,
+                ] {
+                    candidates.push(FormatCells::Currency {
+                        decimals,
+                        symbol: symbol.clone(),
+                        locale: *locale,
+                        position,
+                        negative,
+                    });
+                }
+            }
+        }
+        candidates.extend(
+            symbols
+                .into_iter()
+                .map(|(symbol, locale)| FormatCells::Accounting {
+                    decimals,
+                    symbol: Some(symbol),
+                    locale,
+                }),
+        );
+        if symbol.is_none() {
+            candidates.push(FormatCells::Accounting {
+                decimals,
+                symbol,
+                locale: None,
+            });
+        }
+        candidates.push(FormatCells::Percentage { decimals });
+        candidates.push(FormatCells::Scientific { decimals });
+        candidates.extend(FractionStyle::ALL.map(FormatCells::Fraction));
+        candidates.extend(DatePreset::ALL.map(FormatCells::Date));
+        candidates.extend(TimePreset::ALL.map(FormatCells::Time));
+        candidates.extend(SpecialFormat::ALL.map(FormatCells::Special));
+        candidates
+    }
+}
+
+impl From<FormatCells> for NumFormat {
+    fn from(settings: FormatCells) -> Self {
+        settings.to_num_format()
+    }
+}
+
+/// Currency symbols of a format written with a locale id, like `[$€-407]`
+#[derive(Default)]
+struct CurrencyLocales(Vec<(String, Option<u16>)>);
+
+impl CurrencyLocales {
+    fn of(format: &NumFormat) -> Vec<(String, Option<u16>)> {
+        let mut locales = CurrencyLocales::default();
+        locales.visit_num_format(format);
+        locales.0
+    }
+}
+
+impl<'ast> Visit<'ast> for CurrencyLocales {
+    fn visit_locale(&mut self, node: &'ast PartLocaleID) {
+        if let Some(ParsedLanguageInfo::RawLID(lid)) = node.language_info
+            && !node.currency_symbol.is_empty()
+        {
+            let currency = (node.currency_symbol.clone(), Some(lid));
+            if !self.0.contains(&currency) {
+                self.0.push(currency);
+            }
+        }
+    }
+}
+
+/// The symbol as the format writes it, with its locale id when it has one
+fn written_symbol(symbol: &str, locale: Option<u16>) -> String {
+    match locale {
+        Some(lid) => format!("[${}-{:X}]", symbol, lid),
+        None => symbol.to_string(),
+    }
+}
+
+fn preset_format(format_str: &str) -> NumFormat {
+    NumfmtParser::new(format_str)
+        .parse()
+        .expect("dialog presets are valid formats")
+}
+
+/// `_($* #,##0.00_);_($* \(#,##0.00\);_($* "-"??_);_(@_)`: a fill between
+/// the symbol and the number lines both up, and zero is a dash where the
+/// decimals would end
+fn accounting(decimals: u8, symbol: Option<&str>) -> NumFormat {
+    let number = NumberFormat::decimals(decimals).grouping().build();
+    let NumFormat::AnyNoCond(SectionWrapper {
+        inner: FormatComponent::Number(NFNumber {
+            num_part: digits, ..
+        }),
+        ..
+    }) = number
+    else {
+        unreachable!("a plain number format has one number section")
+    };
+    let lead = |symbol: Option<&str>| {
+        let mut lead = vec![DigitPosOrOther::LiteralCharSpace('(')];
+        lead.extend(symbol.map(currency_elements).unwrap_or_default());
+        lead.push(DigitPosOrOther::FillChar(' '));
+        lead
+    };
+    let number_section = |num_part: Vec<DigitPosOrOther<Percent>>| {
+        any(
+            None,
+            FormatComponent::Number(NFNumber {
+                num_part,
+                exp_part: None,
+                has_percent: false,
+            }),
+        )
+    };
+
+    let mut positive = lead(symbol);
+    positive.extend(digits.iter().cloned());
+    positive.push(DigitPosOrOther::LiteralCharSpace(')'));
+
+    let mut negative = lead(symbol);
+    negative.push(DigitPosOrOther::LiteralString("(".to_string()));
+    negative.extend(digits.iter().cloned());
+    negative.push(DigitPosOrOther::LiteralString(")".to_string()));
+
+    // Without decimals the dash has no placeholders, so the section is text
+    let zero = if decimals == 0 {
+        let mut elements = vec![TextFormatElement::LiteralCharSpace('(')];
+        // Text sections have no locales, so only the symbol is shown
+        let text = symbol.map(|symbol| match split_locale_id(symbol) {
+            Some((locale, rest)) => format!("{}{}", locale.currency_symbol, rest),
+            None => symbol.to_string(),
+        });
+        elements.extend(text.map(TextFormatElement::LiteralString));
+        elements.extend([
+            TextFormatElement::FillChar(' '),
+            TextFormatElement::LiteralString("-".to_string()),
+            TextFormatElement::LiteralCharSpace(')'),
+        ]);
+        FormatComponent::Text(NFText { elements })
+    } else {
+        let mut zero = lead(symbol);
+        zero.push(DigitPosOrOther::LiteralString("-".to_string()));
+        zero.extend((0..decimals).map(|_| digit(NumPlaceholder::Space)));
+        zero.push(DigitPosOrOther::LiteralCharSpace(')'));
+        FormatComponent::Number(NFNumber {
+            num_part: zero,
+            exp_part: None,
+            has_percent: false,
+        })
+    };
+
+    let text = NFText {
+        elements: vec![
+            TextFormatElement::LiteralCharSpace('('),
+            TextFormatElement::AtPlaceholder,
+            TextFormatElement::LiteralCharSpace(')'),
+        ],
+    };
+    NumFormat::FourParts(
+        number_section(positive),
+        number_section(negative),
+        section(None, zero),
+        Some(FormatComponent::Text(text)),
+    )
+}
@@ -0,0 +1,392 @@
+//! Edits of an existing format, the toolbar actions of spreadsheet apps
+//!
+//! Every edit changes the number sections of a format the same way and
+//! returns a new format. Literals, colors, conditions and the date, fraction
+//! and text sections are kept as they are.
+//!
+//! ```
+//! use luscinia::builder::{CurrencyPosition, NegativeStyle};
+//! use luscinia::NumFormat;
+//!
+//! let format: NumFormat = "0.0;[Red]-0.0".parse().unwrap();
+//! let edited = format
+//!     .increase_decimals()
+//!     .toggle_grouping()
+//!     .with_currency("€", CurrencyPosition::After);
+//! assert_eq!(edited.to_string(), "#,##0.00\" €\";[Red]-#,##0.00\" €\"");
+//! assert_eq!(
+//!     edited.with_negative_style(NegativeStyle::Parens).to_string(),
+//!     "#,##0.00\" €\"_);(#,##0.00\" €\")"
+//! );
+//! ```
+
+use crate::builder::{CurrencyPosition, MAX_DECIMALS, NegativeStyle};
+use crate::types::*;
+use crate::visit::visit_mut::{self, VisitMut};
+
+const DECIMAL: DigitPosOrOther<Percent> =
+    DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::Decimal));
+const GROUP: DigitPosOrOther<Percent> =
+    DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::NumberGroup));
+
+fn is_placeholder(part: &DigitPosOrOther<Percent>) -> bool {
+    matches!(part, DigitPosOrOther::Digit(DigitPos::Digit(_)))
+}
+
+fn placeholder(placeholder: NumPlaceholder) -> DigitPosOrOther<Percent> {
+    DigitPosOrOther::Digit(DigitPos::Digit(placeholder))
+}
+
+impl NFNumber {
+    fn decimal_index(&self) -> Option<usize> {
+        self.num_part.iter().position(|part| *part == DECIMAL)
+    }
+
+    /// Position of the last decimal placeholder, the decimal point when
+    /// nothing follows it
+    fn last_decimal(&self) -> Option<usize> {
+        let decimal = self.decimal_index()?;
+        let last = self.num_part[decimal..].iter().rposition(is_placeholder);
+        Some(decimal + last.unwrap_or(0))
+    }
+
+    fn add_decimal(&mut self) {
+        if self.decimal_places() >= MAX_DECIMALS as usize {
+            return;
+        }
+        if let Some(last) = self.last_decimal() {
+            self.num_part
+                .insert(last + 1, placeholder(NumPlaceholder::Zero));
+        } else if let Some(last) = self.num_part.iter().rposition(is_placeholder) {
+            self.num_part.splice(
+                last + 1..last + 1,
+                [DECIMAL, placeholder(NumPlaceholder::Zero)],
+            );
+        }
+    }
+
+    fn remove_decimal(&mut self) {
+        let Some(decimal) = self.decimal_index() else {
+            return;
+        };
+        if let Some(last) = self.last_decimal()
+            && last != decimal
+        {
+            self.num_part.remove(last);
+        }
+        if self.decimal_places() == 0 {
+            self.num_part.remove(decimal);
+            // `.0` would be left without any digit
+            if !self.num_part.iter().any(is_placeholder) {
+                self.num_part
+                    .insert(decimal, placeholder(NumPlaceholder::Zero));
+            }
+        }
+    }
+
+    /// Indices of the placeholders before the decimal point
+    fn integer_placeholders(&self) -> Vec<usize> {
+        let end = self.decimal_index().unwrap_or(self.num_part.len());
+        (0..end)
+            .filter(|&i| is_placeholder(&self.num_part[i]))
+            .collect()
+    }
+
+    /// Pad the integer digits with `#` to `#,##0` and put a `,` before the
+    /// last three
+    fn add_grouping(&mut self) {
+        let integer = self.integer_placeholders();
+        let Some(&first) = integer.first() else {
+            return;
+        };
+        let padding = 4usize.saturating_sub(integer.len());
+        self.num_part.splice(
+            first..first,
+            (0..padding).map(|_| placeholder(NumPlaceholder::Lazy)),
+        );
+        let integer = self.integer_placeholders();
+        self.num_part.insert(integer[integer.len() - 3], GROUP);
+    }
+
+    /// Drop the `,` between integer digits, keeping the scaling commas after
+    /// them, and the `#` padding in front
+    fn remove_grouping(&mut self) {
+        let Some(&last) = self.integer_placeholders().last() else {
+            return;
+        };
+        let mut i = 0;
+        let mut last = last;
+        while i < last {
+            if self.num_part[i] == GROUP {
+                self.num_part.remove(i);
+                last -= 1;
+            } else {
+                i += 1;
+            }
+        }
+        while let [first, _, ..] = self.integer_placeholders()[..]
+            && self.num_part[first] == placeholder(NumPlaceholder::Lazy)
+        {
+            self.num_part.remove(first);
+        }
+    }
+
+    /// Tokens at the end of the number, the exponent when there is one
+    fn tail(&mut self) -> &mut Vec<DigitPosOrOther<Percent>> {
+        match &mut self.exp_part {
+            Some((_, exp_part)) => exp_part,
+            None => &mut self.num_part,
+        }
+    }
+
+    /// Position right after the digits and the `%` at the end of the number
+    fn tail_end(&mut self) -> usize {
+        self.tail()
+            .iter()
+            .rposition(|part| matches!(part, DigitPosOrOther::Digit(_) | DigitPosOrOther::Other(_)))
+            .map_or(0, |i| i + 1)
+    }
+}
+
+struct Decimals {
+    increase: bool,
+}
+
+impl VisitMut for Decimals {
+    fn visit_number_mut(&mut self, node: &mut NFNumber) {
+        if self.increase {
+            node.add_decimal();
+        } else {
+            node.remove_decimal();
+        }
+    }
+}
+
+struct Grouping {
+    add: bool,
+}
+
+impl VisitMut for Grouping {
+    fn visit_number_mut(&mut self, node: &mut NFNumber) {
+        // Scientific notation never groups thousands
+        if node.exp_part.is_some() || node.uses_grouping() == self.add {
+            return;
+        }
+        if self.add {
+            node.add_grouping();
+        } else {
+            node.remove_grouping();
+        }
+    }
+}
+
+struct PercentStyle;
+
+impl VisitMut for PercentStyle {
+    fn visit_number_mut(&mut self, node: &mut NFNumber) {
+        if node.has_percent
+            || node.exp_part.is_some()
+            || !node.num_part.iter().any(is_placeholder)
+        {
+            return;
+        }
+        let end = node.tail_end();
+        node.num_part.insert(end, DigitPosOrOther::Other(Percent));
+        node.has_percent = true;
+    }
+}
+
+/// Puts a currency in place of the one of each section, or adds it to
+/// sections without one
+struct CurrencySwap<'a> {
+    symbol: &'a str,
+    position: CurrencyPosition,
+    /// Whether the section being walked already showed a currency
+    found: bool,
+}
+
+impl VisitMut for CurrencySwap<'_> {
+    fn visit_any_mut(&mut self, node: &mut Any) {
+        self.found = false;
+        visit_mut::walk_any_mut(self, node);
+    }
+
+    fn visit_any_no_cond_mut(&mut self, node: &mut AnyNoCond) {
+        self.found = false;
+        visit_mut::walk_any_no_cond_mut(self, node);
+    }
+
+    fn visit_locale_mut(&mut self, node: &mut PartLocaleID) {
+        if !node.currency_symbol.is_empty() {
+            node.currency_symbol = self.symbol.to_string();
+            self.found = true;
+        }
+    }
+
+    fn visit_number_mut(&mut self, node: &mut NFNumber) {
+        visit_mut::walk_number_mut(self, node);
+        let exp_part = node.exp_part.iter_mut().flat_map(|(_, exp_part)| exp_part);
+        for part in node.num_part.iter_mut().chain(exp_part) {
+            if let DigitPosOrOther::LiteralString(text) = part
+                && text.contains(CURRENCY_SYMBOLS)
+            {
+                *text = text.replacen(CURRENCY_SYMBOLS, self.symbol, 1);
+                self.found = true;
+            }
+        }
+        if self.found || !node.num_part.iter().any(is_placeholder) {
+            return;
+        }
+        match self.position {
+            CurrencyPosition::Before | CurrencyPosition::BeforeSpaced => {
+                let first = node.num_part.iter().position(is_placeholder).unwrap_or(0);
+                let symbol = match self.position {
+                    CurrencyPosition::BeforeSpaced => format!("{} ", self.symbol),
+                    _ => self.symbol.to_string(),
+                };
+                node.num_part
+                    .insert(first, DigitPosOrOther::LiteralString(symbol));
+            }
+            CurrencyPosition::After => {
+                let end = node.tail_end();
+                let symbol = DigitPosOrOther::LiteralString(format!(" {}", self.symbol));
+                node.tail().insert(end, symbol);
+            }
+        }
+    }
+}
+
+fn any_section(section: AnyNoCond) -> Any {
+    SectionWrapper {
+        locale: section.locale,
+        color: section.color,
+        special_prefix: section.special_prefix,
+        inner: AnyInner::ConditionalData(None, section.inner),
+    }
+}
+
+impl NumFormat {
+    fn edited(&self, mut visitor: impl VisitMut) -> NumFormat {
+        let mut format = self.clone();
+        visitor.visit_num_format_mut(&mut format);
+        format
+    }
+
+    /// Show one more decimal, at most 30
+    pub fn increase_decimals(&self) -> NumFormat {
+        self.edited(Decimals { increase: true })
+    }
+
+    /// Show one decimal less, dropping the decimal point with the last one
+    pub fn decrease_decimals(&self) -> NumFormat {
+        self.edited(Decimals { increase: false })
+    }
+
+    /// Group thousands with `,`, or stop grouping them when the first
+    /// section does. Numbers in scientific notation are left alone.
+    pub fn toggle_grouping(&self) -> NumFormat {
+        self.edited(Grouping {
+            add: !self.uses_grouping(),
+        })
+    }
+
+    /// Multiply by 100 and show a `%`, keeping the decimals. Numbers in
+    /// scientific notation are left alone.
+    pub fn with_percent(&self) -> NumFormat {
+        self.edited(PercentStyle)
+    }
+
+    /// Replace the currency of every number, in a `[$€-407]` locale or
+    /// written as a literal. Numbers without a currency get the symbol on the
+    /// given side, after a space when it follows the number.
+    pub fn with_currency(&self, symbol: &str, position: CurrencyPosition) -> NumFormat {
+        self.edited(CurrencySwap {
+            symbol,
+            position,
+            found: false,
+        })
+    }
+
+    /// Rebuild the negative section from the first one in the given style.
+    /// The zero and text sections are kept. Formats with a condition or
+    /// whose first section is not a number are returned as they are.
+    pub fn with_negative_style(&self, style: NegativeStyle) -> NumFormat {
+        let (first, negative, rest) = match self.clone() {
+            NumFormat::AnyNoCond(first) => (any_section(first), None, None),
+            NumFormat::TwoParts(first, negative) => (first, Some(negative), None),
+            NumFormat::ThreeParts(first, negative, zero) => {
+                (first, Some(negative), Some((zero, None)))
+            }
+            NumFormat::FourParts(first, negative, zero, text) => {
+                (first, Some(negative), Some((zero, text)))
+            }
+            NumFormat::ConditionalGeneral(_) => return self.clone(),
+        };
+        if !self.conditions().is_empty() {
+            return self.clone();
+        }
+        let AnyInner::ConditionalData(_, FormatComponent::Number(mut number)) = first.inner else {
+            return self.clone();
+        };
+
+        // Start over from the number itself, without the space lining it up
+        // with a negative in parentheses, written `_)` or as a plain space
+        let padding = [
+            DigitPosOrOther::LiteralCharSpace(')'),
+            DigitPosOrOther::LiteralString(" ".to_string()),
+        ];
+        if number
+            .tail()
+            .last()
+            .is_some_and(|last| padding.contains(last))
+        {
+            number.tail().pop();
+        }
+        let mut positive = number.clone();
+        let mut negative_number = number;
+        let in_parens = matches!(style, NegativeStyle::Parens | NegativeStyle::RedParens);
+        if in_parens {
+            positive.tail().push(DigitPosOrOther::LiteralCharSpace(')'));
+            negative_number
+                .num_part
+                .insert(0, DigitPosOrOther::LiteralString("(".to_string()));
+            negative_number
+                .tail()
+                .push(DigitPosOrOther::LiteralString(")".to_string()));
+        } else if style == NegativeStyle::Minus {
+            negative_number
+                .num_part
+                .insert(0, DigitPosOrOther::LiteralString("-".to_string()));
+        }
+
+        let red = NFPartColor::Intl(DefinedColor::Red);
+        let color = match style {
+            NegativeStyle::Red | NegativeStyle::RedParens => Some(red),
+            // Keep a color that is not there to mark negatives
+            NegativeStyle::Minus | NegativeStyle::Parens => negative
+                .and_then(|negative| negative.color)
+                .filter(|color| *color != red),
+        };
+        let negative = SectionWrapper {
+            locale: first.locale.clone(),
+            color,
+            special_prefix: first.special_prefix.clone(),
+            inner: AnyInner::ConditionalData(None, FormatComponent::Number(negative_number)),
+        };
+        let positive = SectionWrapper {
+            locale: first.locale,
+            color: first.color,
+            special_prefix: first.special_prefix,
+            inner: FormatComponent::Number(positive),
+        };
+
+        match rest {
+            None if style == NegativeStyle::Minus && negative.color.is_none() => {
+                NumFormat::AnyNoCond(positive)
+            }
+            None => NumFormat::TwoParts(any_section(positive), negative),
+            Some((zero, None)) => NumFormat::ThreeParts(any_section(positive), negative, zero),
+            Some((zero, text)) => NumFormat::FourParts(any_section(positive), negative, zero, text),
+        }
+    }
+}
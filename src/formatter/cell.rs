@@ -0,0 +1,204 @@
+//! Structured formatting output, for renderers laying a value out in a cell
+
+use serde::Serialize;
+
+use crate::types::NFPartColor;
+
+/// Times a fill character is repeated when the column width is unknown
+const UNSIZED_FILL_COUNT: usize = 5;
+
+/// A piece of a formatted value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum CellPart {
+    /// Text shown as is
+    Text(String),
+    /// Character repeated to fill the column, from `*x`
+    Fill(char),
+    /// Blank as wide as the character, from `_x`
+    Space(char),
+}
+
+/// Horizontal alignment of a value in its cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Alignment {
+    /// Text values
+    Left,
+    /// Numbers, dates and booleans
+    Right,
+}
+
+/// A formatted value before it is laid out in a cell
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormattedCell {
+    pub parts: Vec<CellPart>,
+    /// Color of the section the value was formatted with
+    pub color: Option<NFPartColor>,
+    pub alignment: Alignment,
+}
+
+impl FormattedCell {
+    /// Width of the content in characters, not counting fills
+    pub fn width(&self) -> usize {
+        self.parts.iter().map(part_width).sum()
+    }
+
+    /// The fill character of the value. Excel only honours the first one
+    /// of a section.
+    pub fn fill(&self) -> Option<char> {
+        self.parts.iter().find_map(|part| match part {
+            CellPart::Fill(c) => Some(*c),
+            _ => None,
+        })
+    }
+
+    /// Number of times the fill character is repeated in a column of
+    /// `width` characters
+    pub fn fill_count(&self, width: Option<usize>) -> usize {
+        match width {
+            Some(width) => width.saturating_sub(self.width()),
+            None => UNSIZED_FILL_COUNT,
+        }
+    }
+
+    /// The value as plain text, with the fill expanded to `width`
+    pub fn to_text(&self, width: Option<usize>) -> String {
+        let mut result = String::new();
+        let mut filled = false;
+        for part in &self.parts {
+            match part {
+                CellPart::Text(text) => result.push_str(text),
+                CellPart::Fill(c) if !filled => {
+                    filled = true;
+                    result.extend(std::iter::repeat_n(*c, self.fill_count(width)));
+                }
+                CellPart::Fill(_) => {}
+                CellPart::Space(_) => result.push(' '),
+            }
+        }
+        result
+    }
+}
+
+fn part_width(part: &CellPart) -> usize {
+    match part {
+        CellPart::Text(text) => text.chars().count(),
+        CellPart::Fill(_) => 0,
+        CellPart::Space(_) => 1,
+    }
+}
+
+/// Output of a section formatter, with fills and blanks kept apart from
+/// the text around them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CellText {
+    /// Each part along with whether it is literal text of the format, which
+    /// numeral modifiers leave alone
+    parts: Vec<(CellPart, bool)>,
+}
+
+impl CellText {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write text of the rendered value
+    pub(crate) fn push(&mut self, c: char) {
+        self.push_text(&c.to_string(), false);
+    }
+
+    /// Write text of the rendered value
+    pub(crate) fn push_str(&mut self, s: &str) {
+        self.push_text(s, false);
+    }
+
+    /// Write literal text of the format
+    pub(crate) fn push_literal(&mut self, s: &str) {
+        self.push_text(s, true);
+    }
+
+    fn push_text(&mut self, s: &str, literal: bool) {
+        match self.parts.last_mut() {
+            _ if s.is_empty() => {}
+            Some((CellPart::Text(text), last)) if *last == literal => text.push_str(s),
+            _ => self.parts.push((CellPart::Text(s.to_string()), literal)),
+        }
+    }
+
+    /// Write a fill character (`*x`)
+    pub(crate) fn push_fill(&mut self, c: char) {
+        self.parts.push((CellPart::Fill(c), true));
+    }
+
+    /// Write a blank as wide as `c` (`_x`)
+    pub(crate) fn push_space(&mut self, c: char) {
+        self.parts.push((CellPart::Space(c), true));
+    }
+
+    pub(crate) fn append(&mut self, other: CellText) {
+        for (part, literal) in other.parts {
+            match part {
+                CellPart::Text(text) => self.push_text(&text, literal),
+                part => self.parts.push((part, literal)),
+            }
+        }
+    }
+
+    /// Write `s` in front of everything written so far
+    pub(crate) fn prepend_str(&mut self, s: &str) {
+        let mut prefixed = CellText::from(s);
+        prefixed.append(std::mem::take(self));
+        *self = prefixed;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.parts.iter().map(|(part, _)| part_width(part)).sum()
+    }
+
+    /// Rewrite the text of the rendered value, leaving literals, fills and
+    /// blanks alone
+    pub(crate) fn map_text(self, f: impl Fn(&str) -> String) -> Self {
+        let mut mapped = CellText::new();
+        for (part, literal) in self.parts {
+            match part {
+                CellPart::Text(text) if !literal => mapped.push_str(&f(&text)),
+                part => mapped.append(CellText {
+                    parts: vec![(part, literal)],
+                }),
+            }
+        }
+        mapped
+    }
+
+    pub(crate) fn into_cell(self, alignment: Alignment) -> FormattedCell {
+        let mut parts = Vec::new();
+        for (part, _) in self.parts {
+            match (parts.last_mut(), part) {
+                (Some(CellPart::Text(text)), CellPart::Text(next)) => text.push_str(&next),
+                (_, part) => parts.push(part),
+            }
+        }
+        FormattedCell {
+            parts,
+            color: None,
+            alignment,
+        }
+    }
+}
+
+impl From<&str> for CellText {
+    fn from(text: &str) -> Self {
+        let mut cell_text = CellText::new();
+        cell_text.push_str(text);
+        cell_text
+    }
+}
+
+impl From<String> for CellText {
+    fn from(text: String) -> Self {
+        CellText::from(text.as_str())
+    }
+}
@@ -1,40 +1,191 @@
 //! Datetime formatter implementation
 
 use crate::formatter::LocaleConfig;
+use crate::formatter::cell::CellText;
 use crate::formatter::error::{FormatError, FormatResult};
+use crate::formatter::general_fmt::format_general;
+use crate::types::common::SectionModifier;
 use crate::types::datetime::*;
-use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Timelike};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+/// Calendar fields of a serial value, rounded at the precision of the finest
+/// token in the section
+struct SerialDateTime {
+    datetime: NaiveDateTime,
+    /// Whole seconds since serial 0, shown by `[h]`, `[m]` and `[s]`
+    elapsed_seconds: i64,
+}
+
+/// First serial after 9999-12-31, the last day of the 1900 date system
+const MAX_SERIAL: f64 = 2958466.0;
+
+/// Calendar the years, months and days of a section are counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Calendar {
+    #[default]
+    Gregorian,
+    Hijri,
+    Japanese,
+    /// Republic of China, years counted from 1912
+    Taiwan,
+}
+
+impl Calendar {
+    /// Calendar chosen by the modifiers of a section, the last one wins
+    pub(crate) fn of(modifiers: &[SectionModifier]) -> Self {
+        modifiers
+            .iter()
+            .filter_map(|modifier| match modifier {
+                SectionModifier::Hijri => Some(Calendar::Hijri),
+                SectionModifier::Japanese => Some(Calendar::Japanese),
+                SectionModifier::Taiwan => Some(Calendar::Taiwan),
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or_default()
+    }
+}
+
+/// Japanese eras since Meiji: first day, name and its Latin initial
+const JAPANESE_ERAS: [((i32, u32, u32), &str, &str); 5] = [
+    ((2019, 5, 1), "令和", "R"),
+    ((1989, 1, 8), "平成", "H"),
+    ((1926, 12, 25), "昭和", "S"),
+    ((1912, 7, 30), "大正", "T"),
+    ((1868, 1, 1), "明治", "M"),
+];
+
+const HIJRI_MONTHS: [&str; 12] = [
+    "Muharram",
+    "Safar",
+    "Rabi' al-awwal",
+    "Rabi' al-thani",
+    "Jumada al-awwal",
+    "Jumada al-thani",
+    "Rajab",
+    "Sha'ban",
+    "Ramadan",
+    "Shawwal",
+    "Dhu al-Qi'dah",
+    "Dhu al-Hijjah",
+];
+
+/// A day as counted by a calendar
+struct CalendarDate {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+/// Year, month and day of a date in a calendar
+fn calendar_date(date: NaiveDate, calendar: Calendar) -> CalendarDate {
+    let gregorian = CalendarDate {
+        year: date.year(),
+        month: date.month(),
+        day: date.day(),
+    };
+    match calendar {
+        Calendar::Gregorian => gregorian,
+        Calendar::Japanese => CalendarDate {
+            year: japanese_era(date).0,
+            ..gregorian
+        },
+        Calendar::Taiwan => CalendarDate {
+            year: date.year() - 1911,
+            ..gregorian
+        },
+        Calendar::Hijri => hijri_date(date),
+    }
+}
+
+/// Year in the Japanese era of a date, with the era name and initial
+fn japanese_era(date: NaiveDate) -> (i32, &'static str, &'static str) {
+    JAPANESE_ERAS
+        .iter()
+        .find(|((y, m, d), _, _)| (date.year(), date.month(), date.day()) >= (*y, *m, *d))
+        .map(|((start, _, _), name, initial)| (date.year() - start + 1, *name, *initial))
+        .unwrap_or((date.year(), "", ""))
+}
+
+/// Convert a date to the tabular Islamic calendar, the arithmetic
+/// approximation spreadsheet apps use for Hijri dates
+fn hijri_date(date: NaiveDate) -> CalendarDate {
+    let julian_day = date.num_days_from_ce() as i64 + 1_721_425;
+    let l = julian_day - 1_948_440 + 10_632;
+    let n = (l - 1) / 10_631;
+    let l = l - 10_631 * n + 354;
+    let j = ((10_985 - l) / 5_316) * ((50 * l) / 17_719) + (l / 5_670) * ((43 * l) / 15_238);
+    let l = l - ((30 - j) / 15) * ((17_719 * j) / 50) - (j / 16) * ((15_238 * j) / 43) + 29;
+    let month = (24 * l) / 709;
+    let day = l - (709 * month) / 24;
+    CalendarDate {
+        year: (30 * n + j - 30) as i32,
+        month: month as u32,
+        day: day as u32,
+    }
+}
 
 /// Format a datetime value according to DatetimeTuple format specification
-pub fn format_datetime(value: f64, format: &DatetimeTuple, locale: &LocaleConfig) -> FormatResult {
-    let datetime = excel_serial_to_datetime(value)?;
+pub fn format_datetime(
+    value: f64,
+    format: &DatetimeTuple,
+    calendar: Calendar,
+    locale: &LocaleConfig,
+) -> Result<CellText, FormatError> {
+    // Date, time of day and elapsed time sections share one valid range in
+    // Excel: anything before serial 0 or after 9999-12-31 shows as #####,
+    // even `[h]` durations cannot be negative
+    if !(0.0..MAX_SERIAL).contains(&value) {
+        return locale
+            .overflow_value(value, "serial outside of the date range")
+            .map(CellText::from);
+    }
+
+    let serial = excel_serial_to_datetime(value, subsecond_precision(format))?;
 
-    let mut result = String::new();
+    let mut result = CellText::new();
 
     if let Some(dt_part1) = &format.0 {
-        result.push_str(&format_nf_datetime(&datetime, dt_part1, locale)?);
+        result.append(format_nf_datetime(&serial, dt_part1, calendar, locale)?);
     }
     if format.1.is_some() {
-        result.push_str(&value.to_string());
+        result.push_str(&format_general(value, locale));
     }
     if let Some(dt_part2) = &format.2 {
-        result.push_str(&format_nf_datetime(&datetime, dt_part2, locale)?);
+        result.append(format_nf_datetime(&serial, dt_part2, calendar, locale)?);
     }
 
     Ok(result)
 }
 
+/// Number of sub-second digits shown by the finest token of the section
+fn subsecond_precision(format: &DatetimeTuple) -> u8 {
+    format
+        .0
+        .iter()
+        .chain(format.2.iter())
+        .flat_map(|dt| dt.components.iter())
+        .filter_map(|component| match component {
+            NFDatetimeComponent::Token(NFDateTimeToken::SubSecond(fmt)) => Some(fmt.0.min(3)),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 /// Format a datetime according to NFDatetime format specification
-pub fn format_nf_datetime(
-    datetime: &DateTime<Local>,
+fn format_nf_datetime(
+    serial: &SerialDateTime,
     format: &NFDatetime,
+    calendar: Calendar,
     locale: &LocaleConfig,
-) -> FormatResult {
+) -> Result<CellText, FormatError> {
+    let datetime = &serial.datetime;
     let has_ampm = format
         .components
         .iter()
         .any(|comp| matches!(comp, NFDatetimeComponent::AMPM(_)));
-    let mut result = String::new();
+    let mut result = CellText::new();
 
     for component in &format.components {
         match component {
@@ -47,10 +198,10 @@ pub fn format_nf_datetime(
                         }
                         result.push_str(&format_hour(hour_12 as i32, *fmt)?);
                     } else {
-                        result.push_str(&format_datetime_token(datetime, token, locale)?);
+                        result.push_str(&format_datetime_token(serial, token, calendar, locale)?);
                     }
                 } else {
-                    result.push_str(&format_datetime_token(datetime, token, locale)?);
+                    result.push_str(&format_datetime_token(serial, token, calendar, locale)?);
                 }
             }
             NFDatetimeComponent::DateSeparator(c) => {
@@ -61,10 +212,10 @@ pub fn format_nf_datetime(
             }
             NFDatetimeComponent::AMPM(ampm) => {
                 let is_pm = datetime.hour() >= 12;
-                result.push_str(&format_ampm(ampm, is_pm));
+                result.push_str(&ampm.marker(is_pm));
             }
             NFDatetimeComponent::Literal(text) => {
-                result.push_str(text);
+                result.push_literal(text);
             }
         }
     }
@@ -73,16 +224,26 @@ pub fn format_nf_datetime(
 }
 
 /// Format a datetime token
-pub fn format_datetime_token(
-    datetime: &DateTime<Local>,
+fn format_datetime_token(
+    serial: &SerialDateTime,
     token: &NFDateTimeToken,
+    calendar: Calendar,
     locale: &LocaleConfig,
 ) -> FormatResult {
+    let datetime = &serial.datetime;
+    let date = calendar_date(datetime.date(), calendar);
     match token {
-        NFDateTimeToken::Year(fmt) => format_year(datetime.year(), fmt),
-        NFDateTimeToken::Month(fmt) => format_month(datetime.month() as i32, *fmt, locale),
+        NFDateTimeToken::Year(fmt) => format_year(date.year, fmt),
+        NFDateTimeToken::Month(fmt) if calendar == Calendar::Hijri && fmt.0 >= 3 => {
+            let name = HIJRI_MONTHS[(date.month - 1) as usize];
+            Ok(match fmt.0 {
+                5 => name[..1].to_string(),
+                _ => name.to_string(),
+            })
+        }
+        NFDateTimeToken::Month(fmt) => format_month(date.month as i32, *fmt, locale),
         NFDateTimeToken::Day(fmt) => format_day(
-            datetime.day() as i32,
+            date.day as i32,
             datetime.weekday().num_days_from_sunday(),
             *fmt,
             locale,
@@ -94,19 +255,21 @@ pub fn format_datetime_token(
         NFDateTimeToken::Minute(fmt) => format_minute(datetime.minute() as i32, *fmt),
         NFDateTimeToken::Second(fmt) => format_second(datetime.second() as i32, *fmt),
         NFDateTimeToken::SubSecond(fmt) => format_subsecond(datetime, fmt),
-        NFDateTimeToken::EraG(_fmt) => {
-            // jp era
-            Ok("平成".to_string())
-        }
+        NFDateTimeToken::EraG(fmt) => Ok(format_era(datetime.date(), calendar, *fmt)),
         NFDateTimeToken::EraYear(fmt) => {
-            // Era year - simplified implementation
-            format_era_year(datetime.year(), *fmt)
+            let year = match calendar {
+                Calendar::Taiwan => date.year,
+                _ => japanese_era(datetime.date()).0,
+            };
+            format_era_year(year, *fmt)
         }
         NFDateTimeToken::CalendarB(_fmt) => {
             // Calendar type - simplified implementation
             Ok("1".to_string())
         }
-        NFDateTimeToken::Abs(abs_token) => format_abs_time_token(datetime, abs_token),
+        NFDateTimeToken::Abs(abs_token) => {
+            format_abs_time_token(serial.elapsed_seconds, abs_token)
+        }
     }
 }
 
@@ -209,103 +372,104 @@ fn format_second(second: i32, fmt: SecondFormat) -> FormatResult {
 }
 
 /// Format subsecond component
-fn format_subsecond(datetime: &DateTime<Local>, fmt: &SubSecondFormat) -> FormatResult {
-    let millis = datetime.nanosecond() / 1_000_000;
+fn format_subsecond(datetime: &NaiveDateTime, fmt: &SubSecondFormat) -> FormatResult {
+    // the value was already rounded at this precision or a finer one
+    let digits = fmt.0.clamp(1, 3) as u32;
+    let fraction = datetime.nanosecond() / 10u32.pow(9 - digits);
 
-    match fmt.0 {
-        1 => Ok(format!(".{}", millis / 100)),
-        2 => Ok(format!(".{:02}", millis / 10)),
-        3 => Ok(format!(".{:03}", millis)),
-        _ => Ok(format!(".{:03}", millis)),
+    Ok(format!(".{:0width$}", fraction, width = digits as usize))
+}
+
+/// Format the era name, Japanese unless the section uses the Republic of
+/// China calendar
+fn format_era(date: NaiveDate, calendar: Calendar, fmt: EraFormatG) -> String {
+    if calendar == Calendar::Taiwan {
+        return match fmt {
+            EraFormatG::ThreeDigit => "中華民國",
+            _ => "民國",
+        }
+        .to_string();
+    }
+    let (_, name, initial) = japanese_era(date);
+    match fmt {
+        EraFormatG::OneDigit => initial.to_string(),
+        EraFormatG::TwoDigit => name.chars().take(1).collect(),
+        EraFormatG::ThreeDigit => name.to_string(),
     }
 }
 
 /// Format era year component
 fn format_era_year(year: i32, fmt: EraYearFormat) -> FormatResult {
-    // Simplified implementation for era year
     match fmt {
-        EraYearFormat::OneDigit => Ok(format!("{}", year % 100)),
-        EraYearFormat::TwoDigit => Ok(format!("{}", year)),
+        EraYearFormat::OneDigit => Ok(format!("{}", year)),
+        EraYearFormat::TwoDigit => Ok(format!("{:02}", year)),
     }
 }
 
 /// Format absolute time token
-fn format_abs_time_token(datetime: &DateTime<Local>, token: &AbsTimeToken) -> FormatResult {
+fn format_abs_time_token(elapsed_seconds: i64, token: &AbsTimeToken) -> FormatResult {
     match token {
         AbsTimeToken::AbsHour(fmt) => {
             // Total number of hours (for durations)
-            let total_hours = datetime.hour() as i32;
-            format_abs_value(total_hours, fmt.0)
+            format_abs_value(elapsed_seconds / 3600, fmt.0)
         }
         AbsTimeToken::AbsMinute(fmt) => {
             // Total number of minutes (for durations)
-            let total_minutes = datetime.hour() as i32 * 60 + datetime.minute() as i32;
-            format_abs_value(total_minutes, fmt.0)
+            format_abs_value(elapsed_seconds / 60, fmt.0)
         }
         AbsTimeToken::AbsSecond(fmt) => {
             // Total number of seconds (for durations)
-            let total_seconds = (datetime.hour() as i32 * 60 + datetime.minute() as i32) * 60
-                + datetime.second() as i32;
-            format_abs_value(total_seconds, fmt.0)
+            format_abs_value(elapsed_seconds, fmt.0)
         }
     }
 }
 
 /// Format absolute time value with specified number of digits
-fn format_abs_value(value: i32, num_digits: u8) -> FormatResult {
+fn format_abs_value(value: i64, num_digits: u8) -> FormatResult {
     let format_str = format!("{:0width$}", value, width = num_digits as usize);
     Ok(format_str)
 }
 
-/// Format AM/PM indicator
-fn format_ampm(ampm: &AmPm, is_pm: bool) -> String {
-    match ampm {
-        AmPm::Full => {
-            if is_pm {
-                "PM".to_string()
-            } else {
-                "AM".to_string()
-            }
-        }
-        AmPm::Simple => {
-            if is_pm {
-                "P".to_string()
-            } else {
-                "A".to_string()
-            }
-        }
-    }
-}
+/// Convert Excel serial date to DateTime, rounding the time of day once to
+/// `precision` sub-second digits so that carries reach minutes, hours and
+/// days the same way Excel shows `23:59:59.9996` as midnight of the next day
+fn excel_serial_to_datetime(serial: f64, precision: u8) -> Result<SerialDateTime, FormatError> {
+    let ticks_per_second = 10i64.pow(precision as u32);
+    let ticks_per_day = 86400 * ticks_per_second;
+    let ticks = (serial * ticks_per_day as f64).round() as i64;
 
-/// Convert Excel serial date to DateTime
-fn excel_serial_to_datetime(serial: f64) -> Result<DateTime<Local>, FormatError> {
-    let days = serial.trunc() as i64;
-    let time_fraction = serial.fract();
-    let adjusted_days = if days > 60 { days - 1 } else { days };
-    let base_date = match NaiveDateTime::parse_from_str("1899-12-31 00:00:00", "%Y-%m-%d %H:%M:%S")
-    {
-        Ok(dt) => dt,
-        Err(_) => {
-            return Err(FormatError::FormatError(
-                "Failed to parse base date".to_string(),
-            ));
-        }
-    };
+    let days = ticks.div_euclid(ticks_per_day);
+    let time_ticks = ticks.rem_euclid(ticks_per_day);
+    let seconds = time_ticks / ticks_per_second;
+    let nanos = (time_ticks % ticks_per_second) * 10i64.pow(9 - precision as u32);
 
-    let date_part = base_date + chrono::Duration::days(adjusted_days);
+    // Excel treats 1900 as a leap year, serial 60 is the fictional 1900-02-29
+    let adjusted_days = if days > 60 { days - 1 } else { days };
+    let base_date = NaiveDate::from_ymd_opt(1899, 12, 31)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| FormatError::FormatError("Failed to build base date".to_string()))?;
 
-    let seconds = (time_fraction * 86400.0).round() as i64; // 86400 seconds in a day
-    let time_part = date_part + chrono::Duration::seconds(seconds);
+    let datetime = base_date
+        .checked_add_signed(chrono::Duration::days(adjusted_days))
+        .and_then(|date| date.checked_add_signed(chrono::Duration::seconds(seconds)))
+        .and_then(|date| date.checked_add_signed(chrono::Duration::nanoseconds(nanos)))
+        .ok_or_else(|| {
+            FormatError::FormatError(format!("Serial {} is out of the date range", serial))
+        })?;
 
-    // Convert to local timezone
-    let datetime = match Local.from_local_datetime(&time_part) {
-        chrono::offset::LocalResult::Single(dt) => dt,
-        _ => {
-            return Err(FormatError::FormatError(
-                "Failed to convert to local time".to_string(),
-            ));
-        }
-    };
+    Ok(SerialDateTime {
+        datetime,
+        elapsed_seconds: days * 86400 + seconds,
+    })
+}
 
-    Ok(datetime)
+/// Serial of a date and time, the inverse of `excel_serial_to_datetime`.
+/// Dates from 1900-03-01 on count the fictional 1900-02-29.
+pub(crate) fn datetime_to_excel_serial(datetime: NaiveDateTime) -> f64 {
+    let base_date = NaiveDate::from_ymd_opt(1899, 12, 31).expect("valid base date");
+    let days = (datetime.date() - base_date).num_days();
+    let days = if days >= 60 { days + 1 } else { days };
+    let seconds = datetime.num_seconds_from_midnight() as f64
+        + datetime.nanosecond() as f64 / 1_000_000_000.0;
+    days as f64 + seconds / 86400.0
 }
@@ -5,6 +5,7 @@ pub enum FormatError {
     ParseError(String),
     FormatError(String),
     UnsupportedFormat(String),
+    OutOfRange(String),
 }
 
 impl fmt::Display for FormatError {
@@ -13,6 +14,7 @@ impl fmt::Display for FormatError {
             FormatError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             FormatError::FormatError(msg) => write!(f, "Format error: {}", msg),
             FormatError::UnsupportedFormat(msg) => write!(f, "Unsupported format: {}", msg),
+            FormatError::OutOfRange(msg) => write!(f, "Value out of range: {}", msg),
         }
     }
 }
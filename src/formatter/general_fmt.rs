@@ -0,0 +1,110 @@
+//! General formatter implementation
+
+use crate::formatter::LocaleConfig;
+
+/// Maximum number of characters Excel uses to display a number in General,
+/// not counting the minus sign
+const GENERAL_MAX_WIDTH: usize = 11;
+
+/// Format a number the way Excel's `General` format displays it
+pub fn format_general(value: f64, locale: &LocaleConfig) -> String {
+    let formatted = format_general_abs(value.abs(), GENERAL_MAX_WIDTH);
+    localize_general(value, &formatted, locale)
+}
+
+/// Format a number in General within `width` characters, sign included.
+/// Like Excel in a narrow column, decimals are dropped first and scientific
+/// notation is used next. Returns `None` when even that does not fit.
+pub fn format_general_in_width(value: f64, width: usize, locale: &LocaleConfig) -> Option<String> {
+    let sign_width = usize::from(value < 0.0);
+    let budget = width.checked_sub(sign_width)?.min(GENERAL_MAX_WIDTH);
+    let formatted = format_general_abs(value.abs(), budget);
+    if formatted.len() > budget {
+        return None;
+    }
+    Some(localize_general(value, &formatted, locale))
+}
+
+/// Add the sign and the locale's decimal separator to a formatted magnitude
+fn localize_general(value: f64, formatted: &str, locale: &LocaleConfig) -> String {
+    let decimal_sep = locale.decimal_separator.unwrap_or('.');
+    let mut result = String::new();
+    if value < 0.0 && formatted != "0" {
+        result.push('-');
+    }
+    for c in formatted.chars() {
+        result.push(if c == '.' { decimal_sep } else { c });
+    }
+    result
+}
+
+/// Format a non-negative number in at most `width` characters, preferring
+/// plain decimals and falling back to scientific notation
+fn format_general_abs(value: f64, width: usize) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return if value.is_finite() {
+            "0".to_string()
+        } else {
+            value.to_string()
+        };
+    }
+
+    let exp = value.log10().floor() as i32;
+
+    if exp >= -4 && exp < width as i32 {
+        // Moderate magnitudes are rounded to whatever fits in the width
+        let int_len = exp.max(0) as usize + 1;
+        let decimals = width.saturating_sub(int_len + 1);
+        let fixed = strip_decimal(&format!("{:.*}", decimals, value));
+        if fixed.len() <= width && fixed != "0" {
+            return fixed;
+        }
+    } else if exp < -4 {
+        // Tiny magnitudes are only shown in decimals when no digit is lost
+        let significant = significant_digits(value);
+        let decimals = (-exp - 1) as usize + significant;
+        if decimals + 2 <= width {
+            return strip_decimal(&format!("{:.*}", decimals, value));
+        }
+    }
+
+    format_scientific(value, width)
+}
+
+/// Format a non-negative number as `d.dddddE+xx` within `width` characters
+fn format_scientific(value: f64, width: usize) -> String {
+    let mut decimals = width.saturating_sub(6);
+    loop {
+        let formatted = format!("{:.*e}", decimals, value);
+        let (mantissa, exponent) = formatted.split_once('e').unwrap();
+        let exponent: i32 = exponent.parse().unwrap();
+        let sign = if exponent < 0 { '-' } else { '+' };
+        let result = format!(
+            "{}E{}{:02}",
+            strip_decimal(mantissa),
+            sign,
+            exponent.abs()
+        );
+        if result.len() <= width || decimals == 0 {
+            return result;
+        }
+        decimals -= 1;
+    }
+}
+
+/// Count the significant digits Excel would show for a number (at most 10)
+fn significant_digits(value: f64) -> usize {
+    let formatted = format!("{:.9e}", value);
+    let mantissa = formatted.split('e').next().unwrap();
+    strip_decimal(mantissa).chars().filter(|c| c.is_ascii_digit()).count()
+}
+
+/// Remove trailing zeros after the decimal point, and the point itself if
+/// nothing is left behind it
+fn strip_decimal(s: &str) -> String {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s.to_string()
+    }
+}
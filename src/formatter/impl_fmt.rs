@@ -1,8 +1,10 @@
 //! Core implementation of the Excel numfmt formatter
 
 use crate::formatter::LocaleConfig;
-use crate::formatter::datetime_fmt::format_datetime;
-use crate::formatter::error::FormatResult;
+use crate::formatter::cell::{Alignment, CellPart, CellText, FormattedCell};
+use crate::formatter::datetime_fmt::{Calendar, format_datetime};
+use crate::formatter::error::{FormatError, FormatResult};
+use crate::formatter::general_fmt::{format_general, format_general_in_width};
 use crate::formatter::number_fmt::{format_fraction, format_nf_number};
 use crate::formatter::text_fmt::format_text;
 use crate::types::common::*;
@@ -10,6 +12,9 @@ use crate::types::elements::*;
 use crate::types::number::*;
 use crate::types::numfmt::*;
 
+/// Result of formatting a value into a cell
+pub type CellResult = Result<FormattedCell, FormatError>;
+
 /// Format a value using a parsed NumFormat
 pub fn format_with_parsed(
     value: f64,
@@ -17,12 +22,37 @@ pub fn format_with_parsed(
     locale_config: Option<LocaleConfig>,
 ) -> FormatResult {
     let locale = locale_config.unwrap_or_default();
+    let cell = format_cell_with_parsed(value, format, &locale)?;
+    Ok(plain_text(&cell, &locale))
+}
+
+/// Format a string value using a parsed NumFormat
+pub fn format_string_with_parsed(
+    value: &str,
+    format: &NumFormat,
+    locale_config: Option<LocaleConfig>,
+) -> FormatResult {
+    let locale = locale_config.unwrap_or_default();
+    let cell = format_string_cell_with_parsed(value, format, &locale)?;
+    Ok(plain_text(&cell, &locale))
+}
 
+/// Render a cell as a plain string, the section color shown as a prefix
+fn plain_text(cell: &FormattedCell, locale: &LocaleConfig) -> String {
+    let text = cell.to_text(locale.column_width);
+    match &cell.color {
+        Some(color) => format_with_color(&text, color),
+        None => text,
+    }
+}
+
+/// Format a value into a cell using a parsed NumFormat
+pub fn format_cell_with_parsed(value: f64, format: &NumFormat, locale: &LocaleConfig) -> CellResult {
     match format {
         NumFormat::ConditionalGeneral(section) => {
-            format_conditional_general(value, section, &locale)
+            format_conditional_general(value, section, locale)
         }
-        NumFormat::AnyNoCond(section) => format_any_no_cond(value, section, &locale),
+        NumFormat::AnyNoCond(section) => format_any_no_cond(value, section, locale),
         NumFormat::TwoParts(positive, negative) => {
             let mut has_condition = false;
             if let SectionWrapper {
@@ -31,7 +61,7 @@ pub fn format_with_parsed(
             } = &positive
             {
                 if evaluate_condition(value, cond) {
-                    return format_any(value, positive, &locale);
+                    return format_any(value, positive, locale);
                 }
                 has_condition = true;
             }
@@ -42,19 +72,19 @@ pub fn format_with_parsed(
             } = &negative
             {
                 if evaluate_condition(value, cond) {
-                    return format_any(value.abs(), negative, &locale);
+                    return format_any(value.abs(), negative, locale);
                 }
                 has_condition = true;
             }
 
             if has_condition {
-                return format_any(value, negative, &locale);
+                return format_any(value, negative, locale);
             }
 
             if value >= 0.0 {
-                format_any(value, positive, &locale)
+                format_any(value, positive, locale)
             } else {
-                format_any(value.abs(), negative, &locale)
+                format_any(value.abs(), negative, locale)
             }
         }
         NumFormat::ThreeParts(positive, negative, zero) => {
@@ -65,7 +95,7 @@ pub fn format_with_parsed(
             } = &positive
             {
                 if evaluate_condition(value, cond) {
-                    return format_any(value, positive, &locale);
+                    return format_any(value, positive, locale);
                 }
                 has_condition = true;
             }
@@ -76,21 +106,21 @@ pub fn format_with_parsed(
             } = &negative
             {
                 if evaluate_condition(value, cond) {
-                    return format_any(value.abs(), negative, &locale);
+                    return format_any(value.abs(), negative, locale);
                 }
                 has_condition = true;
             }
 
             if has_condition {
-                return format_any_no_cond(value, zero, &locale);
+                return format_any_no_cond(value, zero, locale);
             }
 
             if value > 0.0 {
-                format_any(value, positive, &locale)
+                format_any(value, positive, locale)
             } else if value < 0.0 {
-                format_any(value.abs(), negative, &locale)
+                format_any(value.abs(), negative, locale)
             } else {
-                format_any_no_cond(value, zero, &locale)
+                format_any_no_cond(value, zero, locale)
             }
         }
         NumFormat::FourParts(positive, negative, zero, _) => {
@@ -102,7 +132,7 @@ pub fn format_with_parsed(
             } = &positive
             {
                 if evaluate_condition(value, cond) {
-                    return format_any(value, positive, &locale);
+                    return format_any(value, positive, locale);
                 }
                 has_condition = true;
             }
@@ -113,70 +143,79 @@ pub fn format_with_parsed(
             } = &negative
             {
                 if evaluate_condition(value, cond) {
-                    return format_any(value.abs(), negative, &locale);
+                    return format_any(value.abs(), negative, locale);
                 }
                 has_condition = true;
             }
 
             if has_condition {
                 // use section 3
-                return format_any_no_cond(value, zero, &locale);
+                return format_any_no_cond(value, zero, locale);
             }
 
             if value > 0.0 {
-                format_any(value, positive, &locale)
+                format_any(value, positive, locale)
             } else if value < 0.0 {
-                format_any(value.abs(), negative, &locale)
+                format_any(value.abs(), negative, locale)
             } else {
-                format_any_no_cond(value, zero, &locale)
+                format_any_no_cond(value, zero, locale)
             }
         }
     }
 }
 
-/// Format a string value using a parsed NumFormat
-pub fn format_string_with_parsed(
+/// Format a string value into a cell using a parsed NumFormat
+pub fn format_string_cell_with_parsed(
     value: &str,
     format: &NumFormat,
-    locale_config: Option<LocaleConfig>,
-) -> FormatResult {
-    println!("format_string_with_parsed: {:?}", format);
-    let locale = locale_config.unwrap_or_default();
-
+    locale: &LocaleConfig,
+) -> CellResult {
     match format {
         // Handle the standard case where text section is the 4th part of a format
         NumFormat::FourParts(_, _, _, Some(FormatComponent::Text(text_section))) => {
-            apply_bare_text_formatting(value, text_section.clone(), &locale)
+            apply_bare_text_formatting(value, text_section.clone(), locale)
         }
-        NumFormat::FourParts(_, _, _, Some(_)) => Ok(value.to_string()),
+        NumFormat::FourParts(_, _, _, Some(_)) => Ok(text_cell(value)),
 
         NumFormat::AnyNoCond(section) => match &section.inner {
             FormatComponent::Text(text_section) => {
-                apply_bare_text_formatting(value, text_section.clone(), &locale)
+                let formatted = format_text(value, text_section, locale)?;
+                apply_section_decorations(formatted.into_cell(Alignment::Left), section, locale)
             }
-            _ => Ok(value.to_string()),
+            _ => Ok(text_cell(value)),
         },
 
-        _ => Ok(value.to_string()),
+        _ => Ok(text_cell(value)),
     }
 }
 
+/// A string value shown as is
+fn text_cell(value: &str) -> FormattedCell {
+    CellText::from(value).into_cell(Alignment::Left)
+}
+
+/// A number rendered by a section formatter
+fn number_cell(formatted: impl Into<CellText>) -> FormattedCell {
+    formatted.into().into_cell(Alignment::Right)
+}
+
 /// Format a value with a conditional general format
 fn format_conditional_general(
     value: f64,
     section: &SectionWrapper<(NFPartCondition, NFGeneral)>,
     locale: &LocaleConfig,
-) -> FormatResult {
+) -> CellResult {
     let (condition, _) = &section.inner;
 
     // Check if the condition is met
     if evaluate_condition(value, condition) {
         // Apply general format
-        let formatted = format!("{}", value);
-        apply_section_decorations(&formatted, section, locale)
+        let formatted = format_general_fit(value, locale)?;
+        let formatted = native_digits(&formatted, &section.special_prefix, true, locale);
+        apply_section_decorations(number_cell(formatted), section, locale)
     } else {
         // Condition not met, use default format
-        Ok(format!("{}", value))
+        Ok(number_cell(format_general_fit(value, locale)?))
     }
 }
 
@@ -185,9 +224,9 @@ fn format_any_no_cond(
     value: f64,
     section: &SectionWrapper<FormatComponent>,
     locale: &LocaleConfig,
-) -> FormatResult {
-    let formatted = format_component(value, &section.inner, locale)?;
-    apply_section_decorations(&formatted, section, locale)
+) -> CellResult {
+    let formatted = format_component(value, &section.inner, &section.special_prefix, locale)?;
+    apply_section_decorations(formatted, section, locale)
 }
 
 /// Format a value with an AnyNoText format (no text allowed)
@@ -195,73 +234,268 @@ fn format_any_no_text(
     value: f64,
     section: &SectionWrapper<AnyInner>,
     locale: &LocaleConfig,
-) -> FormatResult {
+) -> CellResult {
     let AnyInner::ConditionalData(condition, component) = &section.inner;
     // Check if condition is met
-    if let Some(cond) = condition {
-        if !evaluate_condition(value, cond) {
-            // Condition not met, use default format
-            return Ok(format!("{}", value));
-        }
+    if let Some(cond) = condition
+        && !evaluate_condition(value, cond)
+    {
+        // Condition not met, use default format
+        return Ok(number_cell(format_general_fit(value, locale)?));
     }
 
-    let formatted =
-        format_component(value, component, locale)?;
-    apply_section_decorations(&formatted, section, locale)
+    let formatted = format_component(value, component, &section.special_prefix, locale)?;
+    apply_section_decorations(formatted, section, locale)
 }
 
 /// Format a value with an Any format (could be text or other)
-fn format_any(value: f64, section: &Any, locale: &LocaleConfig) -> FormatResult {
+fn format_any(value: f64, section: &Any, locale: &LocaleConfig) -> CellResult {
     format_any_no_text(value, section, locale)
 }
 
-/// Format a number/fraction/datetime value
+/// Format a number/fraction/datetime value, in the numeral system and
+/// calendar picked by the section modifiers
 fn format_component(
     value: f64,
     format: &FormatComponent,
+    modifiers: &[SectionModifier],
     locale: &LocaleConfig,
-) -> FormatResult {
-    match format {
-        FormatComponent::General() => Ok(format!("{}", value)),
-        FormatComponent::Number(number) => format_nf_number(value, number, locale),
-        FormatComponent::Fraction(fraction) => format_fraction(value, fraction, locale),
-        FormatComponent::Datetime(datetime) => format_datetime(value, datetime, locale),
-        FormatComponent::Text(text) => format_text(&value.to_string(), text, locale),
+) -> CellResult {
+    let formatted = match format {
+        FormatComponent::General() => {
+            let formatted = format_general_fit(value, locale)?;
+            let formatted = native_digits(&formatted, modifiers, true, locale);
+            return Ok(number_cell(formatted));
+        }
+        FormatComponent::Number(number) => format_nf_number(value, number, locale)?,
+        FormatComponent::Fraction(fraction) => format_fraction(value, fraction, locale)?,
+        FormatComponent::Datetime(datetime) => {
+            format_datetime(value, datetime, Calendar::of(modifiers), locale)?
+        }
+        FormatComponent::Text(text) => format_text_with_general(value, text, locale)?,
+    };
+    let whole_numbers = matches!(
+        format,
+        FormatComponent::Number(_) | FormatComponent::Text(_)
+    );
+    let formatted =
+        formatted.map_text(|text| native_digits(text, modifiers, whole_numbers, locale));
+    fit_column(value, number_cell(formatted), locale)
+}
+
+/// Numerals of a section modifier, and for `[DBNum1]` and `[DBNum2]` the
+/// units of tens, hundreds and thousands they spell whole numbers with
+type Numerals = ([char; 10], Option<[char; 3]>);
+
+/// Replace the ASCII digits of a rendered number with the numerals of the
+/// last numeral modifier of the section: `t` for Thai digits, `[DBNum1]` to
+/// `[DBNum3]` for the CJK ones. Modifiers that pick no numerals keep ASCII.
+///
+/// `[DBNum1]` and `[DBNum2]` spell out the whole numbers of General and
+/// number sections with their units, 105 being 一百〇五 or 壹佰零伍. Dates,
+/// times and fractions take one numeral per digit.
+fn native_digits(
+    formatted: &str,
+    modifiers: &[SectionModifier],
+    whole_numbers: bool,
+    locale: &LocaleConfig,
+) -> String {
+    let numerals: Option<Numerals> = modifiers
+        .iter()
+        .filter_map(|modifier| match modifier {
+            SectionModifier::Thai => {
+                Some((['๐', '๑', '๒', '๓', '๔', '๕', '๖', '๗', '๘', '๙'], None))
+            }
+            SectionModifier::DBNum(1) => Some((
+                ['〇', '一', '二', '三', '四', '五', '六', '七', '八', '九'],
+                Some(['十', '百', '千']),
+            )),
+            SectionModifier::DBNum(2) => Some((
+                ['零', '壹', '贰', '叁', '肆', '伍', '陆', '柒', '捌', '玖'],
+                Some(['拾', '佰', '仟']),
+            )),
+            SectionModifier::DBNum(3) => Some((
+                ['０', '１', '２', '３', '４', '５', '６', '７', '８', '９'],
+                None,
+            )),
+            _ => None,
+        })
+        .next_back();
+    match numerals {
+        Some((digits, Some(units))) if whole_numbers => {
+            spell_out(formatted, &digits, &units, locale)
+        }
+        Some((digits, _)) => formatted
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(d) if c.is_ascii_digit() => digits[d as usize],
+                _ => c,
+            })
+            .collect(),
+        None => formatted.to_string(),
     }
 }
 
-/// Apply section wrapper decorations (color, locale, etc.) to a formatted string
-fn apply_section_decorations<T>(
+/// Spell out the whole numbers of a rendered number with units, reading
+/// through grouping separators. Digits after the decimal separator are
+/// written one by one.
+fn spell_out(
     formatted: &str,
-    section: &SectionWrapper<T>,
-    _locale: &LocaleConfig,
-) -> FormatResult {
-    let mut result = formatted.to_string();
+    digits: &[char; 10],
+    units: &[char; 3],
+    locale: &LocaleConfig,
+) -> String {
+    let decimal_sep = locale.decimal_separator.unwrap_or('.');
+    let thousands_sep = locale.thousands_separator.unwrap_or(',');
+    let chars = formatted.chars().collect::<Vec<_>>();
+    let digit_at = |i: usize| {
+        chars
+            .get(i)
+            .and_then(|c| c.to_digit(10).filter(|_| c.is_ascii_digit()))
+    };
 
-    // Apply color if present
-    if let Some(color) = &section.color {
-        // In real implementation, this would apply color formatting
-        // For now, we'll just add a color indicator
-        result = format_with_color(&result, color);
+    let mut result = String::new();
+    let mut decimals = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let Some(d) = digit_at(i) else {
+            decimals = chars[i] == decimal_sep;
+            result.push(chars[i]);
+            i += 1;
+            continue;
+        };
+        if decimals {
+            result.push(digits[d as usize]);
+            i += 1;
+            continue;
+        }
+        let mut number = Vec::new();
+        while let Some(d) = digit_at(i) {
+            number.push(d as usize);
+            i += 1;
+            if chars.get(i) == Some(&thousands_sep) && digit_at(i + 1).is_some() {
+                i += 1;
+            }
+        }
+        result.push_str(&spell_whole(&number, digits, units));
     }
+    result
+}
+
+/// A whole number from its digits, in groups of four marked by 万 and 亿
+fn spell_whole(number: &[usize], digits: &[char; 10], units: &[char; 3]) -> String {
+    let start = number.iter().position(|&d| d != 0);
+    let Some(start) = start else {
+        return digits[0].to_string();
+    };
+    let number = &number[start..];
+
+    let mut result = String::new();
+    let mut zero = false;
+    for (i, &d) in number.iter().enumerate() {
+        let place = number.len() - 1 - i;
+        if d == 0 {
+            zero = true;
+        } else {
+            if zero {
+                result.push(digits[0]);
+                zero = false;
+            }
+            result.push(digits[d]);
+            if let Some(unit) = (place % 4).checked_sub(1) {
+                result.push(units[unit]);
+            }
+        }
+        // a group ends, named if any of its digits is not zero
+        if place > 0 && place.is_multiple_of(4) {
+            let group = &number[i.saturating_sub(3)..=i];
+            if group.iter().any(|&d| d != 0) {
+                if place % 8 == 4 {
+                    result.push('万');
+                }
+                result.extend(std::iter::repeat_n('亿', place / 8));
+            }
+        }
+    }
+    result
+}
+
+/// Format a number as General, shrinking it to the column width if needed
+fn format_general_fit(value: f64, locale: &LocaleConfig) -> FormatResult {
+    match locale.column_width {
+        Some(width) => match format_general_in_width(value, width, locale) {
+            Some(formatted) => Ok(formatted),
+            None => locale.overflow_value(value, "value does not fit the column"),
+        },
+        None => Ok(format_general(value, locale)),
+    }
+}
+
+/// Format a number with a text section, where `@` and `General` stand for
+/// the General rendering of the value. In a narrow column that rendering is
+/// shrunk to leave room for the literals around it.
+fn format_text_with_general(
+    value: f64,
+    text: &NFText,
+    locale: &LocaleConfig,
+) -> Result<CellText, FormatError> {
+    let general = format_general(value, locale);
+    let formatted = format_text(&general, text, locale)?;
+    let formatted_width = formatted.width();
+
+    let placeholders = text
+        .elements
+        .iter()
+        .filter(|e| matches!(e, TextFormatElement::AtPlaceholder | TextFormatElement::General))
+        .count();
+    let Some(width) = locale.column_width else {
+        return Ok(formatted);
+    };
+    if placeholders == 0 || formatted_width <= width {
+        return Ok(formatted);
+    }
+
+    let literal_width = formatted_width - placeholders * general.chars().count();
+    let shrunk = width
+        .checked_sub(literal_width)
+        .and_then(|room| format_general_in_width(value, room / placeholders, locale));
+    match shrunk {
+        Some(general) => format_text(&general, text, locale),
+        None => Ok(formatted),
+    }
+}
+
+/// Numbers never spill into the next cell: when the rendered value is wider
+/// than the column, Excel fills the cell with `#` instead
+fn fit_column(value: f64, cell: FormattedCell, locale: &LocaleConfig) -> CellResult {
+    match locale.column_width {
+        Some(width) if cell.width() > width => Ok(number_cell(
+            locale.overflow_value(value, "value does not fit the column")?,
+        )),
+        _ => Ok(cell),
+    }
+}
+
+/// Apply section wrapper decorations (color, locale, etc.) to a formatted cell
+fn apply_section_decorations<T>(
+    mut cell: FormattedCell,
+    section: &SectionWrapper<T>,
+    _locale: &LocaleConfig,
+) -> CellResult {
+    // Renderers decide how to show the color
+    cell.color = section.color;
 
     // Apply locale if present
     if let Some(locale_id) = &section.locale {
         // In real implementation, this would apply locale-specific formatting
         // For now, we'll just add the currency symbol if present
         if !locale_id.currency_symbol.is_empty() {
-            result = format!("{}{}", locale_id.currency_symbol, result);
+            cell.parts
+                .insert(0, CellPart::Text(locale_id.currency_symbol.clone()));
         }
     }
 
-    // // Apply Thai year prefix if present
-    // if section.is_thai_prefixed {
-    //     // In real implementation, this would convert to Thai year
-    //     // For now, we'll just add a prefix
-    //     result = format!("[THAI]{}", result);
-    // }
-
-    Ok(result)
+    Ok(cell)
 }
 
 /// Apply section wrapper for text formats
@@ -269,13 +503,13 @@ fn apply_section_wrapper_text(
     value: &str,
     section: &SectionWrapper<NFText>,
     locale: &LocaleConfig,
-) -> FormatResult {
+) -> CellResult {
     let formatted = format_text(value, &section.inner, locale)?;
-    apply_section_decorations(&formatted, section, locale)
+    apply_section_decorations(formatted.into_cell(Alignment::Left), section, locale)
 }
 
 /// Apply formatting for a bare NFText (not wrapped in SectionWrapper)
-fn apply_bare_text_formatting(value: &str, text: NFText, locale: &LocaleConfig) -> FormatResult {
+fn apply_bare_text_formatting(value: &str, text: NFText, locale: &LocaleConfig) -> CellResult {
     let wrapper = SectionWrapper {
         locale: None,
         color: None,
@@ -287,32 +521,11 @@ fn apply_bare_text_formatting(value: &str, text: NFText, locale: &LocaleConfig)
 
 /// Format a string with color
 fn format_with_color(value: &str, color: &NFPartColor) -> String {
-    match color {
-        NFPartColor::Intl(defined_color) => {
-            format!("[{}]{}", format_defined_color(defined_color), value)
-        }
-        NFPartColor::Color(index) => {
-            format!("[Color{}]{}", index, value)
-        }
-    }
-}
-
-/// Format a defined color
-fn format_defined_color(color: &DefinedColor) -> String {
-    match color {
-        DefinedColor::Black => "Black".to_string(),
-        DefinedColor::Blue => "Blue".to_string(),
-        DefinedColor::Cyan => "Cyan".to_string(),
-        DefinedColor::Green => "Green".to_string(),
-        DefinedColor::Magenta => "Magenta".to_string(),
-        DefinedColor::Red => "Red".to_string(),
-        DefinedColor::White => "White".to_string(),
-        DefinedColor::Yellow => "Yellow".to_string(),
-    }
+    format!("{}{}", color, value)
 }
 
 /// Evaluate a condition against a value
-fn evaluate_condition(value: f64, condition: &NFPartCondition) -> bool {
+pub(crate) fn evaluate_condition(value: f64, condition: &NFPartCondition) -> bool {
     match condition.op {
         NFCondOperator::Equal => value == condition.value,
         NFCondOperator::NotEqual => value != condition.value,
@@ -1,18 +1,27 @@
 //! Excel-like number format implementation
 
+mod cell;
 mod datetime_fmt;
 mod error;
+mod general_fmt;
 mod impl_fmt;
 mod number_fmt;
 mod text_fmt;
 mod value;
 
+pub use cell::{Alignment, CellPart, FormattedCell};
 pub use error::{FormatError, FormatResult};
 pub use value::FormatValue;
 
+pub(crate) use datetime_fmt::datetime_to_excel_serial;
+pub(crate) use impl_fmt::evaluate_condition;
+
 use crate::parser::NumfmtParser;
 use crate::types::NumFormat;
 
+/// Excel's standard column fits 8 characters
+const DEFAULT_COLUMN_WIDTH: usize = 8;
+
 /// Locale configuration for number formatting
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct LocaleConfig {
@@ -24,6 +33,44 @@ pub struct LocaleConfig {
     pub date_locale: Option<String>,
     /// Currency symbol
     pub currency_symbol: Option<String>,
+    /// What to show for values a section cannot display
+    pub overflow: OverflowPolicy,
+    /// Width of the cell in characters, if known
+    pub column_width: Option<usize>,
+}
+
+/// How to render a value that a section cannot display, such as a negative
+/// serial in a date section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Fail with `FormatError::OutOfRange`
+    Error,
+    /// Fill the cell with `#`, like Excel does
+    #[default]
+    HashFill,
+    /// Show the value in the General format instead
+    General,
+}
+
+impl LocaleConfig {
+    /// Render a value that does not fit its section according to the
+    /// overflow policy
+    pub(crate) fn overflow_value(&self, value: f64, reason: &str) -> FormatResult {
+        match self.overflow {
+            OverflowPolicy::Error => {
+                Err(FormatError::OutOfRange(format!("{}: {}", reason, value)))
+            }
+            OverflowPolicy::HashFill => {
+                let width = self.column_width.unwrap_or(DEFAULT_COLUMN_WIDTH);
+                Ok("#".repeat(width))
+            }
+            OverflowPolicy::General => match self.column_width {
+                Some(width) => Ok(general_fmt::format_general_in_width(value, width, self)
+                    .unwrap_or_else(|| "#".repeat(width))),
+                None => Ok(general_fmt::format_general(value, self)),
+            },
+        }
+    }
 }
 
 /// Formats a value using the specified format string and optional locale configuration
@@ -37,6 +84,34 @@ pub fn format<T: Into<FormatValue>>(
     format_with_parsed(format_value, &format, locale_config)
 }
 
+/// Formats a value using an already parsed format, such as one returned by
+/// [`NumfmtParser::parse_recovering`]
+pub fn format_parsed<T: Into<FormatValue>>(
+    value: T,
+    format: &NumFormat,
+    locale_config: Option<LocaleConfig>,
+) -> FormatResult {
+    format_with_parsed(value.into(), format, locale_config)
+}
+
+/// Formats a value into a cell, keeping the section color, the alignment,
+/// fills and `_x` blanks for renderers
+pub fn format_cell<T: Into<FormatValue>>(
+    value: T,
+    format_str: &str,
+    locale_config: Option<LocaleConfig>,
+) -> Result<FormattedCell, FormatError> {
+    let format = parse_format_string(format_str)?;
+    let locale = locale_config.unwrap_or_default();
+    match value.into() {
+        FormatValue::Number(num) => impl_fmt::format_cell_with_parsed(num, &format, &locale),
+        FormatValue::String(s) => impl_fmt::format_string_cell_with_parsed(&s, &format, &locale),
+        FormatValue::Boolean(b) => {
+            impl_fmt::format_cell_with_parsed(if b { 1.0 } else { 0.0 }, &format, &locale)
+        }
+    }
+}
+
 /// Parse a format string into a NumFormat
 fn parse_format_string(format_str: &str) -> Result<NumFormat, FormatError> {
     NumfmtParser::new(format_str)
@@ -106,6 +181,14 @@ mod tests {
         }
     }
 
+    // Helper function to format a number with a format string and locale
+    fn test_format_with(value: f64, format_str: &str, locale: LocaleConfig) -> FormatResult {
+        let format = NumfmtParser::new(format_str)
+            .parse()
+            .map_err(|e| FormatError::ParseError(e.to_string()))?;
+        format_with_parsed(value, &format, Some(locale))
+    }
+
     #[test]
     fn test_basic_number_formats() {
         assert_eq!(test_format(1234.567, "#,##0.00").unwrap(), "1,234.57");
@@ -135,6 +218,18 @@ mod tests {
             test_format(1234.567, "\"总计：\"#,##0.00\" 元\"").unwrap(),
             "总计：1,234.57 元"
         );
+        // Digits beyond the placeholders and values below one keep the text
+        // in front of the number
+        assert_eq!(test_format(1234.0, "\"$\"0").unwrap(), "$1234");
+        assert_eq!(test_format(12.0, "\"ab\"0.0").unwrap(), "ab12.0");
+        assert_eq!(test_format(0.5, "$0.00").unwrap(), "$0.50");
+        assert_eq!(test_format(0.5, "(0.00)").unwrap(), "(0.50)");
+        assert_eq!(test_format(0.5, "00.0").unwrap(), "00.5");
+        assert_eq!(test_format(0.999, "0").unwrap(), "1");
+        assert_eq!(test_format(1234567.0, "000-00-0000").unwrap(), "001-23-4567");
+        assert_eq!(test_format(12345.678, "\"$\"#,##0").unwrap(), "$12,346");
+        assert_eq!(test_format(0.4, "0000.00").unwrap(), "0000.40");
+        assert_eq!(test_format(0.0, "0 \"kg\"").unwrap(), "0 kg");
     }
 
     #[test]
@@ -241,8 +336,16 @@ mod tests {
             test_format(excel_time, "h:mm:ss AM/PM").unwrap(),
             "3:31:45 PM"
         );
+        assert_eq!(test_format(excel_time, "h:mm am/pm").unwrap(), "3:31 PM");
+        assert_eq!(test_format(excel_time, "h:mm A/P").unwrap(), "3:31 P");
+        assert_eq!(test_format(excel_time, "h:mm a/p").unwrap(), "3:31 p");
+        assert_eq!(test_format(0.25, "h:mm a/P").unwrap(), "6:00 a");
         assert_eq!(test_format(excel_time, "[h]:mm:ss").unwrap(), "15:31:45");
         assert_eq!(test_format(excel_time, "mm:ss").unwrap(), "31:45");
+        assert_eq!(test_format(excel_time, "m:ss").unwrap(), "31:45");
+        assert_eq!(test_format(excel_time, "h \"h\" m").unwrap(), "15 h 31");
+        assert_eq!(test_format(excel_time, "[h]:mm").unwrap(), "15:31");
+        assert_eq!(test_format(excel_time, "m\"min\"ss").unwrap(), "31min45");
         assert_eq!(
             test_format(excel_time, "h\" 小时 \"m\" 分钟\"").unwrap(),
             "15 小时 31 分钟"
@@ -258,6 +361,10 @@ mod tests {
             test_format(excel_datetime, "yyyy-mm-dd hh:mm:ss").unwrap(),
             "2023-05-15 15:31:45"
         );
+        assert_eq!(
+            test_format(excel_datetime, "dd/mm/yyyy m:ss").unwrap(),
+            "15/05/2023 31:45"
+        );
         assert_eq!(
             test_format(excel_datetime, "m/d/yyyy h:mm AM/PM").unwrap(),
             "5/15/2023 3:31 PM"
@@ -268,6 +375,205 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_section_modifiers() {
+        // 2023-05-15
+        let excel_date = 45061.0;
+
+        assert_eq!(test_format(1234, "t#,##0").unwrap(), "๑,๒๓๔");
+        assert_eq!(test_format(2023, "[DBNum1]0").unwrap(), "二千〇二十三");
+        assert_eq!(test_format(105, "[DBNum2]General").unwrap(), "壹佰零伍");
+        assert_eq!(test_format(105, "[DBNum1]General").unwrap(), "一百〇五");
+        assert_eq!(test_format(10020.5, "[DBNum1]#,##0.0").unwrap(), "一万〇二十.五");
+        assert_eq!(test_format(300000001, "[DBNum2]0").unwrap(), "叁亿零壹");
+        assert_eq!(test_format(0, "[DBNum1]0").unwrap(), "〇");
+        // literal text keeps its digits
+        assert_eq!(test_format(5, "[DBNum3]\"Q1 \"0").unwrap(), "Q1 ５");
+        assert_eq!(test_format(12, "[DBNum1]0\"号\"").unwrap(), "一十二号");
+        assert_eq!(test_format(-3.5, "[DBNum3]0.0").unwrap(), "-３.５");
+        assert_eq!(test_format(excel_date, "[DBNum1]yyyy").unwrap(), "二〇二三");
+        assert_eq!(test_format(42, "[ENG]0").unwrap(), "42");
+        assert_eq!(test_format(42, "[NatNum1]0").unwrap(), "42");
+
+        assert_eq!(test_format(excel_date, "[TWN]e/mm/dd").unwrap(), "112/05/15");
+        assert_eq!(test_format(excel_date, "[TWN]ggge").unwrap(), "中華民國112");
+        assert_eq!(test_format(excel_date, "[JPN]ggge").unwrap(), "令和5");
+        assert_eq!(test_format(excel_date, "[JPN]gee/mm/dd").unwrap(), "R05/05/15");
+        // 1989-01-07 is the last day of Shōwa
+        assert_eq!(test_format(32515, "[JPN]ggge").unwrap(), "昭和64");
+        assert_eq!(test_format(32516, "[JPN]ggge").unwrap(), "平成1");
+
+        // 2000-01-01 is 24 Ramadan 1420 in the tabular Islamic calendar
+        assert_eq!(test_format(36526, "[HIJ]yyyy-mm-dd").unwrap(), "1420-09-24");
+        assert_eq!(test_format(36526, "[HIJ]d mmmm yyyy").unwrap(), "24 Ramadan 1420");
+    }
+
+    #[test]
+    fn test_subsecond_formats() {
+        let excel_date = 45061.0;
+        // 15:31:45.678
+        let time = (15.0 * 3600.0 + 31.0 * 60.0 + 45.678) / 86400.0;
+
+        assert_eq!(test_format(time, "h:mm:ss.000").unwrap(), "15:31:45.678");
+        assert_eq!(test_format(time, "h:mm:ss.00").unwrap(), "15:31:45.68");
+        assert_eq!(test_format(time, "h:mm:ss.0").unwrap(), "15:31:45.7");
+        assert_eq!(test_format(time, "mm:ss.0").unwrap(), "31:45.7");
+        assert_eq!(test_format(time, "h:mm:ss").unwrap(), "15:31:46");
+
+        // 10:59:59.6 rounds to whole seconds before minutes are read
+        let time = (10.0 * 3600.0 + 59.0 * 60.0 + 59.6) / 86400.0;
+        assert_eq!(test_format(time, "h:mm").unwrap(), "11:00");
+        assert_eq!(test_format(time, "h:mm:ss.0").unwrap(), "10:59:59.6");
+
+        // carry runs all the way into the next day
+        let almost_midnight = excel_date + 86399.9996 / 86400.0;
+        assert_eq!(
+            test_format(almost_midnight, "yyyy-mm-dd hh:mm:ss.000").unwrap(),
+            "2023-05-16 00:00:00.000"
+        );
+        let almost_midnight = excel_date + 86399.96 / 86400.0;
+        assert_eq!(
+            test_format(almost_midnight, "yyyy-mm-dd hh:mm:ss.00").unwrap(),
+            "2023-05-15 23:59:59.96"
+        );
+        assert_eq!(
+            test_format(almost_midnight, "yyyy-mm-dd hh:mm:ss.0").unwrap(),
+            "2023-05-16 00:00:00.0"
+        );
+    }
+
+    #[test]
+    fn test_elapsed_time_formats() {
+        // 1 day 12:30:15
+        let duration = 1.0 + (12.0 * 3600.0 + 30.0 * 60.0 + 15.0) / 86400.0;
+        assert_eq!(test_format(duration, "[h]:mm:ss").unwrap(), "36:30:15");
+        assert_eq!(test_format(duration, "[mm]:ss").unwrap(), "2190:15");
+        assert_eq!(test_format(duration, "[ss]").unwrap(), "131415");
+        assert_eq!(test_format(duration, "[h]:mm:ss.0").unwrap(), "36:30:15.0");
+    }
+
+    #[test]
+    fn test_out_of_range_dates() {
+        // hash fill is the default, sized to the column when known
+        assert_eq!(test_format(-1.0, "yyyy-mm-dd").unwrap(), "########");
+        assert_eq!(test_format(2958466.0, "yyyy-mm-dd").unwrap(), "########");
+        assert_eq!(test_format(f64::NAN, "h:mm").unwrap(), "########");
+        assert_eq!(test_format(f64::INFINITY, "[h]:mm").unwrap(), "########");
+        assert_eq!(test_format(-0.5, "[h]:mm").unwrap(), "########");
+        assert_eq!(
+            test_format(2958465.0, "yyyy-mm-dd").unwrap(),
+            "9999-12-31"
+        );
+        let narrow = LocaleConfig {
+            column_width: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(test_format_with(-1.0, "yyyy-mm-dd", narrow).unwrap(), "#####");
+
+        let error = LocaleConfig {
+            overflow: OverflowPolicy::Error,
+            ..Default::default()
+        };
+        assert!(matches!(
+            test_format_with(-1.0, "h:mm:ss", error.clone()),
+            Err(FormatError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            test_format_with(3e6, "d/m/yyyy", error.clone()),
+            Err(FormatError::OutOfRange(_))
+        ));
+        assert_eq!(test_format_with(0.5, "h:mm", error).unwrap(), "12:00");
+
+        let general = LocaleConfig {
+            overflow: OverflowPolicy::General,
+            ..Default::default()
+        };
+        assert_eq!(test_format_with(-1.5, "yyyy-mm-dd", general).unwrap(), "-1.5");
+    }
+
+    #[test]
+    fn test_column_width() {
+        let width = |column_width: usize| LocaleConfig {
+            column_width: Some(column_width),
+            ..Default::default()
+        };
+
+        // General drops decimals, then switches to scientific notation
+        assert_eq!(test_format_with(1234.5678, "General", width(6)).unwrap(), "1234.6");
+        assert_eq!(test_format_with(1234.5678, "General", width(4)).unwrap(), "1235");
+        assert_eq!(test_format_with(1234.5678, "General", width(3)).unwrap(), "###");
+        assert_eq!(test_format_with(-1234.5678, "General", width(5)).unwrap(), "-1235");
+        assert_eq!(test_format_with(123456789.0, "General", width(8)).unwrap(), "1.23E+08");
+        assert_eq!(test_format_with(0.5, "General", width(8)).unwrap(), "0.5");
+
+        // explicit formats never shrink
+        assert_eq!(test_format_with(1234567.0, "#,##0.00", width(5)).unwrap(), "#####");
+        assert_eq!(test_format_with(12.0, "0.00", width(5)).unwrap(), "12.00");
+        assert_eq!(test_format_with(45000.0, "yyyy-mm-dd", width(5)).unwrap(), "#####");
+
+        // General inside literals shrinks to leave room for them
+        assert_eq!(
+            test_format_with(1234.5678, "General\" kg\"", width(7)).unwrap(),
+            "1235 kg"
+        );
+
+        let error = LocaleConfig {
+            overflow: OverflowPolicy::Error,
+            column_width: Some(3),
+            ..Default::default()
+        };
+        assert!(matches!(
+            test_format_with(1234.0, "0", error),
+            Err(FormatError::OutOfRange(_))
+        ));
+
+        // text values are allowed to spill over
+        assert_eq!(
+            format("a long piece of text", "@", Some(width(3))).unwrap(),
+            "a long piece of text"
+        );
+    }
+
+    #[test]
+    fn test_format_cell() {
+        let cell = format_cell(-12.0, "0_);[Red](00)", None).unwrap();
+        assert_eq!(cell.parts, vec![CellPart::Text("(12)".to_string())]);
+        assert_eq!(
+            cell.color,
+            Some(crate::types::NFPartColor::Intl(crate::types::DefinedColor::Red))
+        );
+        assert_eq!(cell.alignment, Alignment::Right);
+
+        let cell = format_cell(12.0, "0_)", None).unwrap();
+        assert_eq!(
+            cell.parts,
+            vec![CellPart::Text("12".to_string()), CellPart::Space(')')]
+        );
+        assert_eq!(cell.width(), 3);
+
+        let cell = format_cell("Hello", "*-@", None).unwrap();
+        assert_eq!(cell.alignment, Alignment::Left);
+        assert_eq!(cell.fill(), Some('-'));
+        assert_eq!(cell.to_text(Some(8)), "---Hello");
+
+        // private use characters in the value are text like any other
+        let cell = format_cell("a\u{F8F0}b\u{F8F1}c", "@_)", None).unwrap();
+        assert_eq!(
+            cell.parts,
+            vec![
+                CellPart::Text("a\u{F8F0}b\u{F8F1}c".to_string()),
+                CellPart::Space(')')
+            ]
+        );
+
+        // with a known column width the plain output is filled to it
+        let locale = LocaleConfig {
+            column_width: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(test_format_with(1.5, "*-General", locale).unwrap(), "-----1.5");
+    }
+
     #[test]
     fn test_fraction_formats() {
         assert_eq!(test_format(0.5, "# ??/??").unwrap(), "  1/2 ");
@@ -302,6 +608,8 @@ mod tests {
     fn test_color_formats() {
         // Color formats
         assert_eq!(test_format(123.45, "[Red]#,##0.00").unwrap(), "[Red]123.45");
+        assert_eq!(test_format(123.45, "[red]#,##0.00").unwrap(), "[Red]123.45");
+        assert_eq!(test_format(123.45, "[BLUE]#,##0.00").unwrap(), "[Blue]123.45");
         assert_eq!(
             test_format(123.45, "[Blue]#,##0.00").unwrap(),
             "[Blue]123.45"
@@ -357,6 +665,38 @@ mod tests {
         assert_eq!(test_format(1234.567, "#,##0.00\\-").unwrap(), "1,234.57-");
     }
     
+    #[test]
+    fn test_general_formats() {
+        assert_eq!(test_format(12345678901.0, "General").unwrap(), "12345678901");
+        assert_eq!(test_format(123456789012.0, "General").unwrap(), "1.23457E+11");
+        assert_eq!(test_format(1234.567890123, "General").unwrap(), "1234.56789");
+        assert_eq!(test_format(0.1234567890123, "General").unwrap(), "0.123456789");
+        assert_eq!(test_format(0.00001, "General").unwrap(), "0.00001");
+        assert_eq!(test_format(0.0000123456789, "General").unwrap(), "1.23457E-05");
+        assert_eq!(test_format(-1234.5, "General").unwrap(), "-1234.5");
+    }
+
+    #[test]
+    fn test_general_with_literals() {
+        assert_eq!(test_format(42, "\"Total: \"General").unwrap(), "Total: 42");
+        assert_eq!(test_format(-42, "\"Total: \"General").unwrap(), "Total: -42");
+        assert_eq!(test_format(1.5, "General\" kg\"").unwrap(), "1.5 kg");
+        assert_eq!(test_format(1.5, "General_)").unwrap(), "1.5 ");
+        assert_eq!(test_format(1.5, "*-General").unwrap(), "-----1.5");
+        assert_eq!(
+            test_format(5, "[Red]General;[Blue]-General").unwrap(),
+            "[Red]5"
+        );
+        assert_eq!(
+            test_format(-5, "[Red]General;[Blue]-General").unwrap(),
+            "[Blue]-5"
+        );
+        assert_eq!(
+            test_format("kg", "0;-0;0;General\" units\"").unwrap(),
+            "kg units"
+        );
+    }
+
     #[test]
     fn test_advanced_formats() {
         // Braces and other special characters
@@ -1,15 +1,19 @@
 //! Number formatter implementation
 
 use crate::formatter::LocaleConfig;
+use crate::formatter::cell::CellText;
 use crate::formatter::error::{FormatError, FormatResult};
 use crate::types::common::*;
-use crate::types::datetime::*;
 use crate::types::elements::*;
 use crate::types::number::*;
 
 /// Format a number according to a NFNumber format specification
-pub fn format_nf_number(value: f64, format: &NFNumber, locale: &LocaleConfig) -> FormatResult {
-    let mut result = String::new();
+pub fn format_nf_number(
+    value: f64,
+    format: &NFNumber,
+    locale: &LocaleConfig,
+) -> Result<CellText, FormatError> {
+    let mut result = CellText::new();
     let decimal_sep = locale.decimal_separator.unwrap_or('.');
     let thousands_sep = locale.thousands_separator.unwrap_or(',');
 
@@ -30,7 +34,7 @@ pub fn format_nf_number(value: f64, format: &NFNumber, locale: &LocaleConfig) ->
         let (mantissa, exponent) = scientific_decompose(formatting_value);
         let mantissa_str =
             format_number_part(mantissa, &format.num_part, decimal_sep, thousands_sep)?;
-        result.push_str(&mantissa_str);
+        result.append(mantissa_str);
 
         result.push('E');
         if matches!(sign, Sign::Plus) || exponent < 0 {
@@ -41,7 +45,7 @@ pub fn format_nf_number(value: f64, format: &NFNumber, locale: &LocaleConfig) ->
 
         let exp_str =
             format_number_part(exponent.abs() as f64, exp_part, decimal_sep, thousands_sep)?;
-        result.push_str(&exp_str);
+        result.append(exp_str);
     } else {
         result = format_number_part(
             formatting_value,
@@ -59,7 +63,7 @@ pub fn format_nf_number(value: f64, format: &NFNumber, locale: &LocaleConfig) ->
     // If we have parentheses in the format, the number is already wrapped in parentheses
     // so we don't need to add a negative sign
     if is_negative && !has_parentheses {
-        result = format!("-{}", result);
+        result.prepend_str("-");
     }
 
     Ok(result)
@@ -87,8 +91,12 @@ fn is_parenthesized_format(format_parts: &[DigitPosOrOther<Percent>]) -> bool {
 }
 
 /// Format a fraction according to NFFraction format specification
-pub fn format_fraction(value: f64, format: &NFFraction, locale: &LocaleConfig) -> FormatResult {
-    let mut result = String::new();
+pub fn format_fraction(
+    value: f64,
+    format: &NFFraction,
+    locale: &LocaleConfig,
+) -> Result<CellText, FormatError> {
+    let mut result = CellText::new();
 
     // Extract integer and fractional parts
     let integer_part = value.trunc();
@@ -102,7 +110,7 @@ pub fn format_fraction(value: f64, format: &NFFraction, locale: &LocaleConfig) -
             locale.decimal_separator.unwrap_or('.'),
             locale.thousands_separator.unwrap_or(','),
         )?;
-        result.push_str(&int_str);
+        result.append(int_str);
         result.push(' '); // Space between integer and fraction
     }
 
@@ -129,13 +137,13 @@ pub fn format_fraction(value: f64, format: &NFFraction, locale: &LocaleConfig) -
     if !format.ampm_part.is_empty() {
         result.push(' ');
         for ampm in &format.ampm_part {
-            result.push_str(&format_ampm(ampm, value as i64 >= 12));
+            result.push_str(&ampm.marker(value as i64 >= 12));
         }
     }
 
     // Handle negative sign if integer part isn't formatted separately
     if value < 0.0 && format.integer_part.is_none() {
-        result = format!("-{}", result);
+        result.prepend_str("-");
     }
 
     Ok(result)
@@ -200,7 +208,7 @@ fn format_number_part(
     format_parts: &[DigitPosOrOther<Percent>],
     decimal_sep: char,
     thousands_sep: char,
-) -> FormatResult {
+) -> Result<CellText, FormatError> {
     let mut int_format = Vec::new();
     let mut dec_format = Vec::new();
     let mut has_decimal = false;
@@ -263,103 +271,62 @@ fn format_number_part(
         }
     }
 
-    let mut int_result = String::new();
-    let int_str = int_value.to_string();
-
-    // not enough digits to show full number, add extra digits
-    // should calculate the thousands separator
-    if int_digits < int_str.len() && int_digits > 0 {
-        for i in 0..(int_str.len() - int_digits) {
-            int_result.push(int_str.chars().nth(i).unwrap());
-            if use_thousands && (int_str.len() - i - 1) % 3 == 0 && i < int_str.len() - 1 {
-                int_result.push(thousands_sep);
-            }
-        }
-    }
+    let mut int_result = CellText::new();
+    // A zero integer part has no digits, only `0` placeholders show it
+    let int_str: Vec<char> = if int_value == 0 {
+        Vec::new()
+    } else {
+        int_value.unsigned_abs().to_string().chars().collect()
+    };
 
     let mut digit_pos = 0;
-
-    if value.abs() < 1.0 {
-        match int_format.last() {
-            Some(DigitPosOrOther::Digit(DigitPos::Digit(placeholder))) => match placeholder {
-                NumPlaceholder::Zero => int_result.push('0'),
-                NumPlaceholder::Space => int_result.push(' '),
-                NumPlaceholder::Lazy => {}
-            },
-            Some(DigitPosOrOther::LiteralString(s)) => {
-                int_result.push_str(s);
-            }
-            Some(DigitPosOrOther::LiteralCharSpace(_c)) => {
-                // width should be same as `c`, however as string
-                // we can only push one space
-                int_result.push(' ');
-            }
-            Some(DigitPosOrOther::FillChar(c)) => {
-                int_result.push(*c);
-            }
-            Some(DigitPosOrOther::EscapedChar(c)) => {
-                int_result.push(*c);
-            }
-            _ => {}
-        }
-    } else {
-        for part in &int_format {
-            match part {
-                DigitPosOrOther::Digit(DigitPos::Digit(placeholder)) => {
-                    // rtl
-                    let pos = int_digits - digit_pos - 1;
-                    let digit_idx = int_str.len() as isize - pos as isize - 1;
-
-                    if digit_idx >= 0 && digit_idx < int_str.len() as isize {
-                        if int_str.len() < int_digits
-                            || digit_idx as usize >= int_str.len() - int_digits
-                        {
-                            int_result.push(int_str.chars().nth(digit_idx as usize).unwrap());
-                        }
-                        if use_thousands
-                            && (int_str.len() - digit_idx as usize - 1) % 3 == 0
-                            && digit_idx < int_str.len() as isize - 1
-                        {
-                            int_result.push(thousands_sep);
-                        }
-                    } else if digit_idx < 0 {
-                        // 如果数字位数不够，用 0 补足
+    for part in &int_format {
+        match part {
+            DigitPosOrOther::Digit(DigitPos::Digit(placeholder)) => {
+                // Positions counted from the units digit; the first placeholder
+                // also shows the digits that overflow the format
+                let pos = int_digits - digit_pos - 1;
+                let highest = if digit_pos == 0 {
+                    pos.max(int_str.len().saturating_sub(1))
+                } else {
+                    pos
+                };
+                for p in (pos..=highest).rev() {
+                    if p < int_str.len() {
+                        int_result.push(int_str[int_str.len() - 1 - p]);
+                    } else {
                         match placeholder {
-                            NumPlaceholder::Zero => {
-                                int_result.push('0');
-                                if use_thousands
-                                    && (int_str.len() - digit_idx as usize - 1) % 3 == 0
-                                    && digit_idx < int_str.len() as isize - 1
-                                {
-                                    int_result.push(thousands_sep);
-                                }
-                            }
+                            NumPlaceholder::Zero => int_result.push('0'),
                             NumPlaceholder::Space => int_result.push(' '),
                             NumPlaceholder::Lazy => {}
                         }
+                        if *placeholder != NumPlaceholder::Zero {
+                            continue;
+                        }
+                    }
+                    if use_thousands && p > 0 && p.is_multiple_of(3) {
+                        int_result.push(thousands_sep);
                     }
-                    digit_pos += 1;
-                }
-                DigitPosOrOther::LiteralString(s) => {
-                    int_result.push_str(s);
-                }
-                DigitPosOrOther::LiteralCharSpace(_c) => {
-                    // width should be same as `c`, however as string
-                    // we can only push one space
-                    int_result.push(' ');
-                }
-                DigitPosOrOther::FillChar(c) => {
-                    int_result.push(*c);
-                }
-                DigitPosOrOther::EscapedChar(c) => {
-                    int_result.push(*c);
                 }
-                _ => {}
+                digit_pos += 1;
+            }
+            DigitPosOrOther::LiteralString(s) => {
+                int_result.push_literal(s);
+            }
+            DigitPosOrOther::LiteralCharSpace(c) => {
+                int_result.push_space(*c);
             }
+            DigitPosOrOther::FillChar(c) => {
+                int_result.push_fill(*c);
+            }
+            DigitPosOrOther::EscapedChar(c) => {
+                int_result.push_literal(&c.to_string());
+            }
+            _ => {}
         }
     }
 
-    let mut dec_result = String::new();
+    let mut dec_result = CellText::new();
     let dec_str = if dec_digits > 0 && frac_value != 0.0 {
         format!("{:.*}", dec_digits, frac_value)
             .trim_start_matches("0")
@@ -385,28 +352,25 @@ fn format_number_part(
                 digit_pos += 1;
             }
             DigitPosOrOther::LiteralString(s) => {
-                dec_result.push_str(s);
+                dec_result.push_literal(s);
             }
-            DigitPosOrOther::LiteralCharSpace(_c) => {
-                // the width should be same as `c`, however as string
-                // we can only push one space
-                dec_result.push(' ');
+            DigitPosOrOther::LiteralCharSpace(c) => {
+                dec_result.push_space(*c);
             }
             DigitPosOrOther::FillChar(c) => {
-                dec_result.push(*c);
+                dec_result.push_fill(*c);
             }
             DigitPosOrOther::EscapedChar(c) => {
-                dec_result.push(*c);
+                dec_result.push_literal(&c.to_string());
             }
             _ => {}
         }
     }
 
-    let mut result = String::new();
-    result.push_str(&int_result);
+    let mut result = int_result;
     if has_decimal && (!dec_result.is_empty() || dec_digits > 0) {
         result.push(decimal_sep);
-        result.push_str(&dec_result);
+        result.append(dec_result);
     }
 
     Ok(result)
@@ -505,22 +469,3 @@ fn scientific_decompose(value: f64) -> (f64, i32) {
     (mantissa, exp)
 }
 
-/// Format AM/PM indicator
-fn format_ampm(ampm: &AmPm, is_pm: bool) -> String {
-    match ampm {
-        AmPm::Full => {
-            if is_pm {
-                "PM".to_string()
-            } else {
-                "AM".to_string()
-            }
-        }
-        AmPm::Simple => {
-            if is_pm {
-                "P".to_string()
-            } else {
-                "A".to_string()
-            }
-        }
-    }
-}
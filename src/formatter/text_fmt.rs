@@ -1,13 +1,18 @@
 //! Text formatter implementation
 
 use crate::formatter::LocaleConfig;
-use crate::formatter::error::FormatResult;
+use crate::formatter::cell::CellText;
+use crate::formatter::error::FormatError;
 use crate::types::elements::*;
-use crate::types::{AmPm, NFText};
+use crate::types::NFText;
 
 /// Format text according to NFText format specification
-pub fn format_text(value: &str, format: &NFText, _locale: &LocaleConfig) -> FormatResult {
-    let mut result = String::new();
+pub fn format_text(
+    value: &str,
+    format: &NFText,
+    _locale: &LocaleConfig,
+) -> Result<CellText, FormatError> {
+    let mut result = CellText::new();
 
     for element in &format.elements {
         match element {
@@ -15,53 +20,32 @@ pub fn format_text(value: &str, format: &NFText, _locale: &LocaleConfig) -> Form
                 // @ is the placeholder for the text value
                 result.push_str(value);
             }
+            TextFormatElement::General => {
+                // numeric values arrive here already rendered as General
+                result.push_str(value);
+            }
             TextFormatElement::AmPm(ampm) => {
                 // In text format, AM/PM usually doesn't make sense but we can handle it
                 // For consistency, we'll render it as is
-                result.push_str(&format_ampm(ampm, false)); // Default to AM
+                result.push_str(&ampm.marker(false)); // Default to AM
             }
-            TextFormatElement::LiteralCharSpace(_c) => {
-                // the width should be same as c but we can't handle it in string
-                result.push(' ');
+            TextFormatElement::LiteralCharSpace(c) => {
+                result.push_space(*c);
             }
             TextFormatElement::LiteralString(s) => {
-                result.push_str(s);
+                result.push_literal(s);
             }
             TextFormatElement::FillChar(c) => {
-                // repeat the character 5 times, should be able to configure the number of times
-                const COUNT: usize = 5;
-                for _ in 0..COUNT {
-                    result.push(*c);
-                }
+                result.push_fill(*c);
             }
             TextFormatElement::EscapedChar(c) => {
-                result.push(*c);
+                result.push_literal(&c.to_string());
             }
             TextFormatElement::BareChar(c) => {
-                result.push(*c);
+                result.push_literal(&c.to_string());
             }
         }
     }
 
     Ok(result)
 }
-
-/// Format AM/PM indicator
-fn format_ampm(ampm: &AmPm, is_pm: bool) -> String {
-    match ampm {
-        AmPm::Full => {
-            if is_pm {
-                "PM".to_string()
-            } else {
-                "AM".to_string()
-            }
-        }
-        AmPm::Simple => {
-            if is_pm {
-                "P".to_string()
-            } else {
-                "A".to_string()
-            }
-        }
-    }
-}
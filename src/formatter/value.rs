@@ -1,7 +1,7 @@
 //! Format value types
 
 /// Value that can be formatted
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FormatValue {
     /// Number value
     Number(f64),
@@ -0,0 +1,628 @@
+//! Guessing a format from an example of what it should show
+//!
+//! The example is read as a number, a fraction, a date or a time, and every
+//! format that could have shown it is formatted again with the value read
+//! from it. Only the formats that give the example back are kept, the most
+//! likely first.
+//!
+//! ```
+//! use luscinia::{LocaleConfig, infer_format};
+//!
+//! let inferred = infer_format("$1,234.50", &LocaleConfig::default());
+//! assert_eq!(inferred[0].format.to_string(), "$#,##0.00");
+//! assert_eq!(inferred[0].value, 1234.5);
+//! ```
+
+use crate::builder::{
+    CurrencyPosition, DateFormat, DateOrder, FractionFormat, MonthStyle, NegativeStyle,
+    NumberFormat, TimeFormat,
+};
+use crate::dialog::{DatePreset, TimePreset};
+use crate::formatter::{LocaleConfig, datetime_to_excel_serial, format_parsed};
+use crate::parser::NumfmtParser;
+use crate::types::{CURRENCY_SYMBOLS, NumFormat};
+use chrono::{Datelike, NaiveDate};
+
+pub(crate) const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+pub(crate) const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// A format that shows the example, along with the value it was read as
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredFormat {
+    pub format: NumFormat,
+    /// The number, or serial date, the example shows
+    pub value: f64,
+}
+
+/// Formats that show `example` for the value it is read as, with the
+/// separators of `locale`. The first candidate is the most likely one; an
+/// empty list means the example was not understood.
+///
+/// Dates without a year, such as `14-Mar`, are read in the current year.
+pub fn infer_format(example: &str, locale: &LocaleConfig) -> Vec<InferredFormat> {
+    let example = example.trim();
+    let fractions = fraction_candidates(example);
+    let dates = datetime_candidates(example);
+    let mut candidates = number_candidates(example, locale);
+    // `1 3/4` is a fraction, but `3/4` is typed as a date
+    if example.contains(' ') {
+        candidates.extend(fractions);
+        candidates.extend(dates);
+    } else {
+        candidates.extend(dates);
+        candidates.extend(fractions);
+    }
+
+    let mut inferred: Vec<InferredFormat> = Vec::new();
+    for (format, value) in candidates {
+        let shown = format_parsed(value, &format, Some(locale.clone()));
+        if shown.as_deref() != Ok(example) {
+            continue;
+        }
+        if !inferred.iter().any(|known| known.format == format) {
+            inferred.push(InferredFormat { format, value });
+        }
+    }
+    inferred
+}
+
+fn preset(format_str: &str) -> NumFormat {
+    NumfmtParser::new(format_str)
+        .parse()
+        .expect("dialog presets are valid formats")
+}
+
+/// The currency symbol `text` starts with, from the locale or a known
+/// symbol
+fn leading_currency<'a>(text: &'a str, locale: &LocaleConfig) -> Option<&'a str> {
+    if let Some(symbol) = &locale.currency_symbol
+        && !symbol.is_empty()
+        && text.starts_with(symbol.as_str())
+    {
+        return Some(&text[..symbol.len()]);
+    }
+    let first = text.chars().next()?;
+    CURRENCY_SYMBOLS
+        .contains(&first)
+        .then(|| &text[..first.len_utf8()])
+}
+
+fn trailing_currency<'a>(text: &'a str, locale: &LocaleConfig) -> Option<&'a str> {
+    if let Some(symbol) = &locale.currency_symbol
+        && !symbol.is_empty()
+        && text.ends_with(symbol.as_str())
+    {
+        return Some(&text[text.len() - symbol.len()..]);
+    }
+    let last = text.chars().next_back()?;
+    CURRENCY_SYMBOLS
+        .contains(&last)
+        .then(|| &text[text.len() - last.len_utf8()..])
+}
+
+/// A number as written, with the signs, symbols and separators around it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NumberReading {
+    pub(crate) value: f64,
+    pub(crate) negative: Option<NegativeStyle>,
+    /// The symbol as written, with the spaces between it and the number
+    /// when it comes first
+    pub(crate) currency: Option<(String, CurrencyPosition)>,
+    pub(crate) percent: bool,
+    pub(crate) scientific: bool,
+    pub(crate) decimals: usize,
+    pub(crate) grouped: bool,
+}
+
+/// Read a number written with the separators of `locale`, such as
+/// `-1,234.5`, `(12%)`, `$1,200`, `12 €` or `1.5E+05`. Thousands have to be
+/// grouped by three.
+pub(crate) fn read_number(text: &str, locale: &LocaleConfig) -> Option<NumberReading> {
+    let decimal_separator = locale.decimal_separator.unwrap_or('.');
+    let thousands_separator = locale.thousands_separator.unwrap_or(',');
+
+    let (negative, text) = if let Some(inner) = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+    {
+        (Some(NegativeStyle::Parens), inner)
+    } else if let Some(text) = text.strip_prefix('-') {
+        (Some(NegativeStyle::Minus), text)
+    } else {
+        (None, text)
+    };
+
+    let mut currency = None;
+    let mut text = text;
+    if let Some(symbol) = leading_currency(text, locale) {
+        let number = text[symbol.len()..].trim_start();
+        currency = Some((
+            text[..text.len() - number.len()].to_string(),
+            CurrencyPosition::Before,
+        ));
+        text = number;
+    } else if let Some(symbol) = trailing_currency(text, locale) {
+        // Written after the number, the symbol follows a single space
+        let number = text[..text.len() - symbol.len()].strip_suffix(' ')?;
+        currency = Some((symbol.to_string(), CurrencyPosition::After));
+        text = number;
+    }
+
+    let (text, percent) = match text.strip_suffix('%') {
+        Some(text) => (text, true),
+        None => (text, false),
+    };
+    let (mantissa, exponent) = match text.split_once(['E', 'e']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (text, None),
+    };
+    let (integer, decimals) = match mantissa.split_once(decimal_separator) {
+        Some((integer, decimals)) => (integer, decimals),
+        None => (mantissa, ""),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let mut groups = integer.split(thousands_separator);
+    let first = groups.next()?;
+    let grouped = integer.contains(thousands_separator);
+    if !all_digits(first)
+        || (grouped && first.len() > 3)
+        || !groups.all(|group| group.len() == 3 && all_digits(group))
+        || !(decimals.is_empty() || all_digits(decimals))
+    {
+        return None;
+    }
+    let integer: String = integer
+        .chars()
+        .filter(|&c| c != thousands_separator)
+        .collect();
+    let mut normalized = format!("{}.{}", integer, decimals);
+    if let Some(exponent) = exponent {
+        if !all_digits(exponent.trim_start_matches(['+', '-'])) {
+            return None;
+        }
+        normalized = format!("{}e{}", normalized, exponent);
+    }
+    let mut value = normalized.trim_end_matches('.').parse::<f64>().ok()?;
+    // Too large for a cell, like `1e400`
+    if !value.is_finite() {
+        return None;
+    }
+    if percent {
+        value /= 100.0;
+    }
+    if negative.is_some() {
+        value = -value;
+    }
+    Some(NumberReading {
+        value,
+        negative,
+        currency,
+        percent,
+        scientific: exponent.is_some(),
+        decimals: decimals.len(),
+        grouped,
+    })
+}
+
+/// Numbers, currencies, percentages and scientific notation
+fn number_candidates(example: &str, locale: &LocaleConfig) -> Vec<(NumFormat, f64)> {
+    let Some(number) = read_number(example, locale) else {
+        return Vec::new();
+    };
+    let value = number.value;
+
+    let mut candidates = Vec::new();
+    if number.currency.is_none() && !number.percent && !number.grouped {
+        candidates.push((preset("General"), value));
+    }
+    // Grouping does not show below a thousand, so it is only a second guess
+    let groupings: &[bool] = match (number.grouped, number.scientific) {
+        (true, _) => &[true],
+        (false, false) => &[false, true],
+        (false, true) => &[false],
+    };
+    for &grouping in groupings {
+        let mut format = NumberFormat::decimals(number.decimals as u8)
+            .negative(number.negative.unwrap_or_default());
+        if grouping {
+            format = format.grouping();
+        }
+        if let Some((symbol, position)) = &number.currency {
+            format = format.currency(symbol.clone(), *position);
+        }
+        if number.percent {
+            format = format.percent();
+        }
+        if number.scientific {
+            format = format.scientific();
+        }
+        candidates.push((format.build(), value));
+    }
+    candidates
+}
+
+/// `1 3/4` or `3/4`
+fn fraction_candidates(example: &str) -> Vec<(NumFormat, f64)> {
+    let (negative, text) = match example.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, example),
+    };
+    let (integer, fraction) = match text.split_once(' ') {
+        Some((integer, fraction)) => (Some(integer), fraction.trim_start()),
+        None => (None, text),
+    };
+    let Some((numerator, denominator)) = fraction.split_once('/') else {
+        return Vec::new();
+    };
+    let parse = |s: &str| {
+        (!s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
+            .then(|| s.parse::<u32>().ok())
+            .flatten()
+    };
+    let (Some(numerator), Some(denominator)) = (parse(numerator), parse(denominator)) else {
+        return Vec::new();
+    };
+    let integer = match integer.map(parse) {
+        Some(None) => return Vec::new(),
+        Some(Some(integer)) => integer,
+        None => 0,
+    };
+    if denominator == 0 {
+        return Vec::new();
+    }
+    let mut value = integer as f64 + numerator as f64 / denominator as f64;
+    if negative {
+        value = -value;
+    }
+    let digits = denominator.to_string().len() as u8;
+    vec![
+        (FractionFormat::up_to(digits).build(), value),
+        (FractionFormat::denominator(denominator).build(), value),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Token<'a> {
+    Number(&'a str),
+    Word(&'a str),
+    Other(char),
+}
+
+pub(crate) fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(&rest[..len]));
+            len
+        } else if c.is_alphabetic() {
+            let len = rest
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(&rest[..len]));
+            len
+        } else {
+            tokens.push(Token::Other(c));
+            c.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// Month of an English month name or its abbreviation
+pub(crate) fn month_number(word: &str) -> Option<u32> {
+    let word = word.to_lowercase();
+    let position = MONTHS
+        .iter()
+        .position(|month| word.len() >= 3 && month.starts_with(&word))?;
+    Some(position as u32 + 1)
+}
+
+pub(crate) fn is_weekday(word: &str) -> bool {
+    let word = word.to_lowercase();
+    WEEKDAYS
+        .iter()
+        .any(|day| word.len() >= 3 && day.starts_with(&word))
+}
+
+/// Time of day read from `h:mm`, `h:mm:ss.000` and `AM/PM`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TimeOfDay {
+    pub(crate) hours: u32,
+    pub(crate) minutes: u32,
+    pub(crate) seconds: Option<u32>,
+    /// Sub-second digits and their value in seconds
+    pub(crate) fraction: Option<(u8, f64)>,
+    pub(crate) am_pm: bool,
+}
+
+impl TimeOfDay {
+    pub(crate) fn seconds_of_day(&self) -> f64 {
+        // Elapsed hours have no upper bound, so this would overflow a u32
+        let whole = self.hours as f64 * 3600.0
+            + self.minutes as f64 * 60.0
+            + self.seconds.unwrap_or(0) as f64;
+        whole + self.fraction.map_or(0.0, |(_, fraction)| fraction)
+    }
+
+    fn format(&self, elapsed: bool) -> TimeFormat {
+        let mut format = if self.seconds.is_some() {
+            TimeFormat::hms()
+        } else {
+            TimeFormat::hm()
+        };
+        if let Some((digits, _)) = self.fraction {
+            format = format.sub_seconds(digits);
+        }
+        if self.am_pm {
+            format = format.am_pm();
+        }
+        if elapsed {
+            format = format.elapsed();
+        }
+        format
+    }
+}
+
+/// Take the time out of the tokens, leaving the date
+pub(crate) fn split_time<'a>(tokens: &mut Vec<Token<'a>>) -> Option<Option<TimeOfDay>> {
+    let Some(start) = tokens.windows(3).position(|window| {
+        matches!(
+            window,
+            [Token::Number(_), Token::Other(':'), Token::Number(_)]
+        )
+    }) else {
+        return Some(None);
+    };
+    let number = |token: Option<&Token>| match token {
+        Some(Token::Number(digits)) => digits.parse::<u32>().ok(),
+        _ => None,
+    };
+    let mut end = start + 3;
+    let mut hours = number(tokens.get(start))?;
+    let minutes = number(tokens.get(start + 2))?;
+    let mut seconds = None;
+    if tokens.get(end) == Some(&Token::Other(':')) {
+        seconds = Some(number(tokens.get(end + 1))?);
+        end += 2;
+    }
+    let mut fraction = None;
+    if seconds.is_some()
+        && tokens.get(end) == Some(&Token::Other('.'))
+        && let Some(Token::Number(digits)) = tokens.get(end + 1)
+    {
+        let value = format!("0.{}", digits).parse::<f64>().ok()?;
+        fraction = Some((digits.len() as u8, value));
+        end += 2;
+    }
+    let mut am_pm = false;
+    let marker = if tokens.get(end) == Some(&Token::Other(' ')) {
+        end + 1
+    } else {
+        end
+    };
+    if let Some(Token::Word(word)) = tokens.get(marker) {
+        let word = word.to_uppercase();
+        if word == "AM" || word == "PM" {
+            if hours == 0 || hours > 12 {
+                return None;
+            }
+            hours = hours % 12 + if word == "PM" { 12 } else { 0 };
+            am_pm = true;
+            end = marker + 1;
+        }
+    }
+    if minutes > 59 || seconds.is_some_and(|seconds| seconds > 59) {
+        return None;
+    }
+    tokens.drain(start..end);
+    Some(Some(TimeOfDay {
+        hours,
+        minutes,
+        seconds,
+        fraction,
+        am_pm,
+    }))
+}
+
+/// A two digit year is in 1930–2029, like when typed in Excel
+pub(crate) fn full_year(digits: &str) -> Option<i32> {
+    let year = digits.parse::<i32>().ok()?;
+    Some(match digits.len() {
+        1 | 2 if year < 30 => 2000 + year,
+        1 | 2 => 1900 + year,
+        _ => year,
+    })
+}
+
+/// The dates the tokens can be read as, in order of likelihood, and
+/// whether the year was written
+fn read_dates(tokens: &[Token]) -> Vec<(NaiveDate, bool)> {
+    let mut numbers = Vec::new();
+    let mut month_name = None;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Number(digits) => numbers.push((i, *digits)),
+            Token::Word(word) => match month_number(word) {
+                Some(month) if month_name.is_none() => month_name = Some((i, month)),
+                _ if is_weekday(word) => {}
+                _ => return Vec::new(),
+            },
+            Token::Other(_) => {}
+        }
+    }
+    let current_year = chrono::Local::now().year();
+    let value = |digits: &str| digits.parse::<u32>().ok();
+    // (year digits, month, day) readings
+    let readings: Vec<(Option<&str>, Option<u32>, Option<u32>)> = match (month_name, &numbers[..]) {
+        (Some((_, month)), [(_, first), (_, second)]) => {
+            if second.len() > 2 || first.len() <= 2 {
+                vec![(Some(*second), Some(month), value(first))]
+            } else {
+                vec![(Some(*first), Some(month), value(second))]
+            }
+        }
+        (Some((at, month)), [(i, digits)]) if *i < at => vec![(None, Some(month), value(digits))],
+        (Some((_, month)), [(_, digits)]) if digits.len() > 2 => {
+            vec![(Some(*digits), Some(month), Some(1))]
+        }
+        (Some((_, month)), [(_, digits)]) => vec![
+            (None, Some(month), value(digits)),
+            (Some(*digits), Some(month), Some(1)),
+        ],
+        (None, [(_, first), (_, second), (_, third)]) if first.len() > 2 => {
+            vec![(Some(*first), value(second), value(third))]
+        }
+        (None, [(_, first), (_, second), (_, third)]) => vec![
+            (Some(*third), value(first), value(second)),
+            (Some(*third), value(second), value(first)),
+        ],
+        (None, [(_, first), (_, second)]) if second.len() > 2 => {
+            vec![(Some(*second), value(first), Some(1))]
+        }
+        (None, [(_, first), (_, second)]) => vec![
+            (None, value(first), value(second)),
+            (None, value(second), value(first)),
+        ],
+        _ => Vec::new(),
+    };
+    readings
+        .into_iter()
+        .filter_map(|(year, month, day)| {
+            let full = match year {
+                Some(digits) => full_year(digits)?,
+                None => current_year,
+            };
+            let date = NaiveDate::from_ymd_opt(full, month?, day?)?;
+            Some((date, year.is_some()))
+        })
+        .collect()
+}
+
+/// Date formats from the builder for the separator the example uses, after
+/// the presets of the Format Cells dialog
+fn date_formats(tokens: &[Token], time: Option<&TimeFormat>) -> Vec<NumFormat> {
+    let separator = tokens
+        .iter()
+        .find_map(|token| match token {
+            Token::Other(c) if *c != ' ' && *c != ',' => Some(*c),
+            _ => None,
+        })
+        .unwrap_or(' ');
+    let named = tokens
+        .iter()
+        .any(|token| matches!(token, Token::Word(word) if month_number(word).is_some()));
+    let months: &[MonthStyle] = if named {
+        &[MonthStyle::ShortName, MonthStyle::LongName]
+    } else {
+        &[MonthStyle::Number]
+    };
+
+    let mut formats = Vec::new();
+    for order in [
+        DateOrder::MonthDayYear,
+        DateOrder::DayMonthYear,
+        DateOrder::YearMonthDay,
+    ] {
+        for &month in months {
+            for padded in [false, true] {
+                for short_year in [false, true] {
+                    let mut format = DateFormat::with_order(order).sep(separator).month(month);
+                    if !padded {
+                        format = format.unpadded();
+                    }
+                    if short_year {
+                        format = format.short_year();
+                    }
+                    if let Some(time) = time {
+                        format = format.time(time.clone());
+                    }
+                    formats.push(format.build());
+                }
+            }
+        }
+    }
+    formats
+}
+
+/// Dates, times and both
+fn datetime_candidates(example: &str) -> Vec<(NumFormat, f64)> {
+    let mut tokens = tokenize(example);
+    let Some(time) = split_time(&mut tokens) else {
+        return Vec::new();
+    };
+    // Spaces and commas left around the date do not make it one
+    let has_date = tokens
+        .iter()
+        .any(|token| !matches!(token, Token::Other(' ' | ',')));
+
+    let mut candidates = Vec::new();
+    match (has_date, time) {
+        (false, None) => {}
+        (false, Some(time)) => {
+            let seconds = time.seconds_of_day();
+            let value = seconds / 86400.0;
+            let elapsed = time.hours >= 24;
+            candidates.extend(
+                TimePreset::ALL
+                    .iter()
+                    .filter(|time_preset| **time_preset != TimePreset::DateTime)
+                    .map(|time_preset| (preset(time_preset.format_str()), value)),
+            );
+            candidates.push((time.format(elapsed).build(), value));
+            // `30:55` may also be minutes and seconds
+            if time.seconds.is_none() && !time.am_pm {
+                let value = (time.hours as f64 * 60.0 + time.minutes as f64) / 86400.0;
+                candidates.push((preset(TimePreset::MinuteSecond.format_str()), value));
+            }
+        }
+        (true, time) => {
+            if time.is_some_and(|time| time.hours >= 24) {
+                return Vec::new();
+            }
+            let time_format = time.map(|time| time.format(false));
+            for (date, _) in read_dates(&tokens) {
+                let datetime = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+                let seconds = time.map_or(0.0, |time| time.seconds_of_day());
+                let value = datetime_to_excel_serial(datetime) + seconds / 86400.0;
+                let presets = DatePreset::ALL
+                    .iter()
+                    .map(|preset| preset.format_str())
+                    .chain([TimePreset::DateTime.format_str()]);
+                candidates.extend(presets.map(|format_str| (preset(format_str), value)));
+                candidates.extend(
+                    date_formats(&tokens, time_format.as_ref())
+                        .into_iter()
+                        .map(|format| (format, value)),
+                );
+            }
+        }
+    }
+    candidates
+}
@@ -0,0 +1,260 @@
+//! Reading what is typed into a cell
+//!
+//! Like Excel, an entry that looks like a number, a fraction, a date or a
+//! time becomes a number, along with the format it was typed in when that
+//! is not General. Everything else is text.
+//!
+//! ```
+//! use luscinia::{FormatValue, LocaleConfig, builtin_id, parse_input};
+//!
+//! let (value, format) = parse_input("12%", &LocaleConfig::default());
+//! assert_eq!(value, FormatValue::Number(0.12));
+//! assert_eq!(format.as_ref().and_then(builtin_id), Some(9));
+//! ```
+
+use crate::builder::{CurrencyPosition, DateOrder, NegativeStyle, NumberFormat};
+use crate::builtin::builtin_format;
+use crate::formatter::{FormatValue, LocaleConfig, datetime_to_excel_serial};
+use crate::infer::{TimeOfDay, Token, full_year, month_number, read_number, split_time, tokenize};
+use crate::types::NumFormat;
+use chrono::{Datelike, NaiveDate};
+
+/// Read `text` as typed into a cell, giving its value and the format the
+/// cell takes from it, `None` meaning General.
+///
+/// Numbers may be grouped, negative with a minus or in parentheses, and
+/// carry a percent sign, an exponent or a currency symbol. `1 3/4` is a
+/// fraction, while `3/4` is a date. Dates follow the order of
+/// `locale.date_locale`, month first when none is given. A leading `'`
+/// keeps the rest as text.
+pub fn parse_input(text: &str, locale: &LocaleConfig) -> (FormatValue, Option<NumFormat>) {
+    if let Some(text) = text.strip_prefix('\'') {
+        return (FormatValue::String(text.to_string()), None);
+    }
+    let trimmed = text.trim();
+    if trimmed.eq_ignore_ascii_case("TRUE") {
+        return (FormatValue::Boolean(true), None);
+    }
+    if trimmed.eq_ignore_ascii_case("FALSE") {
+        return (FormatValue::Boolean(false), None);
+    }
+    if !trimmed.is_empty()
+        && let Some((value, format)) = number(trimmed, locale)
+            .or_else(|| fraction(trimmed))
+            .or_else(|| datetime(trimmed, locale))
+    {
+        return (FormatValue::Number(value), format);
+    }
+    (FormatValue::String(text.to_string()), None)
+}
+
+fn builtin(id: u8) -> Option<NumFormat> {
+    builtin_format(id).cloned()
+}
+
+fn number(text: &str, locale: &LocaleConfig) -> Option<(f64, Option<NumFormat>)> {
+    let text = match text.strip_prefix('+') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => text,
+    };
+    let number = read_number(text, locale)?;
+    let decimals = number.decimals > 0;
+    let format = if let Some((symbol, position)) = number.currency {
+        // Excel shows negative amounts in parentheses when the symbol comes
+        // first
+        let negative = match position {
+            CurrencyPosition::Before | CurrencyPosition::BeforeSpaced => NegativeStyle::Parens,
+            CurrencyPosition::After => NegativeStyle::Minus,
+        };
+        let format = NumberFormat::decimals(if decimals { 2 } else { 0 })
+            .grouping()
+            .currency(symbol, position)
+            .negative(negative);
+        Some(format.build())
+    } else if number.scientific {
+        builtin(11)
+    } else if number.percent {
+        builtin(if decimals { 10 } else { 9 })
+    } else if number.grouped {
+        let parens = number.negative == Some(NegativeStyle::Parens);
+        builtin(match (parens, decimals) {
+            (false, false) => 3,
+            (false, true) => 4,
+            (true, false) => 37,
+            (true, true) => 39,
+        })
+    } else {
+        None
+    };
+    Some((number.value, format))
+}
+
+/// `1 3/4`, which needs the whole part to not be read as a date
+fn fraction(text: &str) -> Option<(f64, Option<NumFormat>)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (integer, fraction) = text.split_once(' ')?;
+    let (numerator, denominator) = fraction.trim_start().split_once('/')?;
+    let digits = |s: &str| {
+        (!s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
+            .then(|| s.parse::<u32>().ok())
+            .flatten()
+    };
+    let (integer, numerator, denominator) =
+        (digits(integer)?, digits(numerator)?, digits(denominator)?);
+    if denominator == 0 || denominator > 99 {
+        return None;
+    }
+    let mut value = integer as f64 + numerator as f64 / denominator as f64;
+    if negative {
+        value = -value;
+    }
+    Some((value, builtin(if denominator < 10 { 12 } else { 13 })))
+}
+
+/// The order of a date with numbers only for the locale
+fn date_order(locale: &LocaleConfig) -> DateOrder {
+    let Some(tag) = &locale.date_locale else {
+        return DateOrder::MonthDayYear;
+    };
+    let tag = tag.to_lowercase();
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next();
+    match (language, region) {
+        ("en", None | Some("us")) => DateOrder::MonthDayYear,
+        ("zh" | "ja" | "ko" | "hu" | "lt", _) => DateOrder::YearMonthDay,
+        _ => DateOrder::DayMonthYear,
+    }
+}
+
+fn datetime(text: &str, locale: &LocaleConfig) -> Option<(f64, Option<NumFormat>)> {
+    let mut tokens = tokenize(text);
+    // `1:30.5` is minutes, seconds and tenths
+    if let [
+        Token::Number(minutes),
+        Token::Other(':'),
+        Token::Number(seconds),
+        Token::Other('.'),
+        Token::Number(fraction),
+    ] = tokens[..]
+    {
+        let minutes = minutes.parse::<u32>().ok()?;
+        let seconds = format!("{}.{}", seconds, fraction).parse::<f64>().ok()?;
+        if seconds >= 60.0 {
+            return None;
+        }
+        return Some(((minutes as f64 * 60.0 + seconds) / 86400.0, builtin(47)));
+    }
+    let time = split_time(&mut tokens)?;
+    let has_date = tokens.iter().any(|token| *token != Token::Other(' '));
+    match (has_date, time) {
+        (false, None) => None,
+        (false, Some(time)) => {
+            let id = match time {
+                TimeOfDay { hours: 24.., .. } => 46,
+                TimeOfDay {
+                    am_pm: true,
+                    seconds: None,
+                    ..
+                } => 18,
+                TimeOfDay { am_pm: true, .. } => 19,
+                TimeOfDay { seconds: None, .. } => 20,
+                _ => 21,
+            };
+            Some((time.seconds_of_day() / 86400.0, builtin(id)))
+        }
+        (true, time) => {
+            if time.is_some_and(|time| time.hours >= 24) {
+                return None;
+            }
+            // The space before or after the time is not part of the date
+            let start = tokens.iter().position(|token| *token != Token::Other(' '))?;
+            let end = tokens.iter().rposition(|token| *token != Token::Other(' '))?;
+            let (date, mut id) = date(&tokens[start..=end], date_order(locale))?;
+            let datetime = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+            let mut value = datetime_to_excel_serial(datetime);
+            if let Some(time) = time {
+                value += time.seconds_of_day() / 86400.0;
+                id = 22;
+            }
+            Some((value, builtin(id)))
+        }
+    }
+}
+
+/// A date typed as `3/4/2023`, `3-Apr-23`, `Apr 3, 2023`, `3/4` or `Apr-23`,
+/// with the id of the format it gets
+fn date(tokens: &[Token], order: DateOrder) -> Option<(NaiveDate, u8)> {
+    let mut numbers = Vec::new();
+    let mut month_name = None;
+    let mut separated = true;
+    let mut spaced = false;
+    for token in tokens {
+        match token {
+            Token::Other('/' | '-' | '.') => separated = true,
+            Token::Other(',' | ' ') => {
+                separated = true;
+                spaced = true;
+            }
+            Token::Other(_) => return None,
+            _ if !separated => return None,
+            Token::Number(digits) => {
+                numbers.push(*digits);
+                separated = false;
+            }
+            Token::Word(word) if month_name.is_none() => {
+                month_name = Some((numbers.len(), month_number(word)?));
+                separated = false;
+            }
+            Token::Word(_) => return None,
+        }
+    }
+    // Spaces and commas only separate dates with a month name
+    if spaced && month_name.is_none() {
+        return None;
+    }
+    let value = |digits: &str| digits.parse::<u32>().ok();
+    let current_year = chrono::Local::now().year();
+    let day_this_year =
+        |month: u32, day: &str| NaiveDate::from_ymd_opt(current_year, month, value(day)?);
+    let first_of = |month: u32, year: &str| NaiveDate::from_ymd_opt(full_year(year)?, month, 1);
+
+    let (date, id) = match (month_name, &numbers[..]) {
+        (Some((_, month)), [day, year]) => {
+            let date = NaiveDate::from_ymd_opt(full_year(year)?, month, value(day)?)?;
+            (date, 15)
+        }
+        (Some((0, month)), [day_or_year]) => match day_this_year(month, day_or_year) {
+            Some(date) if day_or_year.len() <= 2 => (date, 16),
+            _ => (first_of(month, day_or_year)?, 17),
+        },
+        (Some((_, month)), [day]) => (day_this_year(month, day)?, 16),
+        (None, [first, second, third]) => {
+            let (year, month, day) = match order {
+                _ if first.len() > 2 => (first, second, third),
+                DateOrder::YearMonthDay => (first, second, third),
+                DateOrder::MonthDayYear => (third, first, second),
+                DateOrder::DayMonthYear => (third, second, first),
+            };
+            let date = NaiveDate::from_ymd_opt(full_year(year)?, value(month)?, value(day)?)?;
+            (date, 14)
+        }
+        (None, [first, second]) if first.len() > 2 => (first_of(value(second)?, first)?, 17),
+        (None, [first, second]) => {
+            let (month, day) = match order {
+                DateOrder::DayMonthYear => (second, first),
+                _ => (first, second),
+            };
+            match day_this_year(value(month)?, day) {
+                Some(date) if day.len() <= 2 => (date, 16),
+                _ => (first_of(value(first)?, second)?, 17),
+            }
+        }
+        _ => return None,
+    };
+    // Serials start in 1900
+    (date.year() >= 1900).then_some((date, id))
+}
@@ -1,12 +1,31 @@
 mod builtin;
+mod edit;
+mod infer;
+mod input;
+mod unformat;
+pub mod builder;
+pub mod diagnostic;
+pub mod dialog;
 pub mod formatter;
+pub mod palette;
 pub mod parser;
+pub mod render;
+pub mod syntax;
 pub mod types;
+pub mod visit;
 
-pub use builtin::{builtin_format, builtin_formats};
-pub use formatter::{FormatError, FormatResult, FormatValue, LocaleConfig, format};
-pub use parser::{NumfmtParser, PResult};
-pub use types::NumFormat;
+pub use builtin::{builtin_format, builtin_formats, builtin_id};
+pub use diagnostic::{DiagnosticCode, ParseDiagnostic, Severity};
+pub use formatter::{
+    Alignment, CellPart, FormatError, FormatResult, FormatValue, FormattedCell, LocaleConfig,
+    OverflowPolicy, format, format_cell, format_parsed,
+};
+pub use infer::{InferredFormat, infer_format};
+pub use input::parse_input;
+pub use palette::{Palette, Rgb};
+pub use parser::{Dialect, NumfmtParser, PResult};
+pub use types::{AST_SCHEMA_VERSION, NumFormat, SchemaError};
+pub use unformat::unformat;
 
 #[cfg(test)]
 mod tests {
@@ -125,6 +144,1162 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_format_string_round_trip() {
+        let round_trip = |s: &str| parse_fmtstr(s).unwrap().to_format_string();
+
+        assert_eq!(
+            round_trip("#,##0.00;(#,##0.00);\"Zero\""),
+            "#,##0.00;(#,##0.00);\"Zero\""
+        );
+        assert_eq!(
+            round_trip("[red][>=100]0.0E+00;[Color10]-0%"),
+            "[Red][>=100]0.0E+00;[Color10]-0%"
+        );
+        assert_eq!(
+            round_trip("[$-F800]dddd\\,\\ mmmm\\ dd\\,\\ yyyy"),
+            "[$-F800]dddd\", \"mmmm dd\", \"yyyy"
+        );
+        assert_eq!(round_trip("[$€-402]\\ #,##0.00"), "[$€-402] #,##0.00");
+        assert_eq!(round_trip("[$-1000409]h:mm AM/PM"), "[$-1000409]h:mm AM/PM");
+        assert_eq!(round_trip("[<=-1.5E+3]0;0"), "[<=-1.5E+3]0;0");
+        assert_eq!(round_trip("0;-0;0;"), "0;-0;0;");
+        assert_eq!(round_trip("_(* #,##0_);_(@_)"), "_(* #,##0_);_(@_)");
+        assert_eq!(round_trip("# ??/??"), "# ??/??");
+        assert_eq!(round_trip("[h]:mm:ss.000"), "[h]:mm:ss.000");
+        assert_eq!(round_trip("\"a\"\"b\"@"), "\"a\"\"b\"@");
+
+        for fixture in ["src/fixture/nfp.txt", "src/fixture/andersnm_valid.txt"] {
+            let lines = std::fs::read_to_string(fixture).unwrap();
+            for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+                let Ok(parsed) = parse_fmtstr(line) else {
+                    continue;
+                };
+                let printed = parsed.to_format_string();
+                let reparsed = parse_fmtstr(&printed)
+                    .unwrap_or_else(|e| panic!("{} printed as {} fails: {:?}", line, printed, e));
+                assert_eq!(reparsed, parsed, "{} printed as {}", line, printed);
+                assert_eq!(reparsed.to_format_string(), printed, "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let parsed: NumFormat = "[Red][>=100]#,##0.00;[DBNum1][$-411]ggge\"年\"m\"月\"".parse().unwrap();
+        let json = parsed.to_json();
+        assert!(json.starts_with(&format!("{{\"version\":{},", AST_SCHEMA_VERSION)));
+        assert_eq!(NumFormat::from_json(&json).unwrap(), parsed);
+
+        let future = json.replacen("\"version\":1", "\"version\":2", 1);
+        assert!(matches!(
+            NumFormat::from_json(&future),
+            Err(SchemaError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            NumFormat::from_json("{\"version\":1,\"format\":{\"Nope\":[]}}"),
+            Err(SchemaError::Json(_))
+        ));
+        assert!("0;0;0;0;0".parse::<NumFormat>().is_err());
+
+        // formats no format string parses to are refused
+        let mut number_as_text: NumFormat = "0;0;0;@".parse().unwrap();
+        if let NumFormat::FourParts(_, _, zero, text) = &mut number_as_text {
+            *text = Some(zero.inner.clone());
+        }
+        assert!(matches!(
+            NumFormat::from_json(&number_as_text.to_json()),
+            Err(SchemaError::Invalid(_))
+        ));
+        let json = "[DBNum1]0".parse::<NumFormat>().unwrap().to_json();
+        assert!(json.contains("{\"DBNum\":1}"));
+        assert!(matches!(
+            NumFormat::from_json(&json.replace("{\"DBNum\":1}", "{\"DBNum\":7}")),
+            Err(SchemaError::Invalid(_))
+        ));
+
+        // General in a date or time is not read back as missing
+        for format_str in ["yyyy General", "General h:mm", "Blue General"] {
+            let parsed: NumFormat = format_str.parse().unwrap();
+            assert_eq!(NumFormat::from_json(&parsed.to_json()).unwrap(), parsed, "{}", format_str);
+        }
+        let json = "yyyy General".parse::<NumFormat>().unwrap().to_json();
+        assert!(matches!(
+            NumFormat::from_json(&json.replace("\"General\"", "\"Number\"")),
+            Err(SchemaError::Json(_))
+        ));
+
+        for fixture in ["src/fixture/nfp.txt", "src/fixture/andersnm_valid.txt"] {
+            let lines = std::fs::read_to_string(fixture).unwrap();
+            for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+                let Ok(parsed) = line.parse::<NumFormat>() else {
+                    continue;
+                };
+                let decoded = NumFormat::from_json(&parsed.to_json());
+                assert_eq!(decoded.ok().as_ref(), Some(&parsed), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitors() {
+        use crate::types::*;
+        use crate::visit::{Fold, Visit, VisitMut};
+
+        /// Counts datetime tokens, currencies and General
+        #[derive(Default)]
+        struct Count {
+            tokens: usize,
+            currencies: usize,
+            generals: usize,
+        }
+        impl<'ast> Visit<'ast> for Count {
+            fn visit_datetime_token(&mut self, _: &'ast NFDateTimeToken) {
+                self.tokens += 1;
+            }
+            fn visit_locale(&mut self, locale: &'ast PartLocaleID) {
+                self.currencies += !locale.currency_symbol.is_empty() as usize;
+            }
+            fn visit_general(&mut self, _: &'ast NFGeneral) {
+                self.generals += 1;
+            }
+        }
+        impl VisitMut for Count {
+            fn visit_datetime_token_mut(&mut self, _: &mut NFDateTimeToken) {
+                self.tokens += 1;
+            }
+            fn visit_locale_mut(&mut self, locale: &mut PartLocaleID) {
+                self.currencies += !locale.currency_symbol.is_empty() as usize;
+            }
+            fn visit_general_mut(&mut self, _: &mut NFGeneral) {
+                self.generals += 1;
+            }
+        }
+        impl Fold for Count {
+            fn fold_datetime_token(&mut self, token: NFDateTimeToken) -> NFDateTimeToken {
+                self.tokens += 1;
+                token
+            }
+            fn fold_locale(&mut self, locale: PartLocaleID) -> PartLocaleID {
+                self.currencies += !locale.currency_symbol.is_empty() as usize;
+                locale
+            }
+            fn fold_general(&mut self, general: NFGeneral) -> NFGeneral {
+                self.generals += 1;
+                general
+            }
+        }
+
+        let format: NumFormat = "[$$-409]#,##0;[Red]-#,##0\\ [$€];yyyy-mm-dd hh:mm;@".parse().unwrap();
+        let mut count = Count::default();
+        count.visit_num_format(&format);
+        assert_eq!((count.tokens, count.currencies), (5, 2));
+
+        // General as a section and within a text section
+        let format: NumFormat = "General;\"n=\"General;0;@".parse().unwrap();
+        let mut count = Count::default();
+        count.visit_num_format(&format);
+        assert_eq!(count.generals, 2);
+        let mut count_mut = Count::default();
+        count_mut.visit_num_format_mut(&mut format.clone());
+        assert_eq!(count_mut.generals, 2);
+        let mut folded = Count::default();
+        folded.fold_num_format(format);
+        assert_eq!(folded.generals, 2);
+
+        for fixture in ["src/fixture/nfp.txt", "src/fixture/andersnm_valid.txt"] {
+            let lines = std::fs::read_to_string(fixture).unwrap();
+            for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+                let Ok(parsed) = line.parse::<NumFormat>() else {
+                    continue;
+                };
+                let mut visited = Count::default();
+                visited.visit_num_format(&parsed);
+
+                let mut edited = parsed.clone();
+                let mut visited_mut = Count::default();
+                visited_mut.visit_num_format_mut(&mut edited);
+                assert_eq!(edited, parsed, "{}", line);
+
+                let mut folded = Count::default();
+                assert_eq!(folded.fold_num_format(parsed.clone()), parsed, "{}", line);
+
+                let counts = |c: &Count| (c.tokens, c.currencies, c.generals);
+                assert_eq!(counts(&visited), counts(&visited_mut), "{}", line);
+                assert_eq!(counts(&visited), counts(&folded), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        use crate::builder::*;
+        use crate::types::Category;
+
+        let cases: Vec<(NumFormat, &str)> = vec![
+            (NumberFormat::new().into(), "0"),
+            (NumberFormat::decimals(2).grouping().into(), "#,##0.00"),
+            (NumberFormat::decimals(1).negative_red().into(), "0.0;[Red]0.0"),
+            (NumberFormat::decimals(2).negative_parens().into(), "0.00_);(0.00)"),
+            (
+                NumberFormat::decimals(2).grouping().currency("$", CurrencyPosition::Before).negative_red_parens().into(),
+                "$#,##0.00_);[Red]($#,##0.00)",
+            ),
+            (NumberFormat::decimals(2).currency("kr", CurrencyPosition::After).into(), "0.00\" kr\""),
+            (NumberFormat::decimals(0).currency("€", CurrencyPosition::BeforeSpaced).into(), "\"€ \"0"),
+            (NumberFormat::decimals(1).percent().into(), "0.0%"),
+            (NumberFormat::decimals(2).grouping().scientific().into(), "0.00E+00"),
+            (DateFormat::ymd().sep('-').into(), "yyyy-mm-dd"),
+            (DateFormat::dmy().sep('.').into(), "dd\".\"mm\".\"yyyy"),
+            (DateFormat::mdy().unpadded().short_year().into(), "m/d/yy"),
+            (DateFormat::dmy().sep('-').month(MonthStyle::ShortName).short_year().into(), "dd-mmm-yy"),
+            (DateFormat::ymd().time(TimeFormat::hms()).into(), "yyyy/mm/dd h:mm:ss"),
+            (TimeFormat::hm().am_pm().into(), "h:mm AM/PM"),
+            (TimeFormat::hms().elapsed().into(), "[h]:mm:ss"),
+            (TimeFormat::hm().sub_seconds(2).into(), "h:mm:ss.00"),
+            (FractionFormat::up_to(2).into(), "# ??/??"),
+            (FractionFormat::denominator(16).into(), "# ??/16"),
+        ];
+        for (built, expected) in cases {
+            assert_eq!(built.to_format_string(), expected);
+            assert_eq!(parse_fmtstr(expected).unwrap(), built, "{}", expected);
+        }
+
+        assert_eq!(NumberFormat::decimals(200).build().decimal_places(), 30);
+        let accounting = NumberFormat::decimals(2).grouping().currency("€", CurrencyPosition::After);
+        assert_eq!(accounting.build().category(), Category::Currency);
+        assert_eq!(
+            format_parsed(-1234.5, &accounting.negative_parens().build(), None).unwrap(),
+            "(1,234.50 €)"
+        );
+    }
+
+    #[test]
+    fn test_format_cells() {
+        use crate::builder::{CurrencyPosition, NegativeStyle};
+        use crate::dialog::*;
+
+        let mut settings = vec![FormatCells::General, FormatCells::Text];
+        settings.extend(DatePreset::ALL.map(FormatCells::Date));
+        settings.extend(TimePreset::ALL.map(FormatCells::Time));
+        settings.extend(SpecialFormat::ALL.map(FormatCells::Special));
+        settings.extend(FractionStyle::ALL.map(FormatCells::Fraction));
+        for decimals in [0, 2] {
+            settings.extend([
+                FormatCells::Number { decimals, grouping: false, negative: NegativeStyle::Parens },
+                FormatCells::Currency {
+                    decimals,
+                    symbol: "€".to_string(),
+                    locale: None,
+                    position: CurrencyPosition::After,
+                    negative: NegativeStyle::RedParens,
+                },
+                FormatCells::Currency {
+                    decimals,
+                    symbol: "€".to_string(),
+                    locale: Some(0x40C),
+                    position: CurrencyPosition::BeforeSpaced,
+                    negative: NegativeStyle::Parens,
+                },
+                FormatCells::Accounting { decimals, symbol: Some("$".to_string()), locale: None },
+                FormatCells::Accounting { decimals, symbol: None, locale: None },
+                FormatCells::Accounting { decimals, symbol: Some("€".to_string()), locale: Some(0x407) },
+                FormatCells::Percentage { decimals },
+                FormatCells::Scientific { decimals },
+            ]);
+        }
+        for setting in settings {
+            let format_str = setting.to_format_string();
+            let parsed = parse_fmtstr(&format_str).unwrap();
+            assert_eq!(FormatCells::from_format(&parsed), Some(setting), "{}", format_str);
+        }
+
+        let classify = |fmt: &str| FormatCells::from_format(&parse_fmtstr(fmt).unwrap());
+        assert_eq!(
+            classify("_($* #,##0.00_);_($* \\(#,##0.00\\);_($* \"-\"??_);_(@_)"),
+            Some(FormatCells::Accounting { decimals: 2, symbol: Some("$".to_string()), locale: None })
+        );
+        assert_eq!(
+            classify("_(* #,##0_);_(* \\(#,##0\\);_(* \"-\"_);_(@_)"),
+            Some(FormatCells::Accounting { decimals: 0, symbol: None, locale: None })
+        );
+        assert_eq!(
+            classify("#,##0.00;[Red]#,##0.00"),
+            Some(FormatCells::Number { decimals: 2, grouping: true, negative: NegativeStyle::Red })
+        );
+        let currency = |symbol: &str, locale, position, negative| FormatCells::Currency {
+            decimals: 2,
+            symbol: symbol.to_string(),
+            locale: Some(locale),
+            position,
+            negative,
+        };
+        assert_eq!(
+            classify("[$$-409]#,##0.00"),
+            Some(currency("$", 0x409, CurrencyPosition::Before, NegativeStyle::Minus))
+        );
+        assert_eq!(
+            classify("[$€-407] #,##0.00"),
+            Some(currency("€", 0x407, CurrencyPosition::BeforeSpaced, NegativeStyle::Minus))
+        );
+        assert_eq!(
+            classify("#,##0.00 [$€-407];[Red]#,##0.00 [$€-407]"),
+            Some(currency("€", 0x407, CurrencyPosition::After, NegativeStyle::Red))
+        );
+        assert_eq!(
+            classify("_([$$-409]* #,##0.00_);_([$$-409]* \\(#,##0.00\\);_([$$-409]* \"-\"??_);_(@_)"),
+            Some(FormatCells::Accounting { decimals: 2, symbol: Some("$".to_string()), locale: Some(0x409) })
+        );
+        assert_eq!(classify("[$€]#,##0.00"), None);
+        assert_eq!(classify("dddd, mmmm d, yyyy"), Some(FormatCells::Date(DatePreset::Long)));
+        assert_eq!(classify("0.0#"), None);
+        assert_eq!(classify("[Blue]0.00"), None);
+        assert_eq!(classify("yyyy/mm/dd"), None);
+    }
+
+    #[test]
+    fn test_edit_operations() {
+        use crate::builder::{CurrencyPosition, NegativeStyle};
+
+        let edit = |fmt: &str, op: fn(&NumFormat) -> NumFormat| {
+            let edited = op(&parse_fmtstr(fmt).unwrap());
+            assert_eq!(parse_fmtstr(&edited.to_string()).unwrap(), edited, "{}", fmt);
+            edited.to_string()
+        };
+
+        assert_eq!(edit("0", NumFormat::increase_decimals), "0.0");
+        assert_eq!(
+            edit("#,##0.00_);[Red](#,##0.00);0.00;@", NumFormat::increase_decimals),
+            "#,##0.000_);[Red](#,##0.000);0.000;@"
+        );
+        assert_eq!(edit("0.0 \"kg\"", NumFormat::decrease_decimals), "0 \"kg\"");
+        assert_eq!(edit("0", NumFormat::decrease_decimals), "0");
+        assert_eq!(edit(".0", NumFormat::decrease_decimals), "0");
+        assert_eq!(edit("t.0", NumFormat::decrease_decimals), "t0");
+        assert_eq!(edit("[White].0", NumFormat::decrease_decimals), "[White]0");
+        assert_eq!(edit("0.0;.0", NumFormat::decrease_decimals), "0;0");
+        assert_eq!(edit("0.00E+00", NumFormat::decrease_decimals), "0.0E+00");
+        assert_eq!(edit("0,", NumFormat::increase_decimals), "0.0,");
+        assert_eq!(edit("General;@", NumFormat::increase_decimals), "General;@");
+        let many = (0..40).fold(parse_fmtstr("0").unwrap(), |f, _| f.increase_decimals());
+        assert_eq!(many.decimal_places(), 30);
+
+        assert_eq!(edit("0.00;[Red]-0.00", NumFormat::toggle_grouping), "#,##0.00;[Red]-#,##0.00");
+        assert_eq!(edit("#,##0.00;[Red]-#,##0.00", NumFormat::toggle_grouping), "0.00;[Red]-0.00");
+        assert_eq!(edit("#,##0,", NumFormat::toggle_grouping), "0,");
+        assert_eq!(edit("00000", NumFormat::toggle_grouping), "00,000");
+        assert_eq!(edit("0.00E+00", NumFormat::toggle_grouping), "0.00E+00");
+
+        assert_eq!(edit("[Blue]0.0;yyyy", NumFormat::with_percent), "[Blue]0.0%;yyyy");
+        assert_eq!(edit("0%", NumFormat::with_percent), "0%");
+
+        let euro = |f: &NumFormat| f.with_currency("€", CurrencyPosition::After);
+        assert_eq!(edit("0.00;-0.00", euro), "0.00\" €\";-0.00\" €\"");
+        assert_eq!(edit("$#,##0_);($#,##0)", euro), "\"€\"#,##0_);(\"€\"#,##0)");
+        assert_eq!(edit("[$$-409]#,##0.00", euro), "[$€-409]#,##0.00");
+        assert_eq!(edit("#,##0 [$$-409]", euro), "#,##0 [$€-409]");
+        assert_eq!(edit("\"Total: $\"0.00", euro), "\"Total: €\"0.00");
+        assert_eq!(edit("0.00\" USD$ only\"", euro), "0.00\" USD€ only\"");
+
+        let negative = |fmt: &str, style: NegativeStyle| {
+            parse_fmtstr(fmt).unwrap().with_negative_style(style).to_string()
+        };
+        assert_eq!(negative("$#,##0.00", NegativeStyle::RedParens), "$#,##0.00_);[Red]($#,##0.00)");
+        assert_eq!(negative("#,##0.00_);[Red](#,##0.00)", NegativeStyle::Minus), "#,##0.00");
+        assert_eq!(negative("0.0%;[Red]-0.0%", NegativeStyle::Parens), "0.0%_);(0.0%)");
+        assert_eq!(negative("#,##0.00 ;(#,##0.00)", NegativeStyle::Parens), "#,##0.00_);(#,##0.00)");
+        assert_eq!(negative("[Blue]0;[Green]-0", NegativeStyle::Red), "[Blue]0;[Red]0");
+        assert_eq!(negative("[Blue]0;[Green]-0", NegativeStyle::Minus), "[Blue]0;[Green]-0");
+        assert_eq!(
+            negative("0.00_);(0.00);\"zero\";@", NegativeStyle::Minus),
+            "0.00;-0.00;\"zero\";@"
+        );
+        assert_eq!(negative("[>100]0;0", NegativeStyle::Red), "[>100]0;0");
+        assert_eq!(negative("yyyy-mm-dd", NegativeStyle::Parens), "yyyy-mm-dd");
+
+        let builder = crate::builder::NumberFormat::decimals(2).grouping();
+        for style in [NegativeStyle::Minus, NegativeStyle::Red, NegativeStyle::Parens, NegativeStyle::RedParens] {
+            assert_eq!(builder.build().with_negative_style(style), builder.clone().negative(style).build());
+        }
+    }
+
+    #[test]
+    fn test_infer_format() {
+        let infer = |example: &str, locale: &LocaleConfig| -> Vec<(String, f64)> {
+            infer_format(example, locale)
+                .into_iter()
+                .map(|inferred| (inferred.format.to_string(), inferred.value))
+                .collect()
+        };
+        let en = LocaleConfig::default();
+        let de = LocaleConfig {
+            decimal_separator: Some(','),
+            thousands_separator: Some('.'),
+            ..Default::default()
+        };
+
+        assert_eq!(infer("$1,234.50", &en)[0], ("$#,##0.00".to_string(), 1234.5));
+        assert_eq!(infer("12.5%", &en)[0], ("0.0%".to_string(), 0.125));
+        assert_eq!(infer("1234", &en)[0], ("General".to_string(), 1234.0));
+        assert_eq!(infer("1234", &en)[1], ("0".to_string(), 1234.0));
+        assert_eq!(infer("(1,234.50)", &en)[0], ("#,##0.00_);(#,##0.00)".to_string(), -1234.5));
+        assert_eq!(infer("1.23E+05", &en)[0], ("0.00E+00".to_string(), 123000.0));
+        assert_eq!(infer("1.234,50 €", &de)[0], ("#,##0.00\" €\"".to_string(), 1234.5));
+        assert_eq!(infer("1 3/4", &en)[0], ("# ?/?".to_string(), 1.75));
+        assert_eq!(infer("1 3/4", &en)[1], ("# ?/4".to_string(), 1.75));
+        // Candidates are kept only when they show the example again
+        assert_eq!(infer("$0.50", &en)[0], ("$0.00".to_string(), 0.5));
+        assert_eq!(infer("$12,345", &en)[0], ("$#,##0".to_string(), 12345.0));
+
+        assert_eq!(infer("2023-05-15 14:30", &en)[0].0, "yyyy-mm-dd h:mm");
+        assert!((infer("2023-05-15 14:30", &en)[0].1 - 45061.6041666).abs() < 1e-6);
+        assert_eq!(infer("May 15, 2023", &en)[0], ("mmmm d\",\" yyyy".to_string(), 45061.0));
+        assert_eq!(infer("15-May-2023", &en)[0], ("d-mmm-yyyy".to_string(), 45061.0));
+        assert_eq!(infer("12/31/2023", &en)[0], ("m/d/yyyy".to_string(), 45291.0));
+        assert_eq!(infer("31/12/2023", &en)[0], ("d/m/yyyy".to_string(), 45291.0));
+        assert_eq!(infer("2:30 PM", &en)[0].0, "h:mm AM/PM");
+        assert_eq!(infer("37:30:55", &en)[0].0, "[h]:mm:ss");
+        assert_eq!(infer("9999999:00", &en)[0], ("[h]:mm".to_string(), 9999999.0 / 24.0));
+        let times = infer("14:30", &en);
+        assert_eq!(times[0].0, "h:mm");
+        assert!(times.iter().any(|(format, _)| format == "mm:ss"));
+
+        assert!(infer("hello", &en).is_empty());
+        assert!(infer("1,2,3,4", &en).is_empty());
+        for example in ["$1,234.50", "1 3/4", "2023-05-15 14:30", "-12.5%", "Monday, May 15, 2023"] {
+            for inferred in infer_format(example, &en) {
+                let shown = format_parsed(inferred.value, &inferred.format, None).unwrap();
+                assert_eq!(shown, example, "{}", inferred.format);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let parse = |text: &str, locale: &LocaleConfig| {
+            let (value, format) = parse_input(text, locale);
+            (value, format.as_ref().and_then(builtin_id))
+        };
+        let en = LocaleConfig::default();
+        let de = LocaleConfig {
+            decimal_separator: Some(','),
+            thousands_separator: Some('.'),
+            date_locale: Some("de-DE".to_string()),
+            ..Default::default()
+        };
+        let number = FormatValue::Number;
+
+        assert_eq!(parse("12%", &en), (number(0.12), Some(9)));
+        assert_eq!(parse("12.5%", &en), (number(0.125), Some(10)));
+        assert_eq!(parse("1,200", &en), (number(1200.0), Some(3)));
+        assert_eq!(parse("1.200,50", &de), (number(1200.5), Some(4)));
+        assert_eq!(parse("(1,500)", &en), (number(-1500.0), Some(37)));
+        assert_eq!(parse("(500)", &en), (number(-500.0), None));
+        assert_eq!(parse("1e5", &en), (number(100000.0), Some(11)));
+        assert_eq!(parse("1 3/4", &en), (number(1.75), Some(12)));
+        assert_eq!(parse("1 3/16", &en), (number(1.1875), Some(13)));
+        assert_eq!(parse("3/4/2023", &en), (number(44989.0), Some(14)));
+        assert_eq!(parse("3/4/2023", &de), (number(45019.0), Some(14)));
+        assert_eq!(parse("2023-04-03", &de), (number(45019.0), Some(14)));
+        assert_eq!(parse("3-Apr-23", &en), (number(45019.0), Some(15)));
+        assert_eq!(parse("Apr-2023", &en), (number(45017.0), Some(17)));
+        assert_eq!(parse("14:30", &en), (number(14.5 / 24.0), Some(20)));
+        assert_eq!(parse("2:30 PM", &en), (number(14.5 / 24.0), Some(18)));
+        assert_eq!(parse("30:00", &en), (number(1.25), Some(46)));
+        assert_eq!(parse("1:30.5", &en), (number(90.5 / 86400.0), Some(47)));
+        assert_eq!(parse("99999999:00", &en), (number(99999999.0 / 24.0), Some(46)));
+        assert_eq!(parse("4/3/2023 12:00", &en), (number(45019.5), Some(22)));
+        assert_eq!(parse("3/4", &en).1, Some(16));
+
+        let (value, format) = parse_input("$1,200", &en);
+        assert_eq!(value, number(1200.0));
+        assert_eq!(format.unwrap().to_string(), "$#,##0_);($#,##0)");
+        let (value, format) = parse_input("1.234,5 €", &de);
+        assert_eq!(value, number(1234.5));
+        assert_eq!(format.unwrap().to_string(), "#,##0.00\" €\"");
+
+        assert_eq!(parse("TRUE", &en), (FormatValue::Boolean(true), None));
+        assert_eq!(parse("'123", &en), (FormatValue::String("123".to_string()), None));
+        for text in ["hello", "1,5", "13/25", "Mr. Smith", "", "1e400"] {
+            assert_eq!(parse(text, &en), (FormatValue::String(text.to_string()), None));
+        }
+    }
+
+    #[test]
+    fn test_unformat() {
+        let en = LocaleConfig::default();
+        let de = LocaleConfig {
+            decimal_separator: Some(','),
+            thousands_separator: Some('.'),
+            ..Default::default()
+        };
+        let read = |text: &str, format_str: &str, locale: &LocaleConfig| {
+            unformat(text, &parse_fmtstr(format_str).unwrap(), locale)
+        };
+        let number = |value: f64| Ok(FormatValue::Number(value));
+
+        assert_eq!(read("(1,234.57)", "#,##0.00;(#,##0.00)", &en), number(-1234.57));
+        assert_eq!(read("1.234,57", "#,##0.00", &de), number(1234.57));
+        assert_eq!(read("-12", "0", &en), number(-12.0));
+        assert_eq!(read("12.35%", "0.00%", &en), number(0.1235));
+        assert_eq!(read("1.23E-04", "0.00E+00", &en), number(0.000123));
+        assert_eq!(read("1.5M", "0.0,,\"M\"", &en), number(1500000.0));
+        assert_eq!(read("123-45-6789", "000-00-0000", &en), number(123456789.0));
+        assert_eq!(read("1 1/4", "# ?/?", &en), number(1.25));
+        assert_eq!(read("2  5/16", "# ??/??", &en), number(2.3125));
+        assert_eq!(read("7/4", "?/?", &en), number(1.75));
+        assert_eq!(read("15-May-23", "d-mmm-yy", &en), number(45061.0));
+        assert_eq!(read("Monday, May 15, 2023", "dddd, mmmm dd, yyyy", &en), number(45061.0));
+        assert_eq!(read("5/15/2023 12:00", "m/d/yyyy h:mm", &en), number(45061.5));
+        assert_eq!(read("2:30 PM", "h:mm AM/PM", &en), number(14.5 / 24.0));
+        assert_eq!(read("36:00:00", "[h]:mm:ss", &en), number(1.5));
+        assert_eq!(read("01:30.5", "mm:ss.0", &en), number(90.5 / 86400.0));
+        assert_eq!(read("[abc]", "0;-0;0;\"[\"@\"]\"", &en), Ok(FormatValue::String("abc".to_string())));
+        assert!(read("abc", "0.00", &en).is_err());
+        assert!(read("13/45/2023", "m/d/yyyy", &en).is_err());
+        // The weekday has to be the one of the date
+        assert!(read("Tuesday, May 15, 2023", "dddd, mmmm d, yyyy", &en).is_err());
+        assert!(read("Monday", "dddd", &en).is_err());
+        assert!(read("Mon 15", "ddd d", &en).is_err());
+        assert!(read("Yes", "\"Yes\";\"No\"", &en).is_err());
+        // Runs of blanks next to each other could be split many ways
+        let blanks = format!("{}x", " ".repeat(80));
+        assert!(read(&blanks, "_(_(_(_(_(0_)_)_)_)_)", &en).is_err());
+
+        let mut formats: Vec<NumFormat> = ["$#,##0.00_);($#,##0.00)", "[Red]0.00;[Blue]-0.00", "0.0;-0.0;\"-\"", "General"]
+            .iter()
+            .map(|format_str| parse_fmtstr(format_str).unwrap())
+            .collect();
+        formats.push(builtin_format(44).unwrap().clone());
+        for format in formats {
+            for value in [1234.5, -0.25, 0.0] {
+                let Ok(shown) = format_parsed(value, &format, None) else {
+                    continue;
+                };
+                let FormatValue::Number(back) = unformat(&shown, &format, &en).unwrap() else {
+                    panic!("{} read back as text", shown);
+                };
+                assert_eq!(format_parsed(back, &format, None).unwrap(), shown);
+            }
+        }
+    }
+
+    #[test]
+    fn test_introspection() {
+        use crate::types::*;
+
+        let category = |fmt: &str| parse_fmtstr(fmt).unwrap().category();
+        assert_eq!(category("General"), Category::General);
+        assert_eq!(category("#,##0.00;[Red]-#,##0.00"), Category::Number);
+        assert_eq!(category("$#,##0.00_);($#,##0.00)"), Category::Currency);
+        assert_eq!(category("[$€-407] #,##0.00"), Category::Currency);
+        assert_eq!(
+            category("_($* #,##0.00_);_($* (#,##0.00);_($* \"-\"??_);_(@_)"),
+            Category::Accounting
+        );
+        assert_eq!(category("yyyy-mm-dd"), Category::Date);
+        assert_eq!(category("h:mm AM/PM"), Category::Time);
+        assert_eq!(category("m/d/yy h:mm"), Category::DateTime);
+        assert_eq!(category("[h]:mm:ss"), Category::Duration);
+        assert_eq!(category("0.00%"), Category::Percent);
+        assert_eq!(category("# ?/?"), Category::Fraction);
+        assert_eq!(category("0.00E+00"), Category::Scientific);
+        assert_eq!(category("@"), Category::Text);
+        assert_eq!(category("[>100]0;0.00"), Category::Custom);
+        assert_eq!(category("0.00;yyyy"), Category::Custom);
+        assert_eq!(category("\"Total: \"General"), Category::Custom);
+
+        let format = parse_fmtstr("[Blue][>=1000]#,##0.000,\"k\";[Red][<0]0.0;0").unwrap();
+        assert_eq!(format.decimal_places(), 3);
+        assert!(format.uses_grouping());
+        assert_eq!(format.colors(), [NFPartColor::Intl(DefinedColor::Blue), NFPartColor::Intl(DefinedColor::Red)]);
+        assert_eq!(format.conditions().len(), 2);
+        assert_eq!(format.conditions()[1].op, NFCondOperator::LessThan);
+        assert!(!parse_fmtstr("0,").unwrap().uses_grouping());
+        assert!(!parse_fmtstr("#,##0").unwrap().is_date());
+
+        let format = parse_fmtstr("yyyy-mm-dd [h]:mm:ss.00").unwrap();
+        assert!(format.is_date() && format.has_time() && format.has_elapsed());
+        assert_eq!(format.decimal_places(), 2);
+        assert!(!parse_fmtstr("hh:mm").unwrap().has_elapsed());
+
+        assert_eq!(parse_fmtstr("0.00\" 元\"").unwrap().currency().as_deref(), Some("元"));
+        assert_eq!(parse_fmtstr("[$-409]0;[$CHF] -0").unwrap().currency().as_deref(), Some("CHF"));
+        assert_eq!(parse_fmtstr("0.00").unwrap().currency(), None);
+
+        let NumFormat::TwoParts(first, second) = parse_fmtstr("[Green]0.0;[$€-407]-0").unwrap() else {
+            panic!("two sections expected");
+        };
+        assert_eq!((first.category(), first.decimal_places()), (Category::Number, 1));
+        assert_eq!(second.currency().as_deref(), Some("€"));
+        assert_eq!(second.category(), Category::Currency);
+    }
+
+    #[test]
+    fn test_syntax_tree() {
+        use crate::syntax::*;
+        use crate::types::*;
+
+        let source = "[Red]0\\-0\"-\";@";
+        let tree = NumfmtParser::new(source).parse_syntax().unwrap();
+        assert_eq!(tree.sections.len(), 2);
+        let texts = tree.sections[0]
+            .nodes
+            .iter()
+            .map(|node| tree.text(&node.span).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["[Red]", "0", "\\-", "0", "\"-\""]);
+        assert_eq!(
+            tree.sections[0].nodes[2].node,
+            SyntaxKind::Number(DigitPosOrOther::LiteralString("-".to_string()))
+        );
+        assert_eq!(tree.sections[1].span, 13..14);
+
+        // separators and minutes are told apart like in the AST
+        let tree = NumfmtParser::new("AM/PM h:mm").parse_syntax().unwrap();
+        let kinds = tree.sections[0]
+            .nodes
+            .iter()
+            .map(|node| node.node.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds[4],
+            SyntaxKind::Datetime(NFDatetimeComponent::Token(NFDateTimeToken::Minute(
+                MinuteFormat::TwoChar
+            )))
+        );
+        assert_eq!(
+            kinds[3],
+            SyntaxKind::Datetime(NFDatetimeComponent::TimeSeparator(':'))
+        );
+
+        // only the edited node changes
+        let tree = NumfmtParser::new("[red]#,##0\\ \"kg\"").parse_syntax().unwrap();
+        let color = &tree.sections[0].nodes[0];
+        assert_eq!(
+            tree.rewrite([(color.span.clone(), "[Blue]")]).unwrap(),
+            "[Blue]#,##0\\ \"kg\""
+        );
+        assert_eq!(
+            tree.rewrite([(0..5, "[Blue]"), (3..7, "")]),
+            Err(RewriteError::Overlapping(0..5, 3..7))
+        );
+        assert_eq!(tree.text(&(10..40)), Err(RewriteError::OutOfRange(10..40)));
+        assert_eq!(
+            tree.rewrite([(16..40, "")]),
+            Err(RewriteError::OutOfRange(16..40))
+        );
+        assert!(matches!(
+            tree.node_at(11),
+            Some((0, Spanned { node: SyntaxKind::Number(_), .. }))
+        ));
+
+        // nodes cover each section exactly, sections cover the source
+        for fixture in ["src/fixture/nfp.txt", "src/fixture/andersnm_valid.txt"] {
+            let lines = std::fs::read_to_string(fixture).unwrap();
+            for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+                let Ok(tree) = NumfmtParser::new(line).parse_syntax() else {
+                    continue;
+                };
+                let mut section_start = 0;
+                for section in &tree.sections {
+                    assert_eq!(section.span.start, section_start, "{}", line);
+                    let mut cursor = section.span.start;
+                    for node in &section.nodes {
+                        assert_eq!(node.span.start, cursor, "{}", line);
+                        cursor = node.span.end;
+                    }
+                    assert_eq!(cursor, section.span.end, "{}", line);
+                    section_start = section.span.end + 1;
+                }
+                assert_eq!(section_start, line.len() + 1, "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let error = |source: &str| NumfmtParser::new(source).parse_diagnosed().unwrap_err();
+
+        let diagnostic = error("[Color99]0.00");
+        assert_eq!(diagnostic.code, DiagnosticCode::BadColorIndex);
+        assert_eq!(diagnostic.span, 0..9);
+        assert_eq!(
+            diagnostic.render("[Color99]0.00"),
+            "error[NF005]: color index must be between 1 and 56\n  |\n  | [Color99]0.00\n  | ^^^^^^^^^"
+        );
+
+        let diagnostic = error("0;0;[>1]0");
+        assert_eq!(diagnostic.code, DiagnosticCode::ConditionNotAllowed);
+        assert_eq!(diagnostic.span, 4..8);
+        assert_eq!(error("[>5]0").code, DiagnosticCode::ConditionNotAllowed);
+
+        let diagnostic = error("0;0;0;@;0");
+        assert_eq!(diagnostic.code, DiagnosticCode::TooManySections);
+        assert_eq!(diagnostic.span, 7..9);
+        assert_eq!(error("[$-4X]0").code, DiagnosticCode::InvalidLocale);
+
+        let diagnostic = error("0/");
+        assert_eq!(diagnostic.code, DiagnosticCode::UnexpectedToken);
+        assert_eq!(diagnostic.message, "unexpected end of format");
+        assert_eq!(diagnostic.render("0/").lines().last(), Some("  |   ^"));
+
+        // lenient readings still parse, but are reported as warnings
+        let diagnostics = NumfmtParser::new("\"kg").diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnbalancedQuote);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].span, 0..3);
+        let diagnostics = NumfmtParser::new("0 \"kg").diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnbalancedQuote);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        let codes = NumfmtParser::new("[Foo]0\\")
+            .diagnostics()
+            .into_iter()
+            .map(|d| d.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, [DiagnosticCode::UnknownBracket, DiagnosticCode::DanglingEscape]);
+        let code = |source: &str| NumfmtParser::new(source).diagnostics()[0].code;
+        assert_eq!(code("[Colorful]0"), DiagnosticCode::UnknownBracket);
+        assert_eq!(code("[Color]0"), DiagnosticCode::UnknownBracket);
+        assert!(NumfmtParser::new("[h]:mm;[Red][<0]0").diagnostics().is_empty());
+
+        // valid fixtures have no errors
+        for fixture in ["src/fixture/nfp.txt", "src/fixture/andersnm_valid.txt"] {
+            let lines = std::fs::read_to_string(fixture).unwrap();
+            for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+                let parser = NumfmtParser::new(line);
+                if parser.parse().is_ok() {
+                    assert!(
+                        parser.diagnostics().iter().all(|d| d.severity == Severity::Warning),
+                        "{}",
+                        line
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        use crate::types::*;
+
+        // valid formats parse as usual
+        let (format, diagnostics) = NumfmtParser::new("0.00;[Red]-0.00").parse_recovering();
+        assert_eq!(format, parse_fmtstr("0.00;[Red]-0.00").unwrap());
+        assert!(diagnostics.is_empty());
+
+        // a broken section keeps its prefixes, the rest becomes text
+        let source = "#,##0;[Blue][Color99]0;0;@";
+        let (format, diagnostics) = NumfmtParser::new(source).parse_recovering();
+        let NumFormat::FourParts(_, negative, _, Some(FormatComponent::Text(_))) = &format else {
+            panic!("{:?}", format);
+        };
+        assert_eq!(negative.color, Some(NFPartColor::Intl(DefinedColor::Blue)));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::BadColorIndex);
+        assert_eq!(diagnostics[0].span, 12..21);
+        assert_eq!(format_parsed(1234, &format, None).unwrap(), "1,234");
+        assert_eq!(format_parsed(-1, &format, None).unwrap(), "[Blue][Color99]0");
+        assert_eq!(format_parsed("abc", &format, None).unwrap(), "abc");
+
+        // every broken section is reported, extra sections are dropped
+        let (format, diagnostics) = NumfmtParser::new("0;0;[>1]0;@;0").parse_recovering();
+        assert!(matches!(format, NumFormat::FourParts(..)));
+        let codes = diagnostics.iter().map(|d| d.code).collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [DiagnosticCode::ConditionNotAllowed, DiagnosticCode::TooManySections]
+        );
+        assert_eq!(format_parsed(0, &format, None).unwrap(), "[>1]0");
+
+        let (format, diagnostics) = NumfmtParser::new("0 \"kg").parse_recovering();
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnbalancedQuote);
+        assert_eq!(format_parsed(5, &format, None).unwrap(), "0 \"kg");
+    }
+
+    #[test]
+    fn test_dialects() {
+        use Dialect::*;
+
+        let accepted_by = |source: &str| {
+            [StrictOoxml, Excel, LibreOffice, Lenient]
+                .into_iter()
+                .filter(|&dialect| NumfmtParser::new(source).with_dialect(dialect).parse().is_ok())
+                .collect::<Vec<_>>()
+        };
+        let all = [StrictOoxml, Excel, LibreOffice, Lenient];
+        assert_eq!(accepted_by("\"$\"#,##0.00_);[Red]\\(\"$\"#,##0.00\\)"), all);
+        assert_eq!(accepted_by("[h]:mm:ss;@"), all);
+        assert_eq!(accepted_by("#,##0.00 €"), [Excel, LibreOffice, Lenient]);
+        assert_eq!(accepted_by("[DBNum1][$-804]General"), [Excel, LibreOffice, Lenient]);
+        assert_eq!(accepted_by("ggge\"年\"m\"月\"d\"日\""), [Excel, LibreOffice, Lenient]);
+        assert_eq!(accepted_by("b2yyyy/mm/dd"), [Excel, LibreOffice, Lenient]);
+        assert_eq!(accepted_by("[NatNum1]0"), [LibreOffice, Lenient]);
+        assert_eq!(accepted_by("x0.00"), [LibreOffice, Lenient]);
+        assert_eq!(accepted_by("[颜色10]0"), [Lenient]);
+
+        // the default keeps accepting everything
+        let lenient = NumfmtParser::new("[NatNum1]0").parse().unwrap();
+        assert_eq!(
+            lenient,
+            NumfmtParser::new("[NatNum1]0").with_dialect(Lenient).parse().unwrap()
+        );
+
+        let diagnose = |source: &str, dialect| {
+            NumfmtParser::new(source)
+                .with_dialect(dialect)
+                .parse_diagnosed()
+                .unwrap_err()
+        };
+        let diagnostic = diagnose("[DBNum1]0", StrictOoxml);
+        assert_eq!(diagnostic.code, DiagnosticCode::NotInDialect);
+        assert_eq!(diagnostic.span, 0..8);
+        assert_eq!(diagnose("[颜色10]0", Excel).code, DiagnosticCode::NotInDialect);
+        assert_eq!(diagnose("[NatNum1]0", Excel).code, DiagnosticCode::UnknownBracket);
+        assert_eq!(diagnose("[Colorful]0", Excel).code, DiagnosticCode::UnknownBracket);
+        // what the lenient reading warns about is an error elsewhere
+        assert_eq!(diagnose("\"kg", Excel).code, DiagnosticCode::UnbalancedQuote);
+
+        // the formats gathered from Excel workbooks parse the same in Excel
+        let lines = std::fs::read_to_string("src/fixture/andersnm_valid.txt").unwrap();
+        for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+            if let Ok(format) = NumfmtParser::new(line).parse() {
+                let excel = NumfmtParser::new(line).with_dialect(Excel).parse();
+                assert_eq!(excel, Ok(format), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        use crate::syntax::TokenKind::{self, *};
+
+        fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+            NumfmtParser::new(source)
+                .tokens()
+                .into_iter()
+                .map(|token| (token.node, &source[token.span]))
+                .collect()
+        }
+        assert_eq!(
+            kinds("[Red]#,##0.0_);\\(0\\);\"x\"@"),
+            [
+                (Color, "[Red]"),
+                (DigitPlaceholder, "#"),
+                (Separator, ","),
+                (DigitPlaceholder, "#"),
+                (DigitPlaceholder, "#"),
+                (DigitPlaceholder, "0"),
+                (Separator, "."),
+                (DigitPlaceholder, "0"),
+                (Spacing, "_)"),
+                (SectionSeparator, ";"),
+                (Escape, "\\("),
+                (DigitPlaceholder, "0"),
+                (Escape, "\\)"),
+                (SectionSeparator, ";"),
+                (Literal, "\"x\""),
+                (TextPlaceholder, "@"),
+            ]
+        );
+        assert_eq!(
+            kinds("[$-409]h:mm AM/PM"),
+            [
+                (Locale, "[$-409]"),
+                (DateToken, "h"),
+                (Separator, ":"),
+                (DateToken, "mm"),
+                (Literal, " "),
+                (DateToken, "AM/PM"),
+            ]
+        );
+
+        // a broken section is split token by token, valid ones as usual
+        assert_eq!(
+            kinds("0%;[Color99]0 \"k"),
+            [
+                (DigitPlaceholder, "0"),
+                (Percent, "%"),
+                (SectionSeparator, ";"),
+                (Error, "[Color99]"),
+                (DigitPlaceholder, "0"),
+                (Literal, " "),
+                (Error, "\""),
+                (Literal, "k"),
+            ]
+        );
+        // the lenient parser reads a lone `[` as text, Excel does not
+        assert_eq!(kinds("[>"), [(Literal, "["), (Literal, ">")]);
+        let tokens = NumfmtParser::new("[>").with_dialect(Dialect::Excel).tokens();
+        assert_eq!(tokens[0].node, Error);
+        assert_eq!(
+            kinds("0.0E+"),
+            [
+                (DigitPlaceholder, "0"),
+                (Separator, "."),
+                (DigitPlaceholder, "0"),
+                (Exponent, "E+"),
+            ]
+        );
+
+        // tokens cover every input, valid or not
+        for fixture in ["src/fixture/nfp.txt", "src/fixture/andersnm_valid.txt"] {
+            let lines = std::fs::read_to_string(fixture).unwrap();
+            for line in lines.split('\n').filter(|line| !line.trim().is_empty()) {
+                let mut cursor = 0;
+                for token in NumfmtParser::new(line).tokens() {
+                    assert_eq!(token.span.start, cursor, "{}", line);
+                    cursor = token.span.end;
+                }
+                assert_eq!(cursor, line.len(), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_general_mixed_with_literals() {
+        use crate::types::*;
+
+        let res = parse_fmtstr("General").unwrap();
+        assert!(matches!(
+            res,
+            NumFormat::AnyNoCond(SectionWrapper {
+                inner: FormatComponent::General(),
+                ..
+            })
+        ));
+
+        let res = parse_fmtstr("\"Total: \"General").unwrap();
+        let NumFormat::AnyNoCond(SectionWrapper {
+            inner: FormatComponent::Text(text),
+            ..
+        }) = res
+        else {
+            panic!("expected a text section, got {:?}", res);
+        };
+        assert_eq!(
+            text.elements,
+            vec![
+                TextFormatElement::LiteralString("Total: ".to_string()),
+                TextFormatElement::General,
+            ]
+        );
+
+        assert!(parse_fmtstr("General\" kg\"").is_ok());
+        assert!(parse_fmtstr("[Red]General;[Blue]-General").is_ok());
+        assert!(parse_fmtstr("_(General_);*-General").is_ok());
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords() {
+        use crate::types::*;
+
+        let color_of = |s: &str| match parse_fmtstr(s).unwrap() {
+            NumFormat::AnyNoCond(section) => section.color,
+            other => panic!("unexpected format {:?}", other),
+        };
+        assert_eq!(color_of("[red]0"), Some(NFPartColor::Intl(DefinedColor::Red)));
+        assert_eq!(
+            color_of("[MAGENTA]0.00"),
+            Some(NFPartColor::Intl(DefinedColor::Magenta))
+        );
+        assert_eq!(color_of("[WHITE]0.0"), Some(NFPartColor::Intl(DefinedColor::White)));
+        assert_eq!(color_of("[color10]0"), Some(NFPartColor::Color(10)));
+        assert_eq!(color_of("[COLOR10]0"), Some(NFPartColor::Color(10)));
+
+        assert!(matches!(
+            parse_fmtstr("GENERAL").unwrap(),
+            NumFormat::AnyNoCond(SectionWrapper {
+                inner: FormatComponent::General(),
+                ..
+            })
+        ));
+        assert!(parse_fmtstr("general;-general").is_ok());
+        assert!(parse_fmtstr("[H]:MM:SS").is_ok());
+        assert!(parse_fmtstr("Yyyy-Mm-Dd").is_ok());
+        assert!(parse_fmtstr("0.00e+00").is_ok());
+        assert!(parse_fmtstr("[eng][dbnum1]0").is_ok());
+
+        let ampm_of = |s: &str| match parse_fmtstr(s).unwrap() {
+            NumFormat::AnyNoCond(SectionWrapper {
+                inner: FormatComponent::Datetime(DatetimeTuple(Some(dt), _, _)),
+                ..
+            }) => dt
+                .components
+                .into_iter()
+                .find_map(|c| match c {
+                    NFDatetimeComponent::AMPM(ampm) => Some(ampm),
+                    _ => None,
+                })
+                .unwrap(),
+            other => panic!("unexpected format {:?}", other),
+        };
+        assert_eq!(ampm_of("h:mm am/pm"), AmPm::Full("am/pm".to_string()));
+        assert_eq!(ampm_of("h:mm Am/Pm"), AmPm::Full("Am/Pm".to_string()));
+        assert_eq!(ampm_of("h:mm a/p"), AmPm::Simple("a/p".to_string()));
+    }
+
+    #[test]
+    fn test_minute_month_disambiguation() {
+        use crate::types::*;
+
+        // 'M' for months, 'm' for minutes, '-' for any other token
+        fn month_or_minute(s: &str) -> String {
+            let section = match parse_fmtstr(s).unwrap() {
+                NumFormat::AnyNoCond(section) => section,
+                other => panic!("unexpected format {:?}", other),
+            };
+            let FormatComponent::Datetime(DatetimeTuple(dt1, _, dt2)) = section.inner else {
+                panic!("expected a datetime section for {}", s);
+            };
+            dt1.into_iter()
+                .chain(dt2)
+                .flat_map(|dt| dt.components)
+                .filter_map(|c| match c {
+                    NFDatetimeComponent::Token(NFDateTimeToken::Month(_)) => Some('M'),
+                    NFDatetimeComponent::Token(NFDateTimeToken::Minute(_)) => Some('m'),
+                    NFDatetimeComponent::Token(_) => Some('-'),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let cases = [
+            // month on its own or next to date tokens
+            ("m", "M"),
+            ("mm", "M"),
+            ("mmm", "M"),
+            ("mmmmm", "M"),
+            ("yyyy-mm-dd", "-M-"),
+            ("m/d/yyyy", "M--"),
+            ("d-mmm-yy", "-M-"),
+            ("mmmm yyyy", "M-"),
+            ("mm h", "M-"),
+            ("s mm", "-M"),
+            // right after an hour
+            ("h:mm", "-m"),
+            ("hh:mm", "-m"),
+            ("h:m", "-m"),
+            ("[h]:mm", "-m"),
+            ("[hh]:mm:ss", "-m-"),
+            ("h:mm AM/PM", "-m"),
+            ("h \"h\" m", "-m"),
+            ("hh\\hmm", "-m"),
+            ("h\" 小时 \"m\" 分钟\"", "-m"),
+            // right before a second
+            ("mm:ss", "m-"),
+            ("m:ss", "m-"),
+            ("mm:ss.0", "m--"),
+            ("m\"min\"ss", "m-"),
+            ("mm \"and\" s", "m-"),
+            // mixed date and time
+            ("m/d/yy h:mm", "M---m"),
+            ("yyyy-mm-dd hh:mm:ss", "-M--m-"),
+            ("dd/mm/yyyy m:ss", "-M-m-"),
+            ("mm/dd h:mm", "M--m"),
+            ("h mmm", "-M"),
+            ("mmm ss", "M-"),
+            ("h:mm mm", "-mM"),
+            ("[h] mm m ss", "-mm-"),
+        ];
+        for (format, expected) in cases {
+            assert_eq!(month_or_minute(format), expected, "format: {}", format);
+        }
+    }
+
+    #[test]
+    fn test_palette() {
+        use crate::types::*;
+
+        let color_of = |s: &str| match parse_fmtstr(s).unwrap() {
+            NumFormat::AnyNoCond(section) => section.color.unwrap(),
+            other => panic!("unexpected format {:?}", other),
+        };
+
+        let palette = Palette::default();
+        assert_eq!(palette.resolve(&color_of("[Red]0")), Some(Rgb::new(255, 0, 0)));
+        assert_eq!(palette.resolve(&color_of("[Cyan]0")), Some(Rgb::new(0, 255, 255)));
+        assert_eq!(palette.resolve(&color_of("[Color10]0")), Some(Rgb::new(0, 128, 0)));
+        assert_eq!(palette.resolve(&color_of("[Color56]0")), Some(Rgb::new(0x33, 0x33, 0x33)));
+        assert_eq!(palette.get(0), None);
+        assert_eq!(palette.get(57), None);
+
+        // <indexedColors> starts with 8 entries that formats cannot address
+        let mut indexed = vec![Rgb::new(0, 0, 0); 8];
+        indexed.extend([Rgb::from_hex("FF000000").unwrap(), Rgb::from_hex("FF112233").unwrap()]);
+        let palette = Palette::from_indexed_colors(&indexed);
+        assert_eq!(palette.get(2), Some(Rgb::new(0x11, 0x22, 0x33)));
+        assert_eq!(palette.resolve(&NFPartColor::Intl(DefinedColor::White)), palette.get(2));
+        assert_eq!(palette.get(3), Some(Rgb::new(255, 0, 0)));
+
+        assert_eq!(Rgb::from_hex("#0066CC").unwrap().to_hex(), "#0066CC");
+        assert_eq!(Rgb::from_hex("0066C"), None);
+        assert_eq!(Rgb::from_hex("GG0066CC"), None);
+    }
+
+    #[test]
+    fn test_ansi_renderer() {
+        let palette = Palette::default();
+        let ansi = |value: FormatValue, fmt: &str, width: Option<usize>| {
+            let cell = format_cell(value, fmt, None).unwrap();
+            render::to_ansi(&cell, width, &palette)
+        };
+
+        // numbers to the right, text to the left
+        assert_eq!(ansi(1.5.into(), "0.00", Some(8)), "    1.50");
+        assert_eq!(ansi("abc".into(), "@", Some(6)), "abc   ");
+        assert_eq!(ansi(1.5.into(), "0.00", None), "1.50");
+
+        // the section color becomes an SGR sequence instead of a prefix
+        assert_eq!(
+            ansi((-2.0).into(), "0;[Red]-0", Some(4)),
+            "  \x1b[38;2;255;0;0m-2\x1b[39m"
+        );
+        assert_eq!(
+            ansi(3.0.into(), "[Color10]0", None),
+            "\x1b[38;2;0;128;0m3\x1b[39m"
+        );
+
+        // fills take the free width, `_x` blanks keep their place
+        assert_eq!(ansi(12.0.into(), "\"$\"* 00", Some(6)), "$   12");
+        assert_eq!(ansi(12.0.into(), "0*-", Some(6)), "12----");
+        assert_eq!(ansi(12.0.into(), "0_)", Some(5)), "  12 ");
+        assert_eq!(ansi("ab".into(), "*.@", Some(5)), "...ab");
+    }
+
+    #[test]
+    fn test_html_renderer() {
+        let palette = Palette::default();
+        let html = |value: FormatValue, fmt: &str| {
+            let cell = format_cell(value, fmt, None).unwrap();
+            render::to_html(&cell, &palette)
+        };
+
+        assert_eq!(
+            html(1.5.into(), "0.00"),
+            "<div style=\"display:flex;justify-content:flex-end;text-align:right;\
+             white-space:pre;overflow:hidden\"><span>1.50</span></div>"
+        );
+        assert_eq!(
+            html("<b>&".into(), "[Blue]@"),
+            "<div style=\"display:flex;justify-content:flex-start;text-align:left;\
+             white-space:pre;overflow:hidden;color:#0000FF\"><span>&lt;b&gt;&amp;</span></div>"
+        );
+
+        let with_blank = html(12.0.into(), "0_)");
+        assert!(with_blank.ends_with(
+            "<span>12</span><span style=\"visibility:hidden\">)</span></div>"
+        ));
+
+        let with_fill = html(12.0.into(), "\"$\"* 00");
+        assert!(with_fill.contains(
+            "<span>$</span><span style=\"flex:1 1 0;min-width:0;overflow:hidden\">   "
+        ));
+        assert!(with_fill.ends_with("</span><span>12</span></div>"));
+    }
+
     #[test]
     fn test_special_prefixes() {
         let test_cases = vec![
@@ -151,6 +1326,24 @@ mod tests {
             }
             println!("---");
         }
+
+        let modifiers = |fmt: &str| match parse_fmtstr(fmt).unwrap() {
+            NumFormat::AnyNoCond(section) => section.special_prefix,
+            other => panic!("unexpected sections: {other:?}"),
+        };
+        assert_eq!(
+            modifiers("t[DBNum2][ENG]0"),
+            vec![types::SectionModifier::Thai, types::SectionModifier::DBNum(2), types::SectionModifier::English]
+        );
+        assert_eq!(
+            modifiers("[HIJ][JPN][TWN]yyyy"),
+            vec![types::SectionModifier::Hijri, types::SectionModifier::Japanese, types::SectionModifier::Taiwan]
+        );
+        assert_eq!(
+            modifiers("[NatNum1]0"),
+            vec![types::SectionModifier::Unknown("NatNum1".to_string())]
+        );
+        assert_eq!(parse_fmtstr("[DBNum3][NatNum1]0").unwrap().to_string(), "[DBNum3][NatNum1]0");
     }
 
     #[test]
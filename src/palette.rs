@@ -0,0 +1,187 @@
+//! Excel color palette, resolving section colors to RGB values
+
+use serde::Serialize;
+
+use crate::types::{DefinedColor, NFPartColor};
+
+/// A 24-bit RGB color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+
+    /// Build a color from a `0xRRGGBB` value
+    pub const fn from_u32(rgb: u32) -> Self {
+        Rgb::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    /// Parse a color written as `RRGGBB` or `AARRGGBB`, as in the `rgb`
+    /// attribute of `<rgbColor>`. The alpha channel is ignored.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let rgb = match hex.len() {
+            6 => hex,
+            8 => &hex[2..],
+            _ => return None,
+        };
+        u32::from_str_radix(rgb, 16).ok().map(Rgb::from_u32)
+    }
+
+    /// The color as `#RRGGBB`
+    pub fn to_hex(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+/// Number of colors addressable with `[Color1]` to `[Color56]`
+pub const PALETTE_SIZE: usize = 56;
+
+/// Entries of `<indexedColors>` before the one used by `[Color1]`
+const INDEXED_COLORS_OFFSET: usize = 8;
+
+/// Excel's default palette, `DEFAULT_PALETTE[n - 1]` is `[ColorN]`
+const DEFAULT_PALETTE: [Rgb; PALETTE_SIZE] = [
+    Rgb::from_u32(0x000000),
+    Rgb::from_u32(0xFFFFFF),
+    Rgb::from_u32(0xFF0000),
+    Rgb::from_u32(0x00FF00),
+    Rgb::from_u32(0x0000FF),
+    Rgb::from_u32(0xFFFF00),
+    Rgb::from_u32(0xFF00FF),
+    Rgb::from_u32(0x00FFFF),
+    Rgb::from_u32(0x800000),
+    Rgb::from_u32(0x008000),
+    Rgb::from_u32(0x000080),
+    Rgb::from_u32(0x808000),
+    Rgb::from_u32(0x800080),
+    Rgb::from_u32(0x008080),
+    Rgb::from_u32(0xC0C0C0),
+    Rgb::from_u32(0x808080),
+    Rgb::from_u32(0x9999FF),
+    Rgb::from_u32(0x993366),
+    Rgb::from_u32(0xFFFFCC),
+    Rgb::from_u32(0xCCFFFF),
+    Rgb::from_u32(0x660066),
+    Rgb::from_u32(0xFF8080),
+    Rgb::from_u32(0x0066CC),
+    Rgb::from_u32(0xCCCCFF),
+    Rgb::from_u32(0x000080),
+    Rgb::from_u32(0xFF00FF),
+    Rgb::from_u32(0xFFFF00),
+    Rgb::from_u32(0x00FFFF),
+    Rgb::from_u32(0x800080),
+    Rgb::from_u32(0x800000),
+    Rgb::from_u32(0x008080),
+    Rgb::from_u32(0x0000FF),
+    Rgb::from_u32(0x00CCFF),
+    Rgb::from_u32(0xCCFFFF),
+    Rgb::from_u32(0xCCFFCC),
+    Rgb::from_u32(0xFFFF99),
+    Rgb::from_u32(0x99CCFF),
+    Rgb::from_u32(0xFF99CC),
+    Rgb::from_u32(0xCC99FF),
+    Rgb::from_u32(0xFFCC99),
+    Rgb::from_u32(0x3366FF),
+    Rgb::from_u32(0x33CCCC),
+    Rgb::from_u32(0x99CC00),
+    Rgb::from_u32(0xFFCC00),
+    Rgb::from_u32(0xFF9900),
+    Rgb::from_u32(0xFF6600),
+    Rgb::from_u32(0x666699),
+    Rgb::from_u32(0x969696),
+    Rgb::from_u32(0x003366),
+    Rgb::from_u32(0x339966),
+    Rgb::from_u32(0x003300),
+    Rgb::from_u32(0x333300),
+    Rgb::from_u32(0x993300),
+    Rgb::from_u32(0x993366),
+    Rgb::from_u32(0x333399),
+    Rgb::from_u32(0x333333),
+];
+
+impl DefinedColor {
+    /// Palette index of the named color, so that `[Red]` is `[Color3]`
+    pub fn palette_index(&self) -> u8 {
+        match self {
+            DefinedColor::Black => 1,
+            DefinedColor::White => 2,
+            DefinedColor::Red => 3,
+            DefinedColor::Green => 4,
+            DefinedColor::Blue => 5,
+            DefinedColor::Yellow => 6,
+            DefinedColor::Magenta => 7,
+            DefinedColor::Cyan => 8,
+        }
+    }
+}
+
+/// The 56 colors a number format can refer to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colors: [Rgb; PALETTE_SIZE],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colors: DEFAULT_PALETTE,
+        }
+    }
+}
+
+impl Palette {
+    /// Build the palette of a workbook from the entries of its
+    /// `<indexedColors>` element, in document order. The first 8 entries
+    /// are not addressable from number formats and `[Color1]` is the 9th.
+    /// Colors missing from a short list keep their default value.
+    pub fn from_indexed_colors(indexed_colors: &[Rgb]) -> Self {
+        let mut palette = Palette::default();
+        for (slot, color) in palette
+            .colors
+            .iter_mut()
+            .zip(indexed_colors.iter().skip(INDEXED_COLORS_OFFSET))
+        {
+            *slot = *color;
+        }
+        palette
+    }
+
+    /// Color used by `[ColorN]`, for `index` between 1 and 56
+    pub fn get(&self, index: u8) -> Option<Rgb> {
+        let slot = (index as usize).checked_sub(1)?;
+        self.colors.get(slot).copied()
+    }
+
+    /// Replace the color used by `[ColorN]`, returning the previous one
+    pub fn set(&mut self, index: u8, color: Rgb) -> Option<Rgb> {
+        let slot = (index as usize).checked_sub(1)?;
+        self.colors
+            .get_mut(slot)
+            .map(|entry| std::mem::replace(entry, color))
+    }
+
+    /// Resolve a section color to RGB. Named colors go through the palette
+    /// like Excel does, so a workbook overriding `[Color3]` also changes
+    /// `[Red]`.
+    pub fn resolve(&self, color: &NFPartColor) -> Option<Rgb> {
+        match color {
+            NFPartColor::Intl(defined) => self.get(defined.palette_index()),
+            NFPartColor::Color(index) => self.get(*index),
+        }
+    }
+
+    /// All colors, `colors()[n - 1]` being `[ColorN]`
+    pub fn colors(&self) -> &[Rgb] {
+        &self.colors
+    }
+}
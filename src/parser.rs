@@ -1,22 +1,300 @@
+use crate::diagnostic::{self, ParseDiagnostic};
+use crate::syntax::*;
 use crate::types::*;
 use peg::str::LineCol;
+use std::ops::Range;
+use std::str::FromStr;
 
 pub type PResult<T> = Result<T, peg::error::ParseError<LineCol>>;
 
+/// Characters Excel shows without quotes, and that never start a token in
+/// any kind of section
+pub(crate) const BARE_LITERALS: &[char] = &[
+    ' ', '$', '-', '+', '(', ')', ':', '!', '^', '&', '\'', '~', '{', '}', '<', '>', '=',
+];
+
+/// Which application's extensions to the MS-OE376 grammar are accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Only what MS-OE376 allows
+    StrictOoxml,
+    /// Adds the `t` and `[DBNum1]` style prefixes and East Asian era and
+    /// calendar tokens
+    Excel,
+    /// Adds LibreOffice's own bracket modifiers such as `[NatNum1]`, and
+    /// unquoted text
+    LibreOffice,
+    /// Anything the parser can make sense of, including `[颜色10]` colors
+    #[default]
+    Lenient,
+}
+
+impl Dialect {
+    pub(crate) fn special_prefixes(self) -> bool {
+        self != Dialect::StrictOoxml
+    }
+
+    pub(crate) fn era_tokens(self) -> bool {
+        self != Dialect::StrictOoxml
+    }
+
+    pub(crate) fn unknown_brackets(self) -> bool {
+        matches!(self, Dialect::LibreOffice | Dialect::Lenient)
+    }
+
+    pub(crate) fn localized_colors(self) -> bool {
+        self == Dialect::Lenient
+    }
+
+    /// Whether a character that is not part of any token can be written
+    /// without quotes
+    pub(crate) fn bare_char(self, c: char) -> bool {
+        match self {
+            Dialect::StrictOoxml => BARE_LITERALS.contains(&c),
+            // Excel reads a single `y` as a year, the grammar only knows
+            // `yy` and `yyyy`
+            Dialect::Excel => !c.is_ascii() || BARE_LITERALS.contains(&c) || c == 'y' || c == 'Y',
+            Dialect::LibreOffice | Dialect::Lenient => true,
+        }
+    }
+}
+
 pub struct NumfmtParser<'source> {
     src: &'source str,
+    dialect: Dialect,
 }
 
 impl<'source> NumfmtParser<'source> {
     pub fn new(src: &'source str) -> Self {
-        NumfmtParser { src }
+        NumfmtParser {
+            src,
+            dialect: Dialect::default(),
+        }
+    }
+
+    /// Accept only the formats the given dialect allows
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
     }
 
     pub fn parse(&self) -> PResult<NumFormat> {
-        numfmt_parser::toplevel(self.src)
+        numfmt_parser::toplevel(self.src, self.dialect)
+    }
+
+    /// Parse the format, explaining a failure with a [`ParseDiagnostic`]
+    pub fn parse_diagnosed(&self) -> Result<NumFormat, ParseDiagnostic> {
+        match diagnostic::diagnose(self.src, self.dialect, self.parse()) {
+            (Some(format), _) => Ok(format),
+            (None, mut diagnostics) => Err(diagnostics.swap_remove(0)),
+        }
+    }
+
+    /// Every problem found in the format, including the ones the parser
+    /// reads leniently, such as an unclosed quote
+    pub fn diagnostics(&self) -> Vec<ParseDiagnostic> {
+        diagnostic::diagnose(self.src, self.dialect, self.parse()).1
+    }
+
+    /// Parse the format like spreadsheet apps do with malformed formats:
+    /// sections that parse are kept, the rest of a broken section after its
+    /// prefixes is read as literal text, and sections past the fourth are
+    /// dropped. Every failure is reported as an error diagnostic.
+    pub fn parse_recovering(&self) -> (NumFormat, Vec<ParseDiagnostic>) {
+        let parsed = self.parse();
+        if parsed.is_ok() {
+            let (format, diagnostics) = diagnostic::diagnose(self.src, self.dialect, parsed);
+            return (format.unwrap(), diagnostics);
+        }
+
+        let mut recovery = Recovery {
+            src: self.src,
+            dialect: self.dialect,
+            spans: diagnostic::split_sections(self.src),
+            failures: Vec::new(),
+        };
+        let format = match recovery.spans.len() {
+            1 => NumFormat::AnyNoCond(recovery.no_cond(0)),
+            2 => NumFormat::TwoParts(recovery.any(0), recovery.any(1)),
+            3 => NumFormat::ThreeParts(recovery.any(0), recovery.any(1), recovery.no_cond(2)),
+            _ => NumFormat::FourParts(
+                recovery.any(0),
+                recovery.any(1),
+                recovery.no_cond(2),
+                recovery.last(3),
+            ),
+        };
+        if let Some(extra) = recovery.spans.get(4) {
+            recovery.failures.push((Some(4), extra.start));
+        }
+        (format, diagnostic::diagnose_failures(self.src, self.dialect, &recovery.failures))
+    }
+
+    /// Split the format into categorized tokens, for syntax highlighting.
+    /// Sections that parse are split the way the parser reads them, the
+    /// others token by token, so incomplete input still gets highlighted.
+    pub fn tokens(&self) -> Vec<Spanned<TokenKind>> {
+        let valid = self.parse().is_ok();
+        let spans = diagnostic::split_sections(self.src);
+        let mut tokens = Vec::new();
+
+        for (index, span) in spans.iter().enumerate() {
+            if index > 0 {
+                tokens.push(Spanned::new(
+                    TokenKind::SectionSeparator,
+                    span.start - 1..span.start,
+                ));
+            }
+            let src = &self.src[span.clone()];
+            let parses = valid
+                || match (index, spans.len()) {
+                    (_, 1) | (2, _) => numfmt_parser::section_no_cond(src, self.dialect).is_ok(),
+                    (0 | 1, _) => numfmt_parser::section_any(src, self.dialect).is_ok(),
+                    (3, _) => {
+                        src.is_empty() || numfmt_parser::section_last(src, self.dialect).is_ok()
+                    }
+                    _ => false,
+                };
+            let section = parses
+                .then(|| numfmt_parser::cst(src, self.dialect).ok())
+                .flatten();
+            let section_tokens = match section {
+                Some(sections) => sections
+                    .into_iter()
+                    .flat_map(|section| section.nodes)
+                    .map(|node| {
+                        let kind = node.node.token_kind(&src[node.span.clone()]);
+                        Spanned::new(kind, node.span)
+                    })
+                    .collect(),
+                None => numfmt_parser::flat_tokens(src, self.dialect)
+                    .expect("every character is a token"),
+            };
+            tokens.extend(section_tokens.into_iter().map(|token| {
+                Spanned::new(token.node, token.span.start + span.start..token.span.end + span.start)
+            }));
+        }
+        tokens
+    }
+
+    /// Parse the format along with the source span of every node
+    pub fn parse_syntax(&self) -> PResult<SyntaxTree<'source>> {
+        let format = self.parse()?;
+        let sections = numfmt_parser::cst(self.src, self.dialect)?;
+        Ok(SyntaxTree {
+            source: self.src,
+            format,
+            sections,
+        })
+    }
+}
+
+impl FromStr for NumFormat {
+    type Err = peg::error::ParseError<LineCol>;
+
+    fn from_str(src: &str) -> PResult<NumFormat> {
+        NumfmtParser::new(src).parse()
     }
 }
 
+/// State of [`NumfmtParser::parse_recovering`], parsing the sections of a
+/// format one by one
+struct Recovery<'source> {
+    src: &'source str,
+    dialect: Dialect,
+    spans: Vec<Range<usize>>,
+    /// Section and offset in the whole format of every failure
+    failures: Vec<(Option<usize>, usize)>,
+}
+
+impl<'source> Recovery<'source> {
+    fn any(&mut self, index: usize) -> Any {
+        match numfmt_parser::section_any(self.section(index), self.dialect) {
+            Ok(section) => section,
+            Err(err) => {
+                let (prefixes, body) = self.degrade(index, err);
+                let (condition, body) = match numfmt_parser::section_condition(body, self.dialect) {
+                    Ok((condition, end)) => (Some(condition), &body[end..]),
+                    Err(_) => (None, body),
+                };
+                with_inner(prefixes, AnyInner::ConditionalData(condition, literal_text(body)))
+            }
+        }
+    }
+
+    fn no_cond(&mut self, index: usize) -> AnyNoCond {
+        match numfmt_parser::section_no_cond(self.section(index), self.dialect) {
+            Ok(section) => section,
+            Err(err) => {
+                let (prefixes, body) = self.degrade(index, err);
+                with_inner(prefixes, literal_text(body))
+            }
+        }
+    }
+
+    fn last(&mut self, index: usize) -> Option<FormatComponent> {
+        let src = self.section(index);
+        if src.is_empty() {
+            return None;
+        }
+        Some(match numfmt_parser::section_last(src, self.dialect) {
+            Ok(component) => component,
+            Err(err) => {
+                self.fail(index, err);
+                literal_text(src)
+            }
+        })
+    }
+
+    fn section(&self, index: usize) -> &'source str {
+        &self.src[self.spans[index].clone()]
+    }
+
+    fn fail(&mut self, index: usize, err: peg::error::ParseError<LineCol>) {
+        let offset = self.spans[index].start + err.location.offset;
+        self.failures.push((Some(index), offset));
+    }
+
+    /// Record the failure and split the section into its prefixes and the
+    /// rest
+    fn degrade(
+        &mut self,
+        index: usize,
+        err: peg::error::ParseError<LineCol>,
+    ) -> (SectionWrapper<()>, &'source str) {
+        self.fail(index, err);
+        let src = self.section(index);
+        let (prefixes, end) = numfmt_parser::section_prefixes(src, self.dialect)
+            .expect("prefixes are optional, so any input matches");
+        (prefixes, &src[end..])
+    }
+}
+
+fn with_inner<T>(prefixes: SectionWrapper<()>, inner: T) -> SectionWrapper<T> {
+    SectionWrapper {
+        locale: prefixes.locale,
+        color: prefixes.color,
+        special_prefix: prefixes.special_prefix,
+        inner,
+    }
+}
+
+/// A currency symbol written with its locale, like `[$€-407]`, split into
+/// the locale and any text after it
+pub(crate) fn split_locale_id(symbol: &str) -> Option<(PartLocaleID, &str)> {
+    let (locale, end) = numfmt_parser::leading_locale_id(symbol, Dialect::default()).ok()?;
+    Some((locale, &symbol[end..]))
+}
+
+fn literal_text(text: &str) -> FormatComponent {
+    let elements = if text.is_empty() {
+        vec![]
+    } else {
+        vec![TextFormatElement::LiteralString(text.to_string())]
+    };
+    FormatComponent::Text(NFText { elements })
+}
+
 // The comment "Line xx" refers to the line number
 // in the original ABNF specification.
 //
@@ -24,7 +302,7 @@ impl<'source> NumfmtParser<'source> {
 //
 // https://learn.microsoft.com/en-us/openspecs/office_standards/ms-oe376/0e59abdb-7f4e-48fc-9b89-67832fa11789
 peg::parser! {
-    grammar numfmt_parser() for str {
+    grammar numfmt_parser(dialect: Dialect) for str {
 
         rule traced<T>(e: rule<T>) -> T =
             &(input:$([_]*) {
@@ -80,15 +358,19 @@ peg::parser! {
             }
 
             rule all_f4() -> FormatComponent // Custom
-                = t:nf_text() { FormatComponent::Text(t) }
-                / f:nf_general() { FormatComponent::General() }
+                = f:nf_general() section_end() { FormatComponent::General() }
+                / t:nf_text() { FormatComponent::Text(t) }
 
             rule format_component() -> FormatComponent // Custom
                 = f:nf_fraction() { FormatComponent::Fraction(f) }
                 / n:nf_number() { FormatComponent::Number(n) }
                 / dt:datetime_tuple() { FormatComponent::Datetime(dt) }
+                / g:nf_general() section_end() { FormatComponent::General() }
                 / t:nf_text() { FormatComponent::Text(t) }
-                / g:nf_general() { FormatComponent::General() }
+
+            // General on its own is a General section, anything around it
+            // turns the section into text with a General element inside
+            rule section_end() = &ascii_semicolon() / ![_]
 
             rule datetime_tuple() -> DatetimeTuple // Custom
                 = dt1:nf_datetime()? g:nf_general()? dt2:nf_datetime()? {?
@@ -98,6 +380,181 @@ peg::parser! {
                     Ok(DatetimeTuple(dt1, g, dt2))
                 }
 
+        // Single sections, for recovering from a format that does not parse
+        // as a whole. A section that fails here still keeps its prefixes,
+        // the rest of it is read as literal text.
+        pub rule section_any() -> Any = s:nf_any() ![_] { s }
+
+        pub rule section_no_cond() -> AnyNoCond = s:nf_any_no_cond() ![_] { s }
+
+        pub rule section_last() -> FormatComponent = f:all_f4() ![_] { f }
+
+        pub rule section_prefixes() -> (SectionWrapper<()>, usize)
+            = special_prefix:nf_part_special_prefix()* locale:nf_part_locale_id()? color:nf_part_color()? end:position!() [_]* {
+                (SectionWrapper { locale, color, special_prefix, inner: () }, end)
+            }
+
+        pub rule section_condition() -> (NFPartCondition, usize)
+            = cond:nf_part_cond() end:position!() [_]* { (cond, end) }
+
+        pub rule leading_locale_id() -> (PartLocaleID, usize)
+            = locale:nf_part_locale_id() end:position!() [_]* { (locale, end) }
+
+        // Tokens of a section that does not parse, read one at a time with
+        // the leaf rules of the grammar. Characters no token starts with are
+        // errors, as are unclosed quotes and brackets.
+        pub rule flat_tokens() -> Vec<Spanned<TokenKind>>
+            = tokens:spanned(<flat_token()>)* { tokens }
+
+            rule flat_token() -> TokenKind
+                = ascii_semicolon() { TokenKind::SectionSeparator }
+                / nf_part_cond() { TokenKind::Condition }
+                / nf_part_color() { TokenKind::Color }
+                / nf_part_locale_id() { TokenKind::Locale }
+                / nf_abs_time_token() { TokenKind::DateToken }
+                / !"t" nf_part_special_prefix() { TokenKind::Prefix }
+                / "[" (!"]" [_])* "]" { TokenKind::Error }
+                / intl_numfmt_general() { TokenKind::General }
+                / intl_ampm() { TokenKind::DateToken }
+                / (ascii_capital_letter_e() / ascii_small_letter_e()) nf_part_sign() { TokenKind::Exponent }
+                / nf_part_num_token1() { TokenKind::DigitPlaceholder }
+                / (intl_char_decimal_sep() / intl_char_numgrp_sep() / ascii_solidus() / intl_char_time_sep()) {
+                    TokenKind::Separator
+                }
+                / nf_datetime_token() { TokenKind::DateToken }
+                / ascii_percent_sign() { TokenKind::Percent }
+                / ascii_commercial_at() { TokenKind::TextPlaceholder }
+                / literal_char() { TokenKind::Escape }
+                / literal_string() { TokenKind::Literal }
+                / literal_char_repeat() { TokenKind::Fill }
+                / literal_char_space() { TokenKind::Spacing }
+                / c:[_] {
+                    if c != '"' && c != '[' && dialect.bare_char(c) {
+                        TokenKind::Literal
+                    } else {
+                        TokenKind::Error
+                    }
+                }
+
+        // Concrete syntax tree, see `crate::syntax`. These rules mirror the
+        // ones building the AST and call the same leaf rules, only keeping
+        // the span of every node. They are run on formats that already
+        // parsed, so they do not validate anything themselves.
+        pub rule cst() -> Vec<SectionSyntax>
+            = sections:cst_section() ** ascii_semicolon() { sections }
+
+            rule spanned<T>(r: rule<T>) -> Spanned<T>
+                = start:position!() node:r() end:position!() { Spanned::new(node, start..end) }
+
+            rule cst_section() -> SectionSyntax
+                = start:position!() prefixes:cst_prefixes() body:cst_body()? end:position!() {
+                    let mut nodes = prefixes;
+                    nodes.extend(body.unwrap_or_default());
+                    SectionSyntax { span: start..end, nodes }
+                }
+
+            rule cst_prefixes() -> Vec<Spanned<SyntaxKind>>
+                = special:spanned(<nf_part_special_prefix()>)*
+                  locale:spanned(<nf_part_locale_id()>)?
+                  color:spanned(<nf_part_color()>)?
+                  cond:spanned(<nf_part_cond()>)? {
+                    special.into_iter().map(|p| p.map(SyntaxKind::SpecialPrefix))
+                        .chain(locale.map(|l| l.map(SyntaxKind::Locale)))
+                        .chain(color.map(|c| c.map(SyntaxKind::Color)))
+                        .chain(cond.map(|c| c.map(SyntaxKind::Condition)))
+                        .collect()
+                }
+
+            rule cst_body() -> Vec<Spanned<SyntaxKind>>
+                = &nf_fraction() nodes:cst_fraction() { nodes }
+                / &nf_number() nodes:cst_number() { nodes }
+                / &datetime_tuple() nodes:cst_datetime() { nodes }
+                / &(nf_general() section_end()) g:spanned(<nf_general()>) {
+                    vec![g.map(|_| SyntaxKind::General)]
+                }
+                / nodes:cst_text() { nodes }
+
+            rule cst_number() -> Vec<Spanned<SyntaxKind>>
+                = part1:cst_number_element()+ exp:(&scientific_notation() e:cst_exponent() { e })? {
+                    part1.into_iter().chain(exp.unwrap_or_default()).collect()
+                }
+
+            rule cst_number_element() -> Spanned<SyntaxKind>
+                = e:spanned(<nf_format_element()>) { e.map(SyntaxKind::Number) }
+
+            rule cst_exponent() -> Vec<Spanned<SyntaxKind>>
+                = start:position!() (ascii_capital_letter_e() / ascii_small_letter_e()) sgn:nf_part_sign() end:position!()
+                  part2:cst_number_element()+ {
+                    let mut nodes = vec![Spanned::new(SyntaxKind::Exponent(sgn), start..end)];
+                    nodes.extend(part2);
+                    nodes
+                }
+
+            rule cst_fraction() -> Vec<Spanned<SyntaxKind>>
+                = prefix:cst_fraction_element()* int_part:cst_num_only()+ sep:spanned(<nf_frac_separator()>)+ rest:cst_fraction_rest() {
+                    prefix.into_iter()
+                        .chain(int_part)
+                        .chain(sep.into_iter().map(|s| s.map(SyntaxKind::Number)))
+                        .chain(rest)
+                        .collect()
+                }
+                / prefix:cst_fraction_element()* rest:cst_fraction_rest() {
+                    prefix.into_iter().chain(rest).collect()
+                }
+
+            rule cst_num_only() -> Spanned<SyntaxKind>
+                = t:spanned(<nf_part_num_token1()>) {
+                    t.map(|t| SyntaxKind::Number(DigitPosOrOther::Digit(DigitPos::Digit(t))))
+                }
+
+            rule cst_fraction_element() -> Spanned<SyntaxKind>
+                = e:spanned(<nf_fraction_preffix_or_suffix_element()>) { e.map(SyntaxKind::Number) }
+
+            rule cst_fraction_rest() -> Vec<Spanned<SyntaxKind>>
+                = num:spanned(<nf_part_fraction()>) slash:spanned(<frac_slash()>) denom:spanned(<nf_part_fraction()>)
+                  suffix:cst_fraction_element()* ampm:spanned(<intl_ampm()>)* {
+                    [
+                        num.map(SyntaxKind::FractionPart),
+                        slash.map(|_| SyntaxKind::FractionSlash),
+                        denom.map(SyntaxKind::FractionPart),
+                    ]
+                    .into_iter()
+                    .chain(suffix)
+                    .chain(ampm.into_iter().map(|a| a.map(SyntaxKind::AmPm)))
+                    .collect()
+                }
+
+            rule cst_datetime() -> Vec<Spanned<SyntaxKind>>
+                = dt1:cst_nf_datetime()? g:spanned(<nf_general()>)? dt2:cst_nf_datetime()? {
+                    dt1.unwrap_or_default()
+                        .into_iter()
+                        .chain(g.map(|g| g.map(|_| SyntaxKind::DatetimeGeneral)))
+                        .chain(dt2.unwrap_or_default())
+                        .collect()
+                }
+
+            rule cst_nf_datetime() -> Vec<Spanned<SyntaxKind>>
+                = &nf_datetime() ampms:spanned(<intl_ampm()>)* components:spanned(<dt_token_or_component()>)+ {
+                    let components = ampms.into_iter()
+                        .map(|a| a.map(NFDatetimeComponent::AMPM))
+                        .chain(components)
+                        .collect::<Vec<_>>();
+                    // tell minutes from months the way the AST does
+                    let mut datetime = NFDatetime {
+                        components: components.iter().map(|c| c.node.clone()).collect(),
+                    };
+                    datetime.resolve_minutes();
+                    components.into_iter()
+                        .zip(datetime.components)
+                        .map(|(c, resolved)| Spanned::new(SyntaxKind::Datetime(resolved), c.span))
+                        .collect()
+                }
+
+            rule cst_text() -> Vec<Spanned<SyntaxKind>>
+                = elements:spanned(<nf_text_element()>)+ {
+                    elements.into_iter().map(|e| e.map(SyntaxKind::Text)).collect()
+                }
+
         rule nf_any() -> SectionWrapper<AnyInner> // Line 2
             = special_prefix:nf_part_special_prefix()* locale:nf_part_locale_id()? color:nf_part_color()? condition:nf_part_cond()? data:format_component() {
                 SectionWrapper {
@@ -140,7 +597,7 @@ peg::parser! {
             }
 
             rule scientific_notation() -> (Sign, Vec<DigitPosOrOther<Percent>>)
-                = quiet!{e:ascii_capital_letter_e() sgn:(ascii_plus_sign() { Sign::Plus } / ascii_hyphen_minus() { Sign::Minus }) part2:nf_part_num() { (sgn, part2) }}
+                = quiet!{e:(ascii_capital_letter_e() / ascii_small_letter_e()) sgn:(ascii_plus_sign() { Sign::Plus } / ascii_hyphen_minus() { Sign::Minus }) part2:nf_part_num() { (sgn, part2) }}
                 / expected!("scientific notation (E+n or E-n)")
 
         rule nf_datetime_token() -> NFDateTimeToken // Line 8
@@ -153,7 +610,7 @@ peg::parser! {
             / ss:nf_part_sub_second() { NFDateTimeToken::SubSecond(ss) }
             / m:nf_part_month() { NFDateTimeToken::Month(m) }
             // / m:nf_part_minute() { NFDateTimeToken::Minute(m) }
-            // minute can only be parsed in patterns below
+            // minute is resolved from month after parsing, see `nf_datetime`
             / cb:nf_part_calendar_b() { NFDateTimeToken::CalendarB(cb) }
             / a:nf_abs_time_token() { NFDateTimeToken::Abs(a) }
 
@@ -164,51 +621,29 @@ peg::parser! {
 
         rule nf_datetime() -> NFDatetime // Line 10
             = ampms:intl_ampm()* components:(dt_token_or_component())+ {?
-                let all_components = components.into_iter().flatten()
-                    .chain(ampms.into_iter().map(NFDatetimeComponent::AMPM))
+                let all_components = ampms.into_iter()
+                    .map(NFDatetimeComponent::AMPM)
+                    .chain(components)
                     .collect::<Vec<_>>();
                 if !all_components.iter().any(|c| matches!(c, NFDatetimeComponent::Token(_))) {
                     return Err("At least one token must be present");
                 }
-                Ok(NFDatetime { components: all_components })
-            }
-
-            rule dt_token_or_component() -> Vec<NFDatetimeComponent>
-                = h_m:nf_pattern_hour_minute() { h_m }
-                / m_s:nf_pattern_minute_second() { m_s }
-                / m_d:nf_pattern_month_day() { m_d }
-                / token:nf_datetime_token() { vec![NFDatetimeComponent::Token(token)] }
-                / component:nf_datetime_component() { vec![component] }
-
-            rule nf_pattern_hour_minute() -> Vec<NFDatetimeComponent>
-                = h:nf_part_hour() components:nf_datetime_component()* m:nf_part_minute_format() {
-                    let mut result = vec![NFDatetimeComponent::Token(NFDateTimeToken::Hour(h))];
-                    result.extend(components);
-                    result.push(NFDatetimeComponent::Token(NFDateTimeToken::Minute(m)));
-                    result
-                }
+                let mut datetime = NFDatetime { components: all_components };
+                datetime.resolve_minutes();
+                Ok(datetime)
+            }
 
-            rule nf_pattern_minute_second() -> Vec<NFDatetimeComponent>
-                = m:nf_part_minute_format() components:nf_datetime_component()* s:nf_part_second() {
-                    let mut result = vec![NFDatetimeComponent::Token(NFDateTimeToken::Minute(m))];
-                    result.extend(components);
-                    result.push(NFDatetimeComponent::Token(NFDateTimeToken::Second(s)));
-                    result
-                }
-
-            rule nf_pattern_month_day() -> Vec<NFDatetimeComponent>
-                = m:nf_part_minute_format() components:nf_datetime_component()* d:nf_part_day() {
-                    let mut result = vec![NFDatetimeComponent::Token(NFDateTimeToken::Month(MonthFormat::from_minute_format(m)))];
-                    result.extend(components);
-                    result.push(NFDatetimeComponent::Token(NFDateTimeToken::Day(d)));
-                    result
-                }
+            // `m` and `mm` are parsed as months, minutes are told apart
+            // afterwards by `NFDatetime::resolve_minutes`
+            rule dt_token_or_component() -> NFDatetimeComponent
+                = token:nf_datetime_token() { NFDatetimeComponent::Token(token) }
+                / component:nf_datetime_component() { component }
 
             rule nf_datetime_component() -> NFDatetimeComponent // Custom
                 = ampm:intl_ampm() { NFDatetimeComponent::AMPM(ampm) }
                 / lit_str:literal_string() { NFDatetimeComponent::Literal(lit_str) }
-                / date_sep:intl_char_date_sep() { NFDatetimeComponent::Literal(date_sep.to_string()) }
-                / time_sep:intl_char_time_sep() { NFDatetimeComponent::Literal(time_sep.to_string()) }
+                / date_sep:intl_char_date_sep() { NFDatetimeComponent::DateSeparator(date_sep) }
+                / time_sep:intl_char_time_sep() { NFDatetimeComponent::TimeSeparator(time_sep) }
                 / ascii_space() { NFDatetimeComponent::Literal(" ".to_string()) }
                 / ascii_comma() { NFDatetimeComponent::Literal(",".to_string()) }
                 / bc:unmatched_literal_char() { NFDatetimeComponent::Literal(bc.to_string()) }
@@ -220,6 +655,7 @@ peg::parser! {
 
             rule nf_text_element() -> TextFormatElement
                 = ascii_commercial_at() { TextFormatElement::AtPlaceholder }
+                / nf_general() { TextFormatElement::General }
                 / ampm_val:intl_ampm() { TextFormatElement::AmPm(ampm_val) }
                 / lcs:literal_char_space() { TextFormatElement::LiteralCharSpace(lcs) }
                 / ls:literal_string() { TextFormatElement::LiteralString(ls) }
@@ -228,7 +664,7 @@ peg::parser! {
                 / bc:unmatched_literal_char() { TextFormatElement::LiteralString(bc.to_string()) }
 
         rule nf_fraction() -> NFFraction // Line 12
-            = prefix:nf_fraction_preffix_or_suffix_element()* int_part:nf_num_only() sep:nf_frac_separator()+ num:nf_part_fraction() ascii_space()* ascii_solidus() ascii_space()* denom:nf_part_fraction() suffix:nf_fraction_preffix_or_suffix_element()* ampm:intl_ampm()* {
+            = prefix:nf_fraction_preffix_or_suffix_element()* int_part:nf_num_only() sep:nf_frac_separator()+ num:nf_part_fraction() frac_slash() denom:nf_part_fraction() suffix:nf_fraction_preffix_or_suffix_element()* ampm:intl_ampm()* {
                 NFFraction {
                     prefix,
                     integer_part: Some(int_part),
@@ -239,7 +675,7 @@ peg::parser! {
                     ampm_part: ampm,
                 }
             }
-            / prefix:nf_fraction_preffix_or_suffix_element()* num:nf_part_fraction() ascii_space()* ascii_solidus() ascii_space()* denom:nf_part_fraction() suffix:nf_fraction_preffix_or_suffix_element()* ampm:intl_ampm()* {
+            / prefix:nf_fraction_preffix_or_suffix_element()* num:nf_part_fraction() frac_slash() denom:nf_part_fraction() suffix:nf_fraction_preffix_or_suffix_element()* ampm:intl_ampm()* {
                 NFFraction {
                     prefix,
                     integer_part: None,
@@ -251,6 +687,8 @@ peg::parser! {
                 }
             }
 
+            rule frac_slash() -> () = ascii_space()* ascii_solidus() ascii_space()* { }
+
             rule nf_num_only() -> Vec<DigitPosOrOther<Percent>>
                 = tks:nf_part_num_token1()+ {
                     tks.into_iter().map(|t| DigitPosOrOther::Digit(DigitPos::Digit(t))).collect()
@@ -300,77 +738,63 @@ peg::parser! {
             = ascii_capital_letter_e() sgn:nf_part_sign() { sgn }
 
         rule nf_part_year() -> YearFormat // Line 15
-            = "yyyy" { YearFormat::FourDigit }
-            / "YYYY" { YearFormat::FourDigit }
-            / "yy" { YearFormat::TwoDigit }
-            / "YY" { YearFormat::TwoDigit }
+            = ['y' | 'Y']*<4> { YearFormat::FourDigit }
+            / ['y' | 'Y']*<2> { YearFormat::TwoDigit }
 
         rule nf_part_era_g() -> EraFormatG // Custom
-            = "g" { EraFormatG::OneDigit }
-            / "gg" { EraFormatG::TwoDigit }
-            / "ggg" { EraFormatG::ThreeDigit }
+            = allowed(dialect.era_tokens()) era:(
+                "ggg" { EraFormatG::ThreeDigit }
+                / "gg" { EraFormatG::TwoDigit }
+                / "g" { EraFormatG::OneDigit }
+            ) { era }
 
         rule nf_part_era_year() -> EraYearFormat // Custom
-            = "e" { EraYearFormat::OneDigit }
-            / "ee" { EraYearFormat::TwoDigit }
+            = allowed(dialect.era_tokens()) era_year:(
+                "ee" { EraYearFormat::TwoDigit }
+                / "e" { EraYearFormat::OneDigit }
+            ) { era_year }
 
         rule nf_part_calendar_b() -> CalendarTypeB // Custom
-            = "b1" { CalendarTypeB::Gregorian }
-            / "b2" { CalendarTypeB::Hijri }
+            = allowed(dialect.era_tokens()) calendar:(
+                "b1" { CalendarTypeB::Gregorian }
+                / "b2" { CalendarTypeB::Hijri }
+            ) { calendar }
             / expected!("calendar type (b1 or b2)")
 
         rule nf_part_month() -> MonthFormat // Line 16
-            = m:(ascii_small_letter_m()*<1,5>) {
-                MonthFormat(m.len() as u8)
-            }
-            / m:(ascii_capital_letter_m()*<1,5>) {
+            = m:(letter_m()*<1,5>) {
                 MonthFormat(m.len() as u8)
             }
 
         rule nf_part_day() -> DayFormat // Line 17
-            = d:(ascii_small_letter_d()*<1,4>) {
-                DayFormat(d.len() as u8)
-            }
-            / d:(ascii_capital_letter_d()*<1,4>) {
+            = d:(letter_d()*<1,4>) {
                 DayFormat(d.len() as u8)
             }
 
         rule nf_part_hour() -> HourFormat // Line 18
-            = "hh" { HourFormat::TwoChar }
-            / "HH" { HourFormat::TwoChar }
-            / "h" { HourFormat::OneChar }
-            / "H" { HourFormat::OneChar }
+            = letter_h()*<2> { HourFormat::TwoChar }
+            / letter_h() { HourFormat::OneChar }
 
         rule nf_part_abs_hour() -> AbsHourFormat // Line 19
-            = ascii_left_square_bracket() h:ascii_small_letter_h()+ ascii_right_square_bracket() {
+            = ascii_left_square_bracket() h:letter_h()+ ascii_right_square_bracket() {
                 AbsHourFormat(h.len() as u8)
             }
 
         rule nf_part_minute() -> MinuteFormat // Line 20
-            = "mm" { MinuteFormat::TwoChar }
-            / "MM" { MinuteFormat::TwoChar }
-            / "m" { MinuteFormat::OneChar }
-            / "M" { MinuteFormat::OneChar }
-
-        rule nf_part_minute_format() -> MinuteFormat // Helper rule to parse m/mm without interpreting it
-            = "mm" { MinuteFormat::TwoChar }
-            / "MM" { MinuteFormat::TwoChar }
-            / "m" { MinuteFormat::OneChar }
-            / "M" { MinuteFormat::OneChar }
+            = letter_m()*<2> { MinuteFormat::TwoChar }
+            / letter_m() { MinuteFormat::OneChar }
 
         rule nf_part_abs_minute() -> AbsMinuteFormat // Line 21
-            = ascii_left_square_bracket() m:ascii_small_letter_m()+ ascii_right_square_bracket() {
+            = ascii_left_square_bracket() m:letter_m()+ ascii_right_square_bracket() {
                 AbsMinuteFormat(m.len() as u8)
             }
 
         rule nf_part_second() -> SecondFormat // Line 22
-            = "ss" { SecondFormat::TwoChar }
-            / "SS" { SecondFormat::TwoChar }
-            / "s" { SecondFormat::OneChar }
-            / "S" { SecondFormat::OneChar }
+            = letter_s()*<2> { SecondFormat::TwoChar }
+            / letter_s() { SecondFormat::OneChar }
 
         rule nf_part_abs_second() -> AbsSecondFormat // Line 23
-            = ascii_left_square_bracket() s:ascii_small_letter_s()+ ascii_right_square_bracket() {
+            = ascii_left_square_bracket() s:letter_s()+ ascii_right_square_bracket() {
                 AbsSecondFormat(s.len() as u8)
             }
 
@@ -381,8 +805,8 @@ peg::parser! {
             / expected!("sub-second (.0, .00, or .000)")
 
         rule nf_part_cond() -> NFPartCondition // Line 25
-            = ascii_left_square_bracket() op:nf_part_comp_oper() value:nf_part_cond_num() ascii_right_square_bracket() {
-                NFPartCondition { op, value }
+            = ascii_left_square_bracket() op:nf_part_comp_oper() value:&nf_part_cond_num() text:$(nf_part_cond_num()) ascii_right_square_bracket() {
+                NFPartCondition { op, value, value_text: Some(text.to_string()) }
             }
 
         rule nf_part_comp_oper() -> NFCondOperator // Line 26
@@ -402,7 +826,7 @@ peg::parser! {
                 ascii_left_square_bracket()
                 ascii_dollar_sign()
                 name_chars:currency_symbol_char()*
-                hex_digits_opt:(ascii_hyphen_minus() v:nf_part_locale_id_hex_value() {v})?
+                hex_digits_opt:(ascii_hyphen_minus() v:$(nf_part_locale_id_hex_value()) {v})?
                 ascii_right_square_bracket()
                 {
                     PartLocaleID::from_parsed_peg(name_chars, hex_digits_opt)
@@ -537,9 +961,9 @@ peg::parser! {
 
         rule nf_part_str_color() -> () // Line 39
             // = ascii_capital_letter_c() ascii_small_letter_o() ascii_small_letter_l() ascii_small_letter_o() ascii_small_letter_r() { }
-            = "Color" { }
+            = keyword("Color") { }
             // zh_CN L10n
-            / "颜色" { }
+            / allowed(dialect.localized_colors()) "颜色" { }
 
         rule literal_char() -> char // Line 40
             = ascii_reverse_solidus() c:utf16_any() { c }
@@ -571,21 +995,21 @@ peg::parser! {
             = ascii_colon() { ':' }
 
         rule intl_color() -> DefinedColor // Line 49
-            = "Black" { DefinedColor::Black }
-            / "Blue" { DefinedColor::Blue }
-            / "Cyan" { DefinedColor::Cyan }
-            / "Green" { DefinedColor::Green }
-            / "Magenta" { DefinedColor::Magenta }
-            / "Red" { DefinedColor::Red }
-            / "White" { DefinedColor::White }
-            / "Yellow" { DefinedColor::Yellow }
+            = keyword("Black") { DefinedColor::Black }
+            / keyword("Blue") { DefinedColor::Blue }
+            / keyword("Cyan") { DefinedColor::Cyan }
+            / keyword("Green") { DefinedColor::Green }
+            / keyword("Magenta") { DefinedColor::Magenta }
+            / keyword("Red") { DefinedColor::Red }
+            / keyword("White") { DefinedColor::White }
+            / keyword("Yellow") { DefinedColor::Yellow }
 
         rule intl_numfmt_general() -> () // Line 50
-            = "General" { }
+            = keyword("General") { }
 
         rule intl_ampm() -> AmPm // Line 51
-            = "AM/PM" { AmPm::Full }
-            / "A/P" { AmPm::Simple }
+            = s:keyword("AM/PM") { AmPm::Full(s.to_string()) }
+            / s:keyword("A/P") { AmPm::Simple(s.to_string()) }
             / expected!("AM/PM or A/P")
 
         rule utf16_any() -> char // Line 52
@@ -594,7 +1018,9 @@ peg::parser! {
         rule unmatched_literal_char() -> char
             = !nf_general() !nf_datetime_token() !intl_ampm() !nf_part_num_token1()
             !nf_abs_time_token() !nf_part_num_token2() !ascii_solidus() !ascii_commercial_at()
-            !ascii_semicolon() !nf_part_exponential() !nf_part_fraction() c:utf16_any() { c }
+            !ascii_semicolon() !nf_part_exponential() !nf_part_fraction() c:utf16_any() {?
+                if dialect.bare_char(c) { Ok(c) } else { Err("quoted text") }
+            }
 
         rule ascii_space() -> ()
             = [' '] { }
@@ -925,6 +1351,28 @@ peg::parser! {
 
         // Custom Part
 
+        // Fails when the dialect does not accept the construct that follows
+        rule allowed(accepted: bool) = {? if accepted { Ok(()) } else { Err("another dialect") } }
+
+        // Keywords are matched regardless of case, the spelling is returned
+        // for nodes that keep it
+        rule keyword(literal: &'static str) -> &'input str
+            = input:$([_]*<{literal.len()}>) {?
+                if input.eq_ignore_ascii_case(literal) {
+                    Ok(input)
+                } else {
+                    Err(literal)
+                }
+            }
+
+        rule letter_d() -> () = ascii_small_letter_d() / ascii_capital_letter_d()
+
+        rule letter_h() -> () = ascii_small_letter_h() / ascii_capital_letter_h()
+
+        rule letter_m() -> () = ascii_small_letter_m() / ascii_capital_letter_m()
+
+        rule letter_s() -> () = ascii_small_letter_s() / ascii_capital_letter_s()
+
         rule uint() -> u128
             = digits:ascii_digit()+ {
                 digits.iter().fold(
@@ -933,27 +1381,18 @@ peg::parser! {
                 )
             }
 
-        rule nf_part_special_prefix() -> String
-            = "t" { "t".to_string() }
-            / "[" prefix:known_special_prefix() "]" { prefix }
-
-        rule known_special_prefix() -> String
-            = "ENG" { "ENG".to_string() }
-            / "DBNum1" { "DBNum1".to_string() }
-            / "DBNum2" { "DBNum2".to_string() }
-            / "DBNum3" { "DBNum3".to_string() }
-            / "HIJ" { "HIJ".to_string() }
-            / "JPN" { "JPN".to_string() }
-            / "TWN" { "TWN".to_string() }
-            / "MAGENTA" { "MAGENTA".to_string() }
-            / "WHITE" { "WHITE".to_string() }
-            / "CYAN" { "CYAN".to_string() }
-            / "BLACK" { "BLACK".to_string() }
-            / "BLUE" { "BLUE".to_string() }
-            / "GREEN" { "GREEN".to_string() }
-            / "YELLOW" { "YELLOW".to_string() }
-            / !intl_color() !nf_part_str_color() !['>'] !['<'] !['='] !['$'] chars:utf16_any()+ {
-                chars.into_iter().collect()
+        rule nf_part_special_prefix() -> SectionModifier
+            = allowed(dialect.special_prefixes()) "t" { SectionModifier::Thai }
+            / allowed(dialect.special_prefixes()) !nf_abs_time_token() "[" modifier:known_special_prefix() "]" { modifier }
+
+        rule known_special_prefix() -> SectionModifier
+            = keyword("ENG") { SectionModifier::English }
+            / keyword("DBNum") n:['1'..='3'] { SectionModifier::DBNum(n as u8 - b'0') }
+            / keyword("HIJ") { SectionModifier::Hijri }
+            / keyword("JPN") { SectionModifier::Japanese }
+            / keyword("TWN") { SectionModifier::Taiwan }
+            / allowed(dialect.unknown_brackets()) !intl_color() !keyword("Color") !"颜色" !['>'] !['<'] !['='] !['$'] chars:unknown_prefix_char()+ {
+                SectionModifier::Unknown(chars.into_iter().collect())
             }
 
         rule unknown_prefix_char() -> char
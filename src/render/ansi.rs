@@ -0,0 +1,34 @@
+//! ANSI terminal renderer
+
+use crate::formatter::{Alignment, FormattedCell};
+use crate::palette::Palette;
+
+/// Render a cell for a terminal, padded to `width` columns when given.
+/// The section color is written as a 24-bit SGR foreground color resolved
+/// through `palette`, the fill character is repeated to the column width
+/// and the value is aligned the way Excel aligns it.
+pub fn to_ansi(cell: &FormattedCell, width: Option<usize>, palette: &Palette) -> String {
+    let content = cell.to_text(width);
+    let padding = match (width, cell.fill()) {
+        (Some(width), None) => width.saturating_sub(cell.width()),
+        _ => 0,
+    };
+
+    let mut result = String::new();
+    if cell.alignment == Alignment::Right {
+        result.extend(std::iter::repeat_n(' ', padding));
+    }
+    match cell.color.as_ref().and_then(|color| palette.resolve(color)) {
+        Some(rgb) => {
+            result.push_str(&format!("\x1b[38;2;{};{};{}m", rgb.r, rgb.g, rgb.b));
+            result.push_str(&content);
+            result.push_str("\x1b[39m");
+        }
+        None => result.push_str(&content),
+    }
+    if cell.alignment == Alignment::Left {
+        result.extend(std::iter::repeat_n(' ', padding));
+    }
+    result
+}
+
@@ -0,0 +1,68 @@
+//! HTML renderer
+
+use crate::formatter::{Alignment, CellPart, FormattedCell};
+use crate::palette::Palette;
+
+/// Times the fill character is written in its span. The span is clipped to
+/// the free width of the cell, so this only has to exceed any column.
+const FILL_REPEAT: usize = 256;
+
+/// Render a cell as an HTML element laid out like Excel shows it. The cell
+/// stretches to its container, the fill character takes the free width and
+/// `_x` blanks keep the width of their character.
+pub fn to_html(cell: &FormattedCell, palette: &Palette) -> String {
+    let (align, justify) = match cell.alignment {
+        Alignment::Left => ("left", "flex-start"),
+        Alignment::Right => ("right", "flex-end"),
+    };
+    let mut style = format!(
+        "display:flex;justify-content:{};text-align:{};white-space:pre;overflow:hidden",
+        justify, align
+    );
+    if let Some(rgb) = cell.color.as_ref().and_then(|color| palette.resolve(color)) {
+        style.push_str(";color:");
+        style.push_str(&rgb.to_hex());
+    }
+
+    let mut result = format!("<div style=\"{}\">", style);
+    let mut filled = false;
+    for part in &cell.parts {
+        match part {
+            CellPart::Text(text) => {
+                result.push_str("<span>");
+                result.push_str(&escape_html(text));
+                result.push_str("</span>");
+            }
+            CellPart::Fill(c) if !filled => {
+                filled = true;
+                result.push_str("<span style=\"flex:1 1 0;min-width:0;overflow:hidden\">");
+                result.push_str(&escape_html(&c.to_string()).repeat(FILL_REPEAT));
+                result.push_str("</span>");
+            }
+            CellPart::Fill(_) => {}
+            CellPart::Space(c) => {
+                result.push_str("<span style=\"visibility:hidden\">");
+                result.push_str(&escape_html(&c.to_string()));
+                result.push_str("</span>");
+            }
+        }
+    }
+    result.push_str("</div>");
+    result
+}
+
+/// Escape the characters with a meaning in HTML text and attributes
+fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
@@ -0,0 +1,7 @@
+//! Renderers laying out formatted cells for display
+
+mod ansi;
+mod html;
+
+pub use ansi::to_ansi;
+pub use html::to_html;
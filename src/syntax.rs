@@ -0,0 +1,235 @@
+//! Concrete syntax tree of a format string
+//!
+//! The AST keeps what a format means, the syntax tree keeps how it was
+//! written: every node records the byte range it was parsed from, so the
+//! source text of a literal tells `\-` and `"-"` apart. Sections are split
+//! on `;`, which is not part of any section span.
+
+use std::fmt;
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::types::*;
+
+/// A node with the byte range of the source it was parsed from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Range<usize>,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Range<usize>) -> Self {
+        Spanned { node, span }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned {
+            node: f(self.node),
+            span: self.span,
+        }
+    }
+}
+
+/// Kind of a syntax node, holding the AST value parsed from its source
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SyntaxKind {
+    /// `t` or a bracketed prefix such as `[DBNum1]`
+    SpecialPrefix(SectionModifier),
+    /// `[$USD-409]` at the start of a section
+    Locale(PartLocaleID),
+    Color(NFPartColor),
+    Condition(NFPartCondition),
+    /// `General` on its own
+    General,
+    /// Element of a number section, or of the integer part and the
+    /// surroundings of a fraction
+    Number(DigitPosOrOther<Percent>),
+    /// `E+` or `E-` of a scientific format
+    Exponent(Sign),
+    /// Numerator or denominator of a fraction
+    FractionPart(Vec<FracToken>),
+    /// The `/` of a fraction, with the spaces around it
+    FractionSlash,
+    /// `AM/PM` or `A/P` following a fraction
+    AmPm(AmPm),
+    /// Element of a date or time section
+    Datetime(NFDatetimeComponent),
+    /// `General` between the parts of a date or time section
+    DatetimeGeneral,
+    /// Element of a text section
+    Text(TextFormatElement),
+}
+
+/// One section of a format string
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SectionSyntax {
+    pub span: Range<usize>,
+    /// Prefixes and body of the section, in source order
+    pub nodes: Vec<Spanned<SyntaxKind>>,
+}
+
+/// A parsed format string with the source of every node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyntaxTree<'source> {
+    pub source: &'source str,
+    pub format: NumFormat,
+    pub sections: Vec<SectionSyntax>,
+}
+
+impl<'source> SyntaxTree<'source> {
+    /// Source text of a span
+    pub fn text(&self, span: &Range<usize>) -> Result<&'source str, RewriteError> {
+        self.source
+            .get(span.clone())
+            .ok_or_else(|| RewriteError::OutOfRange(span.clone()))
+    }
+
+    /// Section and node found at a byte offset, if any
+    pub fn node_at(&self, offset: usize) -> Option<(usize, &Spanned<SyntaxKind>)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .flat_map(|(index, section)| section.nodes.iter().map(move |node| (index, node)))
+            .find(|(_, node)| node.span.contains(&offset))
+    }
+
+    /// Replace the given spans of the source, keeping everything else as it
+    /// was written. Spans must not overlap.
+    pub fn rewrite<S: AsRef<str>>(
+        &self,
+        edits: impl IntoIterator<Item = (Range<usize>, S)>,
+    ) -> Result<String, RewriteError> {
+        let mut edits = edits.into_iter().collect::<Vec<_>>();
+        edits.sort_by_key(|(span, _)| span.start);
+
+        let mut result = String::with_capacity(self.source.len());
+        let mut previous = 0..0;
+        for (span, replacement) in edits {
+            if span.start < previous.end {
+                return Err(RewriteError::Overlapping(previous, span));
+            }
+            result.push_str(self.text(&(previous.end..span.start))?);
+            self.text(&span)?;
+            result.push_str(replacement.as_ref());
+            previous = span;
+        }
+        result.push_str(self.text(&(previous.end..self.source.len()))?);
+        Ok(result)
+    }
+}
+
+/// Failure to read or rewrite a span of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewriteError {
+    /// The span ends past the source, or splits a character
+    OutOfRange(Range<usize>),
+    /// Two edits replace overlapping spans
+    Overlapping(Range<usize>, Range<usize>),
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::OutOfRange(span) => write!(f, "Span {:?} is not in the source", span),
+            RewriteError::Overlapping(first, second) => {
+                write!(f, "Edits at {:?} and {:?} overlap", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RewriteError {}
+
+/// Category of a token, for syntax highlighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TokenKind {
+    /// `0`, `#`, `?`, or a fraction numerator or denominator
+    DigitPlaceholder,
+    /// Decimal and grouping separators, date and time separators and the
+    /// `/` of fractions
+    Separator,
+    /// `%`
+    Percent,
+    /// `E+` or `E-`
+    Exponent,
+    /// Date and time tokens, elapsed times and AM/PM markers
+    DateToken,
+    /// `General`
+    General,
+    /// `@`
+    TextPlaceholder,
+    /// Quoted or bare text
+    Literal,
+    /// `\x`
+    Escape,
+    /// `*x`
+    Fill,
+    /// `_x`
+    Spacing,
+    /// `t` or a bracketed prefix such as `[DBNum1]`
+    Prefix,
+    Color,
+    Condition,
+    /// `[$USD-409]`, as a prefix or a currency
+    Locale,
+    /// `;`
+    SectionSeparator,
+    /// Text that cannot be read as part of a format
+    Error,
+}
+
+impl SyntaxKind {
+    /// Token category of a node, given its source text
+    pub(crate) fn token_kind(&self, text: &str) -> TokenKind {
+        // `\x` runs are read as literal strings
+        let literal = || {
+            if text.starts_with('\\') {
+                TokenKind::Escape
+            } else {
+                TokenKind::Literal
+            }
+        };
+        match self {
+            SyntaxKind::SpecialPrefix(_) => TokenKind::Prefix,
+            SyntaxKind::Locale(_) => TokenKind::Locale,
+            SyntaxKind::Color(_) => TokenKind::Color,
+            SyntaxKind::Condition(_) => TokenKind::Condition,
+            SyntaxKind::General | SyntaxKind::DatetimeGeneral => TokenKind::General,
+            SyntaxKind::Number(element) => match element {
+                DigitPosOrOther::Digit(DigitPos::Digit(_)) => TokenKind::DigitPlaceholder,
+                DigitPosOrOther::Digit(DigitPos::Separator(_)) => TokenKind::Separator,
+                DigitPosOrOther::Other(Percent) => TokenKind::Percent,
+                DigitPosOrOther::Currency(_) => TokenKind::Locale,
+                DigitPosOrOther::LiteralCharSpace(_) => TokenKind::Spacing,
+                DigitPosOrOther::LiteralString(_) => literal(),
+                DigitPosOrOther::FillChar(_) => TokenKind::Fill,
+                DigitPosOrOther::EscapedChar(_) => TokenKind::Escape,
+            },
+            SyntaxKind::Exponent(_) => TokenKind::Exponent,
+            SyntaxKind::FractionPart(_) => TokenKind::DigitPlaceholder,
+            SyntaxKind::FractionSlash => TokenKind::Separator,
+            SyntaxKind::AmPm(_) => TokenKind::DateToken,
+            SyntaxKind::Datetime(component) => match component {
+                NFDatetimeComponent::Token(_) | NFDatetimeComponent::AMPM(_) => {
+                    TokenKind::DateToken
+                }
+                NFDatetimeComponent::DateSeparator(_) | NFDatetimeComponent::TimeSeparator(_) => {
+                    TokenKind::Separator
+                }
+                NFDatetimeComponent::Literal(_) => literal(),
+            },
+            SyntaxKind::Text(element) => match element {
+                TextFormatElement::AtPlaceholder => TokenKind::TextPlaceholder,
+                TextFormatElement::General => TokenKind::General,
+                TextFormatElement::AmPm(_) => TokenKind::DateToken,
+                TextFormatElement::LiteralString(_) => literal(),
+                TextFormatElement::LiteralCharSpace(_) => TokenKind::Spacing,
+                TextFormatElement::FillChar(_) => TokenKind::Fill,
+                TextFormatElement::EscapedChar(_) => TokenKind::Escape,
+                TextFormatElement::BareChar(_) => TokenKind::Literal,
+            },
+        }
+    }
+}
@@ -1,28 +1,48 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SectionWrapper<T> {
     pub locale: Option<PartLocaleID>,
     pub color: Option<NFPartColor>,
-    pub special_prefix: Vec<String>,  // 统一存储 "t", "ENG", "DBNum1", "HIJ", "JPN", "TWN" 等
+    pub special_prefix: Vec<SectionModifier>,
     pub inner: T,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Prefix changing how a whole section is shown, written before its locale
+/// and color
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SectionModifier {
+    /// `t`, Thai digits
+    Thai,
+    /// `[DBNum1]` to `[DBNum3]`, Chinese and Japanese numerals
+    DBNum(u8),
+    /// `[ENG]`, English month and day names whatever the locale
+    English,
+    /// `[HIJ]`, Hijri calendar
+    Hijri,
+    /// `[JPN]`, Japanese era calendar
+    Japanese,
+    /// `[TWN]`, Republic of China calendar
+    Taiwan,
+    /// Any other bracketed text, such as LibreOffice's `[NatNum1]`
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TextOr<T> {
     Text(NFText),
     Other(T),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Sign {
     Plus,
     Minus,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum DefinedColor {
     Black,
@@ -35,13 +55,13 @@ pub enum DefinedColor {
     Yellow,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum NFPartColor {
     Intl(DefinedColor),
     Color(u8),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum NFCondOperator {
     Equal,
@@ -53,10 +73,12 @@ pub enum NFCondOperator {
 }
 
 /// [>=1.0]
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NFPartCondition {
     pub op: NFCondOperator,
     pub value: f64,
+    /// `value` as written in the format, such as `1.5E+3`
+    pub value_text: Option<String>,
 }
 
 impl Eq for NFPartCondition {}
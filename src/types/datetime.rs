@@ -1,20 +1,65 @@
 use crate::types::NFGeneral;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// [NFDateTime] [NFGeneral] [NFDateTime]
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DatetimeTuple(
     pub Option<NFDatetime>,
     pub Option<NFGeneral>,
     pub Option<NFDatetime>,
 );
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NFDatetime {
     pub components: Vec<NFDatetimeComponent>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+impl NFDatetime {
+    /// Turn `m`/`mm` month tokens into minutes using Excel's rule: the token
+    /// means minutes when the previous token is an hour (`h`, `hh`, `[h]`) or
+    /// the next token is a second (`s`, `ss`). Literals, separators and AM/PM
+    /// markers between the tokens are ignored.
+    pub(crate) fn resolve_minutes(&mut self) {
+        let token_indices = self
+            .components
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| matches!(c, NFDatetimeComponent::Token(_)))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let token_at = |components: &[NFDatetimeComponent], i: usize| match components[i] {
+            NFDatetimeComponent::Token(token) => token,
+            _ => unreachable!(),
+        };
+
+        let mut minutes = Vec::new();
+        for (k, &i) in token_indices.iter().enumerate() {
+            let NFDateTimeToken::Month(month) = token_at(&self.components, i) else {
+                continue;
+            };
+            let Some(minute) = MinuteFormat::from_month_format(month) else {
+                continue;
+            };
+            let after_hour = k > 0
+                && matches!(
+                    token_at(&self.components, token_indices[k - 1]),
+                    NFDateTimeToken::Hour(_) | NFDateTimeToken::Abs(AbsTimeToken::AbsHour(_))
+                );
+            let before_second = token_indices.get(k + 1).is_some_and(|&next| {
+                matches!(token_at(&self.components, next), NFDateTimeToken::Second(_))
+            });
+            if after_hour || before_second {
+                minutes.push((i, minute));
+            }
+        }
+
+        for (i, minute) in minutes {
+            self.components[i] = NFDatetimeComponent::Token(NFDateTimeToken::Minute(minute));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NFDatetimeComponent {
     Token(NFDateTimeToken),
     DateSeparator(char),
@@ -23,21 +68,42 @@ pub enum NFDatetimeComponent {
     Literal(String),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
-#[repr(u8)]
+/// AM/PM marker, keeping the spelling used in the format string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AmPm {
-    Full,
-    Simple,
+    /// `AM/PM`, in any casing
+    Full(String),
+    /// `A/P`, in any casing
+    Simple(String),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+impl AmPm {
+    /// Text shown in place of the marker. Like Excel, `AM/PM` is always
+    /// rendered in capitals while `A/P` reuses the letters as written,
+    /// so `a/p` gives `a` or `p`.
+    pub fn marker(&self, is_pm: bool) -> String {
+        match self {
+            AmPm::Full(_) => if is_pm { "PM" } else { "AM" }.to_string(),
+            AmPm::Simple(spelling) => {
+                let letter = if is_pm {
+                    spelling.chars().nth(2).unwrap_or('P')
+                } else {
+                    spelling.chars().next().unwrap_or('A')
+                };
+                letter.to_string()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AbsTimeToken {
     AbsHour(AbsHourFormat),
     AbsMinute(AbsMinuteFormat),
     AbsSecond(AbsSecondFormat),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NFDateTimeToken {
     EraG(EraFormatG),
     CalendarB(CalendarTypeB),
@@ -52,76 +118,78 @@ pub enum NFDateTimeToken {
     Abs(AbsTimeToken),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EraFormatG {
     OneDigit,
     TwoDigit,
     ThreeDigit,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CalendarTypeB {
     Gregorian,
     Hijri,
 }
 
 /// 1-5
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MonthFormat(pub u8);
 
-impl MonthFormat {
-    pub(crate) fn from_minute_format(minute_format: MinuteFormat) -> Self {
-        match minute_format {
-            MinuteFormat::OneChar => MonthFormat(1),
-            MinuteFormat::TwoChar => MonthFormat(2),
-        }
-    }
-}
-
 /// 1-4
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DayFormat(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum HourFormat {
     OneChar,
     TwoChar,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AbsHourFormat(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum MinuteFormat {
     OneChar,
     TwoChar,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+impl MinuteFormat {
+    /// `m` and `mm` can be read as minutes, longer month tokens cannot
+    pub(crate) fn from_month_format(month_format: MonthFormat) -> Option<Self> {
+        match month_format.0 {
+            1 => Some(MinuteFormat::OneChar),
+            2 => Some(MinuteFormat::TwoChar),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AbsMinuteFormat(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum SecondFormat {
     OneChar,
     TwoChar,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AbsSecondFormat(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SubSecondFormat(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum YearFormat {
     TwoDigit,
     FourDigit,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EraYearFormat {
     OneDigit,
     TwoDigit,
@@ -0,0 +1,428 @@
+//! Printing of parsed formats back to format strings
+//!
+//! Printing a parsed format and parsing the result again gives the same
+//! AST. The spelling may differ from the source: literals are quoted,
+//! keywords use their canonical casing and `\-` becomes `-`.
+
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::parser::BARE_LITERALS;
+use crate::types::*;
+
+impl NumFormat {
+    /// Print the format back to a format string
+    pub fn to_format_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for NumFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NumFormat::ConditionalGeneral(section) => {
+                write_prefixes(f, section)?;
+                let (condition, general) = &section.inner;
+                write!(f, "{}{}", condition, general)
+            }
+            NumFormat::AnyNoCond(section) => write!(f, "{}", section),
+            NumFormat::TwoParts(positive, negative) => write!(f, "{};{}", positive, negative),
+            NumFormat::ThreeParts(positive, negative, zero) => {
+                write!(f, "{};{};{}", positive, negative, zero)
+            }
+            NumFormat::FourParts(positive, negative, zero, text) => {
+                write!(f, "{};{};{};", positive, negative, zero)?;
+                match text {
+                    Some(text) => write!(f, "{}", text),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for SectionWrapper<FormatComponent> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_prefixes(f, self)?;
+        write!(f, "{}", self.inner)
+    }
+}
+
+impl Display for SectionWrapper<AnyInner> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_prefixes(f, self)?;
+        let AnyInner::ConditionalData(condition, component) = &self.inner;
+        if let Some(condition) = condition {
+            write!(f, "{}", condition)?;
+        }
+        write!(f, "{}", component)
+    }
+}
+
+/// Write the prefixes of a section in the order the grammar expects them
+fn write_prefixes<T>(f: &mut Formatter<'_>, section: &SectionWrapper<T>) -> fmt::Result {
+    for prefix in &section.special_prefix {
+        write!(f, "{}", prefix)?;
+    }
+    if let Some(locale) = &section.locale {
+        write!(f, "{}", locale)?;
+    }
+    if let Some(color) = &section.color {
+        write!(f, "{}", color)?;
+    }
+    Ok(())
+}
+
+impl Display for SectionModifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SectionModifier::Thai => f.write_str("t"),
+            SectionModifier::DBNum(n) => write!(f, "[DBNum{}]", n),
+            SectionModifier::English => f.write_str("[ENG]"),
+            SectionModifier::Hijri => f.write_str("[HIJ]"),
+            SectionModifier::Japanese => f.write_str("[JPN]"),
+            SectionModifier::Taiwan => f.write_str("[TWN]"),
+            SectionModifier::Unknown(text) => write!(f, "[{}]", text),
+        }
+    }
+}
+
+impl Display for PartLocaleID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[${}", self.currency_symbol)?;
+        // the digits as written, unless the language info was changed since
+        if let Some(text) = &self.language_text
+            && PartLocaleID::from_parsed_peg(vec![], Some(text)).language_info == self.language_info
+        {
+            return write!(f, "-{}]", text);
+        }
+        match &self.language_info {
+            // more than 4 digits are needed to read the value back as
+            // calendar and number system bytes, whatever its magnitude
+            Some(ParsedLanguageInfo::Complex { raw_value, .. }) => write!(f, "-{:08X}", raw_value)?,
+            Some(ParsedLanguageInfo::SystemLongDate) => f.write_str("-F800")?,
+            Some(ParsedLanguageInfo::SystemTimeFormat) => f.write_str("-F400")?,
+            Some(ParsedLanguageInfo::RawLID(lid)) => write!(f, "-{:03X}", lid)?,
+            None => {}
+        }
+        f.write_char(']')
+    }
+}
+
+impl Display for NFPartColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NFPartColor::Intl(color) => write!(f, "[{}]", color),
+            NFPartColor::Color(index) => write!(f, "[Color{}]", index),
+        }
+    }
+}
+
+impl Display for DefinedColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DefinedColor::Black => "Black",
+            DefinedColor::Blue => "Blue",
+            DefinedColor::Cyan => "Cyan",
+            DefinedColor::Green => "Green",
+            DefinedColor::Magenta => "Magenta",
+            DefinedColor::Red => "Red",
+            DefinedColor::White => "White",
+            DefinedColor::Yellow => "Yellow",
+        })
+    }
+}
+
+impl Display for NFPartCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.value_text {
+            Some(text) if text.parse::<f64>() == Ok(self.value) => {
+                write!(f, "[{}{}]", self.op, text)
+            }
+            // `f64` prints the shortest text that reads back as the same value
+            _ => write!(f, "[{}{}]", self.op, self.value),
+        }
+    }
+}
+
+impl Display for NFCondOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NFCondOperator::Equal => "=",
+            NFCondOperator::NotEqual => "<>",
+            NFCondOperator::GreaterThan => ">",
+            NFCondOperator::LessThan => "<",
+            NFCondOperator::GreaterThanOrEqual => ">=",
+            NFCondOperator::LessThanOrEqual => "<=",
+        })
+    }
+}
+
+impl Display for NFGeneral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("General")
+    }
+}
+
+impl Display for FormatComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatComponent::General() => write!(f, "{}", NFGeneral),
+            FormatComponent::Number(number) => write!(f, "{}", number),
+            FormatComponent::Fraction(fraction) => write!(f, "{}", fraction),
+            FormatComponent::Datetime(datetime) => write!(f, "{}", datetime),
+            FormatComponent::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl Display for NFNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_elements(f, &self.num_part)?;
+        if let Some((sign, exp_part)) = &self.exp_part {
+            write!(f, "E{}", sign)?;
+            write_elements(f, exp_part)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for NFFraction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_elements(f, &self.prefix)?;
+        if let Some(integer_part) = &self.integer_part {
+            write_elements(f, integer_part)?;
+        }
+        for separator in self.separator.iter().flatten() {
+            match separator {
+                // a plain space is the usual separator of `# ?/?`, so a
+                // literal one has to stay quoted
+                DigitPosOrOther::LiteralCharSpace(' ') => f.write_char(' ')?,
+                DigitPosOrOther::LiteralString(s) => write_quoted(f, s)?,
+                other => write!(f, "{}", other)?,
+            }
+        }
+        write_frac_tokens(f, &self.numerator)?;
+        f.write_char('/')?;
+        write_frac_tokens(f, &self.denominator)?;
+        write_elements(f, &self.suffix)?;
+        for ampm in &self.ampm_part {
+            write!(f, "{}", ampm)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_elements<T: Display>(f: &mut Formatter<'_>, elements: &[DigitPosOrOther<T>]) -> fmt::Result {
+    elements.iter().try_for_each(|element| write!(f, "{}", element))
+}
+
+fn write_frac_tokens(f: &mut Formatter<'_>, tokens: &[FracToken]) -> fmt::Result {
+    tokens.iter().try_for_each(|token| write!(f, "{}", token))
+}
+
+impl Display for FracToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FracToken::Placeholder(placeholder) => write!(f, "{}", placeholder),
+            FracToken::Percent => f.write_char('%'),
+            FracToken::Digit(digit) => write!(f, "{}", digit),
+            FracToken::Number(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+impl<T: Display> Display for DigitPosOrOther<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DigitPosOrOther::Digit(digit) => write!(f, "{}", digit),
+            DigitPosOrOther::Other(other) => write!(f, "{}", other),
+            DigitPosOrOther::Currency(locale) => write!(f, "{}", locale),
+            DigitPosOrOther::LiteralCharSpace(c) => write!(f, "_{}", c),
+            DigitPosOrOther::LiteralString(s) => write_literal(f, s),
+            DigitPosOrOther::FillChar(c) => write!(f, "*{}", c),
+            DigitPosOrOther::EscapedChar(c) => write!(f, "\\{}", c),
+        }
+    }
+}
+
+impl Display for DigitPos {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DigitPos::Digit(placeholder) => write!(f, "{}", placeholder),
+            DigitPos::Separator(NumSeparator::Decimal) => f.write_char('.'),
+            DigitPos::Separator(NumSeparator::NumberGroup) => f.write_char(','),
+        }
+    }
+}
+
+impl Display for NumPlaceholder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char(match self {
+            NumPlaceholder::Zero => '0',
+            NumPlaceholder::Lazy => '#',
+            NumPlaceholder::Space => '?',
+        })
+    }
+}
+
+impl Display for Percent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char('%')
+    }
+}
+
+impl Display for Sign {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char(match self {
+            Sign::Plus => '+',
+            Sign::Minus => '-',
+        })
+    }
+}
+
+impl Display for NFText {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.elements
+            .iter()
+            .try_for_each(|element| write!(f, "{}", element))
+    }
+}
+
+impl Display for TextFormatElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TextFormatElement::AtPlaceholder => f.write_char('@'),
+            TextFormatElement::General => write!(f, "{}", NFGeneral),
+            TextFormatElement::AmPm(ampm) => write!(f, "{}", ampm),
+            TextFormatElement::LiteralString(s) => write_literal(f, s),
+            TextFormatElement::LiteralCharSpace(c) => write!(f, "_{}", c),
+            TextFormatElement::FillChar(c) => write!(f, "*{}", c),
+            TextFormatElement::EscapedChar(c) => write!(f, "\\{}", c),
+            TextFormatElement::BareChar(c) => f.write_char(*c),
+        }
+    }
+}
+
+impl Display for AmPm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AmPm::Full(spelling) | AmPm::Simple(spelling) => f.write_str(spelling),
+        }
+    }
+}
+
+impl Display for DatetimeTuple {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(datetime) = &self.0 {
+            write!(f, "{}", datetime)?;
+        }
+        if let Some(general) = &self.1 {
+            write!(f, "{}", general)?;
+        }
+        if let Some(datetime) = &self.2 {
+            write!(f, "{}", datetime)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for NFDatetime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.components
+            .iter()
+            .try_for_each(|component| write!(f, "{}", component))
+    }
+}
+
+impl Display for NFDatetimeComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NFDatetimeComponent::Token(token) => write!(f, "{}", token),
+            NFDatetimeComponent::DateSeparator(c) | NFDatetimeComponent::TimeSeparator(c) => {
+                f.write_char(*c)
+            }
+            NFDatetimeComponent::AMPM(ampm) => write!(f, "{}", ampm),
+            // bare, these would read back as separators
+            NFDatetimeComponent::Literal(s) if matches!(s.as_str(), "-" | ":") => write_quoted(f, s),
+            NFDatetimeComponent::Literal(s) => write_literal(f, s),
+        }
+    }
+}
+
+impl Display for NFDateTimeToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NFDateTimeToken::EraG(era) => f.write_str(match era {
+                EraFormatG::OneDigit => "g",
+                EraFormatG::TwoDigit => "gg",
+                EraFormatG::ThreeDigit => "ggg",
+            }),
+            NFDateTimeToken::CalendarB(calendar) => f.write_str(match calendar {
+                CalendarTypeB::Gregorian => "b1",
+                CalendarTypeB::Hijri => "b2",
+            }),
+            NFDateTimeToken::Year(year) => f.write_str(match year {
+                YearFormat::TwoDigit => "yy",
+                YearFormat::FourDigit => "yyyy",
+            }),
+            NFDateTimeToken::EraYear(era_year) => f.write_str(match era_year {
+                EraYearFormat::OneDigit => "e",
+                EraYearFormat::TwoDigit => "ee",
+            }),
+            NFDateTimeToken::Month(month) => write_repeated(f, 'm', month.0),
+            NFDateTimeToken::Day(day) => write_repeated(f, 'd', day.0),
+            NFDateTimeToken::Hour(hour) => f.write_str(match hour {
+                HourFormat::OneChar => "h",
+                HourFormat::TwoChar => "hh",
+            }),
+            NFDateTimeToken::Minute(minute) => f.write_str(match minute {
+                MinuteFormat::OneChar => "m",
+                MinuteFormat::TwoChar => "mm",
+            }),
+            NFDateTimeToken::Second(second) => f.write_str(match second {
+                SecondFormat::OneChar => "s",
+                SecondFormat::TwoChar => "ss",
+            }),
+            NFDateTimeToken::SubSecond(sub_second) => {
+                f.write_char('.')?;
+                write_repeated(f, '0', sub_second.0)
+            }
+            NFDateTimeToken::Abs(abs) => {
+                let (letter, count) = match abs {
+                    AbsTimeToken::AbsHour(hour) => ('h', hour.0),
+                    AbsTimeToken::AbsMinute(minute) => ('m', minute.0),
+                    AbsTimeToken::AbsSecond(second) => ('s', second.0),
+                };
+                f.write_char('[')?;
+                write_repeated(f, letter, count)?;
+                f.write_char(']')
+            }
+        }
+    }
+}
+
+fn write_repeated(f: &mut Formatter<'_>, c: char, count: u8) -> fmt::Result {
+    (0..count).try_for_each(|_| f.write_char(c))
+}
+
+/// Write a literal, bare when it is a single display character, quoted
+/// otherwise
+fn write_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && BARE_LITERALS.contains(&c)
+    {
+        return f.write_char(c);
+    }
+    write_quoted(f, s)
+}
+
+/// Write a literal between quotes. Quotes cannot be escaped inside a quoted
+/// string, so literals containing one are written as escaped characters.
+fn write_quoted(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
+    if s.contains('"') {
+        s.chars().try_for_each(|c| write!(f, "\\{}", c))
+    } else {
+        write!(f, "\"{}\"", s)
+    }
+}
@@ -1,9 +1,11 @@
 use crate::types::{datetime::AmPm, PartLocaleID};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TextFormatElement {
     AtPlaceholder,
+    /// `General` mixed with literals, e.g. `"Total: "General`
+    General,
     AmPm(AmPm),
     LiteralString(String),
     LiteralCharSpace(char),
@@ -12,12 +14,12 @@ pub enum TextFormatElement {
     BareChar(char),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NFText {
     pub elements: Vec<TextFormatElement>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum NumPlaceholder {
     /// 0
     Zero,
@@ -27,7 +29,7 @@ pub enum NumPlaceholder {
     Space,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum NumSeparator {
     /// .
@@ -36,13 +38,13 @@ pub enum NumSeparator {
     NumberGroup,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DigitPos {
     Digit(NumPlaceholder),
     Separator(NumSeparator),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DigitPosOrOther<T> {
     Digit(DigitPos),
     Other(T),
@@ -53,10 +55,10 @@ pub enum DigitPosOrOther<T> {
     EscapedChar(char),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Percent;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FracToken {
     Placeholder(NumPlaceholder),
     Percent,
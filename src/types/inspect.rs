@@ -0,0 +1,419 @@
+//! What a format shows, read from the AST without rendering a value
+
+use crate::types::*;
+use crate::visit::{self, Visit};
+use serde::{Deserialize, Serialize};
+
+/// Kind of value a format shows, the categories of the Format Cells dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Category {
+    General,
+    Number,
+    Currency,
+    /// Currency or numbers lined up with a fill, such as `_($* #,##0_)`
+    Accounting,
+    Date,
+    Time,
+    DateTime,
+    /// Elapsed time, such as `[h]:mm`
+    Duration,
+    Percent,
+    Fraction,
+    Scientific,
+    Text,
+    /// Anything else, including every format with a condition
+    Custom,
+}
+
+/// Symbols read as a currency when they appear as a literal in a number
+pub(crate) const CURRENCY_SYMBOLS: &[char] = &[
+    '$', '¢', '£', '¤', '¥', '৳', '฿', '₡', '₦', '₩', '₪', '₫', '€', '₭', '₮', '₱', '₲', '₴', '₸',
+    '₹', '₺', '₼', '₽', '₾', '₿', '円', '元',
+];
+
+/// Content of a section: a number, date or text component, possibly behind
+/// a condition
+pub trait SectionContent {
+    /// `None` for a section holding only a condition, which shows General
+    fn component(&self) -> Option<&FormatComponent>;
+
+    fn condition(&self) -> Option<&NFPartCondition>;
+}
+
+impl SectionContent for FormatComponent {
+    fn component(&self) -> Option<&FormatComponent> {
+        Some(self)
+    }
+
+    fn condition(&self) -> Option<&NFPartCondition> {
+        None
+    }
+}
+
+impl SectionContent for AnyInner {
+    fn component(&self) -> Option<&FormatComponent> {
+        let AnyInner::ConditionalData(_, component) = self;
+        Some(component)
+    }
+
+    fn condition(&self) -> Option<&NFPartCondition> {
+        let AnyInner::ConditionalData(condition, _) = self;
+        condition.as_ref()
+    }
+}
+
+impl SectionContent for (NFPartCondition, NFGeneral) {
+    fn component(&self) -> Option<&FormatComponent> {
+        None
+    }
+
+    fn condition(&self) -> Option<&NFPartCondition> {
+        Some(&self.0)
+    }
+}
+
+/// Everything the inspection methods report, gathered in one walk
+#[derive(Default)]
+struct Facts<'ast> {
+    date: bool,
+    time: bool,
+    elapsed: bool,
+    fill: bool,
+    subsecond_digits: usize,
+    currencies: Vec<String>,
+    colors: Vec<NFPartColor>,
+    conditions: Vec<&'ast NFPartCondition>,
+}
+
+impl<'ast> Facts<'ast> {
+    fn of_format(format: &'ast NumFormat) -> Self {
+        let mut facts = Facts::default();
+        facts.visit_num_format(format);
+        facts
+    }
+
+    fn of_section<T: SectionContent>(section: &'ast SectionWrapper<T>) -> Self {
+        let mut facts = Facts::default();
+        if let Some(locale) = &section.locale {
+            facts.visit_locale(locale);
+        }
+        if let Some(color) = &section.color {
+            facts.visit_color(color);
+        }
+        if let Some(condition) = section.inner.condition() {
+            facts.visit_condition(condition);
+        }
+        if let Some(component) = section.inner.component() {
+            facts.visit_format_component(component);
+        }
+        facts
+    }
+}
+
+impl<'ast> Visit<'ast> for Facts<'ast> {
+    fn visit_locale(&mut self, node: &'ast PartLocaleID) {
+        if !node.currency_symbol.is_empty() {
+            self.currencies.push(node.currency_symbol.clone());
+        }
+    }
+
+    fn visit_color(&mut self, node: &'ast NFPartColor) {
+        self.colors.push(*node);
+    }
+
+    fn visit_condition(&mut self, node: &'ast NFPartCondition) {
+        self.conditions.push(node);
+    }
+
+    fn visit_digit_pos_or_other(&mut self, node: &'ast DigitPosOrOther<Percent>) {
+        match node {
+            DigitPosOrOther::FillChar(_) => self.fill = true,
+            DigitPosOrOther::LiteralString(text) => {
+                let text = text.trim();
+                if text.contains(CURRENCY_SYMBOLS) {
+                    self.currencies.push(text.to_string());
+                }
+            }
+            _ => visit::walk_digit_pos_or_other(self, node),
+        }
+    }
+
+    fn visit_datetime_token(&mut self, node: &'ast NFDateTimeToken) {
+        match node {
+            NFDateTimeToken::EraG(_)
+            | NFDateTimeToken::CalendarB(_)
+            | NFDateTimeToken::Year(_)
+            | NFDateTimeToken::EraYear(_)
+            | NFDateTimeToken::Month(_)
+            | NFDateTimeToken::Day(_) => self.date = true,
+            NFDateTimeToken::Hour(_) | NFDateTimeToken::Minute(_) | NFDateTimeToken::Second(_) => {
+                self.time = true
+            }
+            NFDateTimeToken::SubSecond(fmt) => {
+                self.time = true;
+                self.subsecond_digits = self.subsecond_digits.max(fmt.0 as usize);
+            }
+            NFDateTimeToken::Abs(_) => {
+                self.time = true;
+                self.elapsed = true;
+            }
+        }
+    }
+}
+
+impl NFNumber {
+    /// Digit placeholders after the decimal point
+    pub fn decimal_places(&self) -> usize {
+        self.num_part
+            .iter()
+            .skip_while(|part| {
+                !matches!(
+                    part,
+                    DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::Decimal))
+                )
+            })
+            .filter(|part| matches!(part, DigitPosOrOther::Digit(DigitPos::Digit(_))))
+            .count()
+    }
+
+    /// Whether a `,` between digit placeholders groups the thousands, as
+    /// opposed to trailing commas that scale the value
+    pub fn uses_grouping(&self) -> bool {
+        let integer_part = self.num_part.iter().take_while(|part| {
+            !matches!(
+                part,
+                DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::Decimal))
+            )
+        });
+        let mut seen_digit = false;
+        let mut pending_group = false;
+        for part in integer_part {
+            match part {
+                DigitPosOrOther::Digit(DigitPos::Digit(_)) => {
+                    if pending_group {
+                        return true;
+                    }
+                    seen_digit = true;
+                }
+                DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::NumberGroup)) => {
+                    pending_group = seen_digit;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn has_digits(&self) -> bool {
+        self.num_part
+            .iter()
+            .any(|part| matches!(part, DigitPosOrOther::Digit(DigitPos::Digit(_))))
+    }
+}
+
+impl FormatComponent {
+    /// Category of the component, `None` when it shows only literals
+    fn category(&self, facts: &Facts) -> Option<Category> {
+        let category = match self {
+            FormatComponent::General() => Category::General,
+            FormatComponent::Fraction(_) => Category::Fraction,
+            FormatComponent::Text(text) => {
+                let shows = |element: &TextFormatElement| {
+                    matches!(
+                        element,
+                        TextFormatElement::AtPlaceholder | TextFormatElement::General
+                    )
+                };
+                if !text.elements.iter().any(shows) {
+                    return None;
+                }
+                if text
+                    .elements
+                    .iter()
+                    .all(|e| !matches!(e, TextFormatElement::General))
+                {
+                    Category::Text
+                } else {
+                    Category::Custom
+                }
+            }
+            FormatComponent::Datetime(_) => match (facts.elapsed, facts.date, facts.time) {
+                (true, _, _) => Category::Duration,
+                (_, true, true) => Category::DateTime,
+                (_, true, false) => Category::Date,
+                (_, false, true) => Category::Time,
+                _ => return None,
+            },
+            FormatComponent::Number(number) => {
+                if !number.has_digits() {
+                    return None;
+                }
+                if number.exp_part.is_some() {
+                    Category::Scientific
+                } else if number.has_percent {
+                    Category::Percent
+                } else if facts.fill {
+                    Category::Accounting
+                } else if !facts.currencies.is_empty() {
+                    Category::Currency
+                } else {
+                    Category::Number
+                }
+            }
+        };
+        Some(category)
+    }
+
+    fn decimal_places(&self, facts: &Facts) -> usize {
+        match self {
+            FormatComponent::Number(number) => number.decimal_places(),
+            FormatComponent::Datetime(_) => facts.subsecond_digits,
+            _ => 0,
+        }
+    }
+}
+
+impl<T: SectionContent> SectionWrapper<T> {
+    /// Category of what the section shows; a section holding only
+    /// literals is [`Category::Custom`]
+    pub fn category(&self) -> Category {
+        match self.inner.component() {
+            Some(component) => component
+                .category(&Facts::of_section(self))
+                .unwrap_or(Category::Custom),
+            None => Category::General,
+        }
+    }
+
+    /// Digits shown after the decimal point, sub-second digits for times
+    pub fn decimal_places(&self) -> usize {
+        self.inner.component().map_or(0, |component| {
+            component.decimal_places(&Facts::of_section(self))
+        })
+    }
+
+    pub fn uses_grouping(&self) -> bool {
+        matches!(self.inner.component(), Some(FormatComponent::Number(number)) if number.uses_grouping())
+    }
+
+    pub fn is_date(&self) -> bool {
+        Facts::of_section(self).date
+    }
+
+    pub fn has_time(&self) -> bool {
+        Facts::of_section(self).time
+    }
+
+    /// Whether the section shows elapsed time, such as `[h]` or `[mm]`
+    pub fn has_elapsed(&self) -> bool {
+        Facts::of_section(self).elapsed
+    }
+
+    /// Currency of the section, from its `[$€-407]` locale or a currency
+    /// symbol written in the number
+    pub fn currency(&self) -> Option<String> {
+        Facts::of_section(self).currencies.into_iter().next()
+    }
+}
+
+impl NumFormat {
+    /// Category of the whole format. All the sections that show the value
+    /// have to agree, and a format with a condition is always
+    /// [`Category::Custom`], like in the Format Cells dialog.
+    pub fn category(&self) -> Category {
+        let facts = Facts::of_format(self);
+        if !facts.conditions.is_empty() {
+            return Category::Custom;
+        }
+        let mut contents = self.section_contents();
+        // The text section only shows text values, `@` beside numbers is
+        // still a number format
+        if let NumFormat::FourParts(.., Some(FormatComponent::Text(_))) = self {
+            contents.pop();
+        }
+        let mut categories = contents
+            .into_iter()
+            .filter_map(|(component, facts)| component.category(&facts));
+        let Some(first) = categories.next() else {
+            return Category::Custom;
+        };
+        if categories.all(|category| category == first) {
+            first
+        } else {
+            Category::Custom
+        }
+    }
+
+    /// Digits shown after the decimal point by the first section
+    pub fn decimal_places(&self) -> usize {
+        self.section_contents()
+            .first()
+            .map_or(0, |(component, facts)| component.decimal_places(facts))
+    }
+
+    /// Whether the first section groups thousands
+    pub fn uses_grouping(&self) -> bool {
+        matches!(
+            self.section_contents().first(),
+            Some((FormatComponent::Number(number), _)) if number.uses_grouping()
+        )
+    }
+
+    /// Whether any section shows a date
+    pub fn is_date(&self) -> bool {
+        Facts::of_format(self).date
+    }
+
+    /// Whether any section shows a time of day or an elapsed time
+    pub fn has_time(&self) -> bool {
+        Facts::of_format(self).time
+    }
+
+    /// Whether any section shows elapsed time, such as `[h]` or `[mm]`
+    pub fn has_elapsed(&self) -> bool {
+        Facts::of_format(self).elapsed
+    }
+
+    /// First currency of the format, from a `[$€-407]` locale or a
+    /// currency symbol written in a number
+    pub fn currency(&self) -> Option<String> {
+        Facts::of_format(self).currencies.into_iter().next()
+    }
+
+    /// Colors of the sections, in order
+    pub fn colors(&self) -> Vec<NFPartColor> {
+        Facts::of_format(self).colors
+    }
+
+    /// Conditions of the sections, in order
+    pub fn conditions(&self) -> Vec<&NFPartCondition> {
+        Facts::of_format(self).conditions
+    }
+
+    /// Component of every section with content, along with what the section
+    /// uses. The text section of a four part format is included.
+    fn section_contents(&self) -> Vec<(&FormatComponent, Facts<'_>)> {
+        fn content<T: SectionContent>(
+            section: &SectionWrapper<T>,
+        ) -> Option<(&FormatComponent, Facts<'_>)> {
+            Some((section.inner.component()?, Facts::of_section(section)))
+        }
+        let contents = match self {
+            NumFormat::ConditionalGeneral(section) => vec![content(section)],
+            NumFormat::AnyNoCond(section) => vec![content(section)],
+            NumFormat::TwoParts(first, second) => vec![content(first), content(second)],
+            NumFormat::ThreeParts(first, second, third) => {
+                vec![content(first), content(second), content(third)]
+            }
+            NumFormat::FourParts(first, second, third, text) => {
+                let text = text.as_ref().map(|text| {
+                    let mut facts = Facts::default();
+                    facts.visit_format_component(text);
+                    (text, facts)
+                });
+                vec![content(first), content(second), content(third), text]
+            }
+        };
+        contents.into_iter().flatten().collect()
+    }
+}
@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ParsedLanguageInfo {
     Complex {
         raw_value: u32,
@@ -53,26 +53,22 @@ impl ParsedLanguageInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PartLocaleID {
     // "USD"
     pub currency_symbol: String,
     pub language_info: Option<ParsedLanguageInfo>,
+    /// The hex digits of `language_info` as written, such as `1000409`
+    pub language_text: Option<String>,
 }
 
 impl PartLocaleID {
-    pub fn from_parsed_peg(
-        currency_symbol_chars: Vec<char>,
-        hex_digits_opt: Option<Vec<u8>>,
-    ) -> Self {
+    pub fn from_parsed_peg(currency_symbol_chars: Vec<char>, hex_digits_opt: Option<&str>) -> Self {
         let lang_info = hex_digits_opt.and_then(|digits| {
             if digits.len() < 3 || digits.len() > 8 {
                 return None;
             }
-            let mut num_val: u32 = 0;
-            for &digit_val in &digits {
-                num_val = (num_val << 4) | (digit_val as u32);
-            }
+            let num_val = u32::from_str_radix(digits, 16).ok()?;
 
             match num_val {
                 0xf800 => Some(ParsedLanguageInfo::SystemLongDate),
@@ -98,6 +94,7 @@ impl PartLocaleID {
         PartLocaleID {
             currency_symbol: currency_symbol_chars.into_iter().collect(),
             language_info: lang_info,
+            language_text: hex_digits_opt.map(str::to_string),
         }
     }
 }
@@ -1,13 +1,19 @@
 pub(crate) mod common;
 pub(crate) mod datetime;
+mod display;
 pub(crate) mod elements;
+mod inspect;
 pub(crate) mod locale;
 pub(crate) mod number;
 pub(crate) mod numfmt;
+pub(crate) mod schema;
 
 pub use common::*;
 pub use datetime::*;
 pub use elements::*;
+pub use inspect::{Category, SectionContent};
+pub(crate) use inspect::CURRENCY_SYMBOLS;
 pub use locale::*;
 pub use number::*;
 pub use numfmt::*;
+pub use schema::{AST_SCHEMA_VERSION, SchemaError};
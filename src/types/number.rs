@@ -1,20 +1,45 @@
 use crate::types::common::*;
 use crate::types::elements::*;
-use serde::Serialize;
+use serde::de::{Error, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::AmPm;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Serialized as the string `"General"`, since the `null` of a unit struct
+/// would read back as a missing General part of a [`DatetimeTuple`]
+///
+/// [`DatetimeTuple`]: crate::types::DatetimeTuple
+#[derive(Debug, Clone, PartialEq)]
 pub struct NFGeneral;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+impl Serialize for NFGeneral {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("General")
+    }
+}
+
+impl<'de> Deserialize<'de> for NFGeneral {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        if name == "General" {
+            Ok(NFGeneral)
+        } else {
+            Err(D::Error::invalid_value(
+                Unexpected::Str(&name),
+                &"\"General\"",
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NFNumber {
     pub num_part: Vec<DigitPosOrOther<Percent>>,
     pub exp_part: Option<(Sign, Vec<DigitPosOrOther<Percent>>)>,
     pub has_percent: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NFFraction {
     pub prefix: Vec<DigitPosOrOther<Percent>>,
     pub integer_part: Option<Vec<DigitPosOrOther<Percent>>>,
@@ -2,9 +2,9 @@ use crate::types::common::*;
 use crate::types::datetime::*;
 use crate::types::elements::*;
 use crate::types::number::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NumFormat {
     ConditionalGeneral(SectionWrapper<(NFPartCondition, NFGeneral)>),
     AnyNoCond(AnyNoCond),
@@ -13,7 +13,7 @@ pub enum NumFormat {
     FourParts(Any, Any, AnyNoCond, Option<FormatComponent>),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AnyInner {
     ConditionalData(Option<NFPartCondition>, FormatComponent),
 }
@@ -23,7 +23,7 @@ pub type AnyNoText = SectionWrapper<AnyInner>;
 pub type AnyNoCond = SectionWrapper<FormatComponent>;
 pub type AnyNoTextNoCond = SectionWrapper<FormatComponent>;
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FormatComponent {
     General(),
     Number(NFNumber),
@@ -0,0 +1,176 @@
+//! Versioned JSON encoding of the AST, see [`NumFormat::to_json`]
+
+use crate::types::*;
+use crate::visit::{Visit, walk_datetime_tuple, walk_number};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the AST written by [`NumFormat::to_json`]
+pub const AST_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    format: &'a NumFormat,
+}
+
+#[derive(Deserialize)]
+struct OwnedDocument {
+    version: u32,
+    format: NumFormat,
+}
+
+/// Failure to read a JSON document written by [`NumFormat::to_json`]
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The document was written with another version of the AST
+    UnsupportedVersion(u32),
+    /// The document is not JSON, or does not describe a format
+    Json(serde_json::Error),
+    /// The format is one no format string parses to, such as a date section
+    /// without a date or a number as the text section
+    Invalid(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported AST version {}, expected {}",
+                version, AST_SCHEMA_VERSION
+            ),
+            SchemaError::Json(e) => write!(f, "Invalid AST document: {}", e),
+            SchemaError::Invalid(reason) => {
+                write!(f, "Invalid format in AST document: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(e: serde_json::Error) -> Self {
+        SchemaError::Json(e)
+    }
+}
+
+impl NumFormat {
+    /// Encode the format as a versioned JSON document, for storing parsed
+    /// formats and for exchanging them with other services.
+    ///
+    /// A document is an object with two fields, here for the format `@`:
+    ///
+    /// ```json
+    /// {
+    ///   "version": 1,
+    ///   "format": {
+    ///     "AnyNoCond": {
+    ///       "locale": null,
+    ///       "color": null,
+    ///       "special_prefix": [],
+    ///       "inner": {"Text": {"elements": ["AtPlaceholder"]}}
+    ///     }
+    ///   }
+    /// }
+    /// ```
+    ///
+    /// `format` is the [`NumFormat`] as serde writes it:
+    ///
+    /// - structs are objects keyed by field name, tuple structs such as
+    ///   [`DatetimeTuple`](crate::types::DatetimeTuple) are arrays and unit
+    ///   structs such as [`Percent`](crate::types::Percent) are `null`,
+    ///   except [`NFGeneral`](crate::types::NFGeneral) which is `"General"`
+    /// - enum variants are externally tagged: a unit variant is its name as a
+    ///   string (`"Zero"`), any other variant is an object with the name as its
+    ///   only key (`{"Digit": "Zero"}`, `{"Color": 10}`), tuple variants holding
+    ///   several values use an array (`{"TwoParts": [..., ...]}`) and
+    ///   `FormatComponent::General` is `{"General": []}`
+    /// - `Option` is `null` or the value, and characters are one-character
+    ///   strings
+    ///
+    /// The version changes whenever a node is added, removed or renamed, so
+    /// consumers can reject documents they do not understand instead of
+    /// misreading them. Version 1 is the AST of this release.
+    pub fn to_json(&self) -> String {
+        let document = Document {
+            version: AST_SCHEMA_VERSION,
+            format: self,
+        };
+        serde_json::to_string(&document).expect("the AST always serializes")
+    }
+
+    /// Decode a document written by [`NumFormat::to_json`], refusing other
+    /// versions of the AST and formats no format string parses to
+    pub fn from_json(json: &str) -> Result<NumFormat, SchemaError> {
+        let OwnedDocument { version, format } = serde_json::from_str(json)?;
+        if version != AST_SCHEMA_VERSION {
+            return Err(SchemaError::UnsupportedVersion(version));
+        }
+        let mut invariants = Invariants::default();
+        invariants.visit_num_format(&format);
+        match invariants.violation {
+            Some(reason) => Err(SchemaError::Invalid(reason)),
+            None => Ok(format),
+        }
+    }
+}
+
+/// What the parser guarantees about a format, checked on decoded documents
+#[derive(Default)]
+struct Invariants {
+    violation: Option<String>,
+}
+
+impl Invariants {
+    fn check(&mut self, holds: bool, reason: impl FnOnce() -> String) {
+        if !holds && self.violation.is_none() {
+            self.violation = Some(reason());
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Invariants {
+    fn visit_num_format(&mut self, node: &'ast NumFormat) {
+        if let NumFormat::FourParts(_, _, _, Some(text)) = node {
+            self.check(
+                matches!(text, FormatComponent::General() | FormatComponent::Text(_)),
+                || "the fourth section must be General or text".to_string(),
+            );
+        }
+        crate::visit::walk_num_format(self, node);
+    }
+
+    fn visit_section_modifier(&mut self, node: &'ast SectionModifier) {
+        if let SectionModifier::DBNum(n) = node {
+            self.check((1..=3).contains(n), || format!("[DBNum{}] does not exist", n));
+        }
+    }
+
+    fn visit_color(&mut self, node: &'ast NFPartColor) {
+        if let NFPartColor::Color(index) = node {
+            self.check((1..=56).contains(index), || {
+                format!("color index {} is not between 1 and 56", index)
+            });
+        }
+    }
+
+    fn visit_number(&mut self, node: &'ast NFNumber) {
+        let percent = node
+            .num_part
+            .iter()
+            .any(|element| matches!(element, DigitPosOrOther::Other(Percent)));
+        self.check(node.has_percent == percent, || {
+            "has_percent does not match the number elements".to_string()
+        });
+        walk_number(self, node);
+    }
+
+    fn visit_datetime_tuple(&mut self, node: &'ast DatetimeTuple) {
+        self.check(node.0.is_some() || node.2.is_some(), || {
+            "a date or time section needs a date or time".to_string()
+        });
+        walk_datetime_tuple(self, node);
+    }
+}
@@ -0,0 +1,882 @@
+//! Reading a value back from the text a format produced
+//!
+//! Every section of the format is turned into the pieces it shows, literals,
+//! runs of digits, month names and AM/PM markers, and the text is matched
+//! against them section by section. The value is then put back together
+//! from what the pieces matched, with the sign of the section.
+//!
+//! ```
+//! use luscinia::{FormatValue, LocaleConfig, NumfmtParser, unformat};
+//!
+//! let format = NumfmtParser::new("#,##0.00;(#,##0.00)").parse().unwrap();
+//! let value = unformat("(1,234.57)", &format, &LocaleConfig::default()).unwrap();
+//! assert_eq!(value, FormatValue::Number(-1234.57));
+//! ```
+
+use crate::formatter::{
+    FormatError, FormatValue, LocaleConfig, datetime_to_excel_serial, evaluate_condition,
+};
+use crate::infer::{MONTHS, WEEKDAYS, full_year};
+use crate::types::*;
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashSet;
+
+/// What the text matched by a piece stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// Shown text that carries no value, such as a color or a currency
+    Literal,
+    Minus,
+    Integer,
+    Decimal,
+    ExponentSign,
+    Exponent,
+    Numerator,
+    Denominator,
+    Year,
+    ShortYear,
+    Month,
+    MonthName,
+    Weekday,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    SubSecond,
+    AmPm,
+    ElapsedHours,
+    ElapsedMinutes,
+    ElapsedSeconds,
+    /// `@` of a text section
+    Text,
+    /// `@` or `General` showing a number
+    General,
+}
+
+/// Part of the text a section shows
+#[derive(Debug, Clone)]
+enum Piece {
+    Literal(String),
+    /// Text that may be left out
+    Optional(String, Field),
+    /// Any number of a char, for the blanks of `_x` and the fills of `*x`
+    Repeat(char),
+    /// Digits with the separators and blanks their placeholders show
+    Digits {
+        field: Field,
+        min: usize,
+        max: usize,
+        grouping: Option<char>,
+        blanks: bool,
+    },
+    /// One of the names, in any case
+    Name(Field, Vec<String>),
+    /// Any text
+    Any(Field),
+}
+
+impl Piece {
+    fn digits(field: Field, min: usize, max: usize) -> Self {
+        Piece::Digits {
+            field,
+            min,
+            max,
+            grouping: None,
+            blanks: false,
+        }
+    }
+}
+
+/// Text matched by a piece, and which name when it is one
+#[derive(Debug, Clone, Copy)]
+struct Capture<'a> {
+    field: Field,
+    text: &'a str,
+    name: usize,
+}
+
+/// Match the whole of `text` against the pieces, trying the longest runs
+/// first. `failed` holds the numbers of pieces and bytes left that did not
+/// match, so runs of blanks next to each other are not tried again for
+/// every way of splitting them.
+fn match_pieces<'a>(
+    pieces: &[Piece],
+    text: &'a str,
+    captures: &mut Vec<Capture<'a>>,
+    failed: &mut HashSet<(usize, usize)>,
+) -> bool {
+    let Some((piece, rest)) = pieces.split_first() else {
+        return text.is_empty();
+    };
+    if failed.contains(&(pieces.len(), text.len())) {
+        return false;
+    }
+    let mut attempt = |field: Field, len: usize, name: usize| {
+        captures.push(Capture {
+            field,
+            text: &text[..len],
+            name,
+        });
+        if match_pieces(rest, &text[len..], captures, failed) {
+            return true;
+        }
+        captures.pop();
+        false
+    };
+    let matched = match piece {
+        Piece::Literal(literal) => {
+            text.starts_with(literal.as_str()) && attempt(Field::Literal, literal.len(), 0)
+        }
+        Piece::Optional(literal, field) => {
+            (text.starts_with(literal.as_str()) && attempt(*field, literal.len(), 0))
+                || match_pieces(rest, text, captures, failed)
+        }
+        Piece::Repeat(c) => {
+            let len = text.len() - text.trim_start_matches(*c).len();
+            (0..=len / c.len_utf8())
+                .rev()
+                .any(|count| attempt(Field::Literal, count * c.len_utf8(), 0))
+        }
+        Piece::Digits {
+            field,
+            min,
+            max,
+            grouping,
+            blanks,
+        } => {
+            let mut ends = vec![(0, 0)];
+            let mut digits = 0;
+            for (i, c) in text.char_indices() {
+                if c.is_ascii_digit() {
+                    digits += 1;
+                } else if Some(c) != *grouping && !(*blanks && c == ' ') {
+                    break;
+                }
+                ends.push((i + c.len_utf8(), digits));
+            }
+            ends.into_iter()
+                .rev()
+                .filter(|(_, digits)| (*min..=*max).contains(digits))
+                .any(|(len, _)| attempt(*field, len, 0))
+        }
+        Piece::Name(field, names) => names.iter().enumerate().any(|(i, name)| {
+            text.get(..name.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(name))
+                && attempt(*field, name.len(), i)
+        }),
+        Piece::Any(field) => {
+            let mut ends: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+            ends.push(text.len());
+            ends.into_iter().rev().any(|len| attempt(*field, len, 0))
+        }
+    };
+    if !matched {
+        failed.insert((pieces.len(), text.len()));
+    }
+    matched
+}
+
+/// A value read from a section, before the sign of the section
+enum Reading {
+    Number(f64),
+    Text(String),
+}
+
+/// How the value shown by a section relates to the value of the cell
+#[derive(Debug, Clone, Copy)]
+enum Role<'a> {
+    /// Shown with its own sign
+    Signed,
+    /// Shown without its minus
+    Negative,
+    Zero,
+    Text,
+    /// Shown when the condition holds, without its minus when `abs`
+    Condition(&'a NFPartCondition, bool),
+}
+
+struct Section<'a> {
+    locale: Option<&'a PartLocaleID>,
+    color: Option<NFPartColor>,
+    modifiers: &'a [SectionModifier],
+    component: &'a FormatComponent,
+    role: Role<'a>,
+}
+
+static GENERAL: FormatComponent = FormatComponent::General();
+
+impl<'a> Section<'a> {
+    fn new<T>(
+        wrapper: &'a SectionWrapper<T>,
+        component: &'a FormatComponent,
+        role: Role<'a>,
+    ) -> Self {
+        Section {
+            locale: wrapper.locale.as_ref(),
+            color: wrapper.color,
+            modifiers: &wrapper.special_prefix,
+            component,
+            role,
+        }
+    }
+
+    fn any(wrapper: &'a Any, role: Role<'a>, has_condition: bool) -> Self {
+        let AnyInner::ConditionalData(condition, component) = &wrapper.inner;
+        let role = match condition {
+            Some(condition) => Role::Condition(condition, matches!(role, Role::Negative)),
+            None if has_condition => Role::Signed,
+            None => role,
+        };
+        Section::new(wrapper, component, role)
+    }
+}
+
+/// The sections of a format in the order they are tried
+fn sections(format: &NumFormat) -> Vec<Section<'_>> {
+    let has_condition = |sections: &[&Any]| {
+        sections
+            .iter()
+            .any(|section| matches!(section.inner, AnyInner::ConditionalData(Some(_), _)))
+    };
+    match format {
+        NumFormat::ConditionalGeneral(section) => {
+            vec![Section::new(section, &GENERAL, Role::Signed)]
+        }
+        NumFormat::AnyNoCond(section) => {
+            let role = match section.inner {
+                FormatComponent::Text(_) => Role::Text,
+                _ => Role::Signed,
+            };
+            vec![Section::new(section, &section.inner, role)]
+        }
+        NumFormat::TwoParts(positive, negative) => {
+            let conditional = has_condition(&[positive, negative]);
+            vec![
+                Section::any(positive, Role::Signed, conditional),
+                Section::any(negative, Role::Negative, conditional),
+            ]
+        }
+        NumFormat::ThreeParts(positive, negative, zero)
+        | NumFormat::FourParts(positive, negative, zero, _) => {
+            let conditional = has_condition(&[positive, negative]);
+            let zero_role = if conditional {
+                Role::Signed
+            } else {
+                Role::Zero
+            };
+            let mut sections = vec![
+                Section::any(positive, Role::Signed, conditional),
+                Section::any(negative, Role::Negative, conditional),
+                Section::new(zero, &zero.inner, zero_role),
+            ];
+            if let NumFormat::FourParts(.., Some(text)) = format {
+                sections.push(Section {
+                    locale: None,
+                    color: None,
+                    modifiers: &[],
+                    component: text,
+                    role: Role::Text,
+                });
+            }
+            sections
+        }
+    }
+}
+
+/// Read back the value `format` showed as `text`, the reverse of
+/// [`format_parsed`](crate::format_parsed).
+///
+/// The section the text came from gives the sign: text matching the
+/// negative section is negative, and the zero section reads as zero.
+/// Literals, currency symbols, grouping, percent signs, scaling commas,
+/// exponents, fractions, month names and AM/PM markers are read the way the
+/// format shows them. Dates without a year are in the current one, and
+/// text sections give back the text their `@` shows.
+///
+/// Fails with `FormatError::ParseError` when no section of the format could
+/// have shown the text, or when the section does not show the value, and
+/// with `FormatError::UnsupportedFormat` for eras, calendars and native
+/// numerals.
+pub fn unformat(
+    text: &str,
+    format: &NumFormat,
+    locale: &LocaleConfig,
+) -> Result<FormatValue, FormatError> {
+    for section in sections(format) {
+        let Some(reading) = read_section(text, &section, locale)? else {
+            continue;
+        };
+        let value = match (reading, section.role) {
+            (Reading::Text(text), _) => return Ok(FormatValue::String(text)),
+            (Reading::Number(value), Role::Signed | Role::Text) => Some(value),
+            (Reading::Number(value), Role::Negative) => Some(-value.abs()),
+            (Reading::Number(value), Role::Zero) => (value == 0.0).then_some(0.0),
+            (Reading::Number(value), Role::Condition(condition, abs)) => {
+                let candidates = if abs {
+                    vec![-value.abs(), value.abs()]
+                } else {
+                    vec![value]
+                };
+                candidates
+                    .into_iter()
+                    .find(|value| evaluate_condition(*value, condition))
+            }
+        };
+        if let Some(value) = value {
+            return Ok(FormatValue::Number(value));
+        }
+    }
+    Err(FormatError::ParseError(format!(
+        "\"{}\" cannot be read with this format",
+        text
+    )))
+}
+
+/// Read `text` with one section, `None` when the section could not have
+/// shown it
+fn read_section(
+    text: &str,
+    section: &Section,
+    locale: &LocaleConfig,
+) -> Result<Option<Reading>, FormatError> {
+    if let Some(modifier) = section.modifiers.iter().find(|modifier| {
+        !matches!(
+            modifier,
+            SectionModifier::English | SectionModifier::Unknown(_)
+        )
+    }) {
+        return Err(FormatError::UnsupportedFormat(format!(
+            "{:?} sections cannot be read back",
+            modifier
+        )));
+    }
+
+    // Plain text shows the color in front
+    let mut pieces = Vec::new();
+    if let Some(color) = section.color {
+        pieces.push(Piece::Optional(color.to_string(), Field::Literal));
+    }
+    if let Some(locale_id) = section.locale
+        && !locale_id.currency_symbol.is_empty()
+    {
+        pieces.push(Piece::Optional(
+            locale_id.currency_symbol.clone(),
+            Field::Literal,
+        ));
+    }
+
+    let reading = match section.component {
+        FormatComponent::General() => {
+            pieces.push(Piece::Any(Field::General));
+            matches(&pieces, text).and_then(|captures| general_value(&captures, locale))
+        }
+        FormatComponent::Number(number) => {
+            let scale = number_pieces(number, locale, &mut pieces);
+            let shows_digits = pieces
+                .iter()
+                .any(|piece| matches!(piece, Piece::Digits { .. }));
+            match matches(&pieces, text) {
+                Some(_) if !shows_digits && !matches!(section.role, Role::Zero) => {
+                    return Err(hidden_value(text));
+                }
+                Some(captures) => number_value(&captures, number, scale),
+                None => None,
+            }
+        }
+        FormatComponent::Fraction(fraction) => {
+            let alternatives = fraction_pieces(fraction, locale, pieces);
+            alternatives
+                .iter()
+                .find_map(|pieces| matches(pieces, text))
+                .and_then(|captures| fraction_value(&captures, fraction))
+        }
+        FormatComponent::Datetime(datetime) => {
+            datetime_pieces(datetime, &mut pieces)?;
+            matches(&pieces, text).and_then(|captures| datetime_value(&captures))
+        }
+        FormatComponent::Text(text_format) => {
+            let field = match section.role {
+                Role::Text => Field::Text,
+                _ => Field::General,
+            };
+            text_pieces(text_format, field, &mut pieces);
+            match matches(&pieces, text) {
+                Some(captures) if field == Field::Text => {
+                    let capture = captures
+                        .iter()
+                        .find(|capture| capture.field == Field::Text)
+                        .ok_or_else(|| hidden_value(text))?;
+                    Some(Reading::Text(capture.text.to_string()))
+                }
+                Some(captures) if has(&captures, Field::General) => {
+                    general_value(&captures, locale)
+                }
+                Some(_) if matches!(section.role, Role::Zero) => Some(Reading::Number(0.0)),
+                Some(_) => return Err(hidden_value(text)),
+                None => None,
+            }
+        }
+    };
+    Ok(reading)
+}
+
+fn hidden_value(text: &str) -> FormatError {
+    FormatError::ParseError(format!(
+        "\"{}\" comes from a section that does not show the value",
+        text
+    ))
+}
+
+fn matches<'a>(pieces: &[Piece], text: &'a str) -> Option<Vec<Capture<'a>>> {
+    let mut captures = Vec::new();
+    match_pieces(pieces, text, &mut captures, &mut HashSet::new()).then_some(captures)
+}
+
+/// Shown text of a literal element, `None` for the ones without any
+fn literal_piece<T>(part: &DigitPosOrOther<T>) -> Option<Piece> {
+    match part {
+        DigitPosOrOther::LiteralString(s) => Some(Piece::Literal(s.clone())),
+        DigitPosOrOther::EscapedChar(c) => Some(Piece::Literal(c.to_string())),
+        DigitPosOrOther::LiteralCharSpace(_) => Some(Piece::Repeat(' ')),
+        DigitPosOrOther::FillChar(c) => Some(Piece::Repeat(*c)),
+        DigitPosOrOther::Currency(locale_id) if !locale_id.currency_symbol.is_empty() => Some(
+            Piece::Optional(locale_id.currency_symbol.clone(), Field::Literal),
+        ),
+        _ => None,
+    }
+}
+
+/// A run of digit placeholders not yet turned into a piece
+#[derive(Default)]
+struct Run {
+    placeholders: usize,
+    zeros: usize,
+    blanks: bool,
+}
+
+impl Run {
+    fn push(&mut self, placeholder: NumPlaceholder) {
+        self.placeholders += 1;
+        match placeholder {
+            NumPlaceholder::Zero => self.zeros += 1,
+            NumPlaceholder::Space => self.blanks = true,
+            NumPlaceholder::Lazy => {}
+        }
+    }
+
+    /// The digits of the run, the first run of a number also showing the
+    /// digits that overflow its placeholders
+    fn flush(
+        &mut self,
+        field: Field,
+        first: bool,
+        grouping: Option<char>,
+        pieces: &mut Vec<Piece>,
+    ) {
+        if self.placeholders > 0 {
+            pieces.push(Piece::Digits {
+                field,
+                min: self.zeros,
+                max: if first { usize::MAX } else { self.placeholders },
+                grouping,
+                blanks: self.blanks,
+            });
+        }
+        *self = Run::default();
+    }
+}
+
+/// Pieces of digits and literals, the digits before the decimal separator
+/// going to `field` and the ones after to `Field::Decimal`. Gives the number
+/// of scaling commas.
+fn digit_pieces(
+    parts: &[DigitPosOrOther<Percent>],
+    field: Field,
+    locale: &LocaleConfig,
+    grouping: bool,
+    pieces: &mut Vec<Piece>,
+) -> i32 {
+    let grouping = grouping.then(|| locale.thousands_separator.unwrap_or(','));
+    let mut run = Run::default();
+    let mut first = true;
+    let mut in_decimals = false;
+    let mut scale = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let run_field = if in_decimals { Field::Decimal } else { field };
+        match part {
+            DigitPosOrOther::Digit(DigitPos::Digit(placeholder)) => run.push(*placeholder),
+            DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::Decimal)) => {
+                run.flush(run_field, first && !in_decimals, grouping, pieces);
+                first = false;
+                in_decimals = true;
+                pieces.push(Piece::Literal(
+                    locale.decimal_separator.unwrap_or('.').to_string(),
+                ));
+            }
+            DigitPosOrOther::Digit(DigitPos::Separator(NumSeparator::NumberGroup)) => {
+                // A comma no digit of the integer follows scales by a thousand
+                let groups_digits = !in_decimals
+                    && parts[i + 1..]
+                        .iter()
+                        .take_while(|part| {
+                            **part
+                                != DigitPosOrOther::Digit(DigitPos::Separator(
+                                    NumSeparator::Decimal,
+                                ))
+                        })
+                        .any(|part| matches!(part, DigitPosOrOther::Digit(DigitPos::Digit(_))));
+                if !groups_digits {
+                    scale += 1;
+                }
+            }
+            DigitPosOrOther::Other(Percent) => {
+                run.flush(run_field, first && !in_decimals, grouping, pieces);
+                pieces.push(Piece::Literal("%".to_string()));
+            }
+            other => {
+                if let Some(piece) = literal_piece(other) {
+                    if run.placeholders > 0 {
+                        run.flush(run_field, first && !in_decimals, grouping, pieces);
+                        first = false;
+                    }
+                    pieces.push(piece);
+                }
+            }
+        }
+    }
+    let run_field = if in_decimals { Field::Decimal } else { field };
+    run.flush(run_field, first && !in_decimals, grouping, pieces);
+    scale
+}
+
+fn number_pieces(number: &NFNumber, locale: &LocaleConfig, pieces: &mut Vec<Piece>) -> i32 {
+    pieces.push(Piece::Optional("-".to_string(), Field::Minus));
+    let scale = digit_pieces(
+        &number.num_part,
+        Field::Integer,
+        locale,
+        number.uses_grouping(),
+        pieces,
+    );
+    if let Some((_, exp_part)) = &number.exp_part {
+        pieces.push(Piece::Literal("E".to_string()));
+        pieces.push(Piece::Name(
+            Field::ExponentSign,
+            vec!["+".to_string(), "-".to_string()],
+        ));
+        digit_pieces(exp_part, Field::Exponent, locale, false, pieces);
+    }
+    scale
+}
+
+fn digits_of(captures: &[Capture], field: Field) -> String {
+    captures
+        .iter()
+        .filter(|capture| capture.field == field)
+        .flat_map(|capture| capture.text.chars())
+        .filter(char::is_ascii_digit)
+        .collect()
+}
+
+fn has(captures: &[Capture], field: Field) -> bool {
+    captures.iter().any(|capture| capture.field == field)
+}
+
+fn number_value(captures: &[Capture], number: &NFNumber, scale: i32) -> Option<Reading> {
+    let mut exponent = 3 * scale;
+    if number.has_percent {
+        exponent -= 2;
+    }
+    if number.exp_part.is_some() {
+        let shown = digits_of(captures, Field::Exponent).parse::<i32>().ok()?;
+        let negative = captures
+            .iter()
+            .any(|capture| capture.field == Field::ExponentSign && capture.name == 1);
+        exponent += if negative { -shown } else { shown };
+    }
+    let mantissa = format!(
+        "0{}.{}0",
+        digits_of(captures, Field::Integer),
+        digits_of(captures, Field::Decimal)
+    );
+    let value = format!("{}e{}", mantissa, exponent).parse::<f64>().ok()?;
+    Some(Reading::Number(if has(captures, Field::Minus) {
+        -value
+    } else {
+        value
+    }))
+}
+
+/// Pieces of a fraction, and of the same fraction with only its whole part
+/// as Excel shows whole numbers
+fn fraction_pieces(
+    fraction: &NFFraction,
+    locale: &LocaleConfig,
+    mut pieces: Vec<Piece>,
+) -> Vec<Vec<Piece>> {
+    let literals = |parts: &[DigitPosOrOther<Percent>], pieces: &mut Vec<Piece>| {
+        pieces.extend(parts.iter().filter_map(literal_piece));
+    };
+    literals(&fraction.prefix, &mut pieces);
+    pieces.push(Piece::Optional("-".to_string(), Field::Minus));
+    if let Some(integer_part) = &fraction.integer_part {
+        digit_pieces(integer_part, Field::Integer, locale, false, &mut pieces);
+        pieces.push(Piece::Repeat(' '));
+    }
+    let mut whole = pieces.clone();
+
+    let frac_pieces = |tokens: &[FracToken], field: Field, pieces: &mut Vec<Piece>| {
+        let mut run = Run::default();
+        for token in tokens {
+            match token {
+                FracToken::Placeholder(placeholder) => run.push(*placeholder),
+                FracToken::Number(n) => pieces.push(Piece::Literal(n.to_string())),
+                FracToken::Digit(d) => pieces.push(Piece::Literal(d.to_string())),
+                FracToken::Percent => {
+                    run.flush(field, true, None, pieces);
+                    pieces.push(Piece::Literal("%".to_string()));
+                }
+            }
+        }
+        run.flush(field, true, None, pieces);
+    };
+    frac_pieces(&fraction.numerator, Field::Numerator, &mut pieces);
+    pieces.push(Piece::Literal("/".to_string()));
+    frac_pieces(&fraction.denominator, Field::Denominator, &mut pieces);
+    literals(&fraction.suffix, &mut pieces);
+
+    if fraction.integer_part.is_none() {
+        return vec![pieces];
+    }
+    whole.push(Piece::Repeat(' '));
+    literals(&fraction.suffix, &mut whole);
+    vec![pieces, whole]
+}
+
+fn fraction_value(captures: &[Capture], fraction: &NFFraction) -> Option<Reading> {
+    let integer = digits_of(captures, Field::Integer);
+    let mut value = if integer.is_empty() {
+        0.0
+    } else {
+        integer.parse::<f64>().ok()?
+    };
+    if has(captures, Field::Numerator) {
+        let numerator = digits_of(captures, Field::Numerator).parse::<f64>().ok()?;
+        let denominator = if has(captures, Field::Denominator) {
+            digits_of(captures, Field::Denominator)
+                .parse::<f64>()
+                .ok()?
+        } else {
+            fixed_denominator(&fraction.denominator)?
+        };
+        if denominator == 0.0 {
+            return None;
+        }
+        value += numerator / denominator;
+    }
+    Some(Reading::Number(if has(captures, Field::Minus) {
+        -value
+    } else {
+        value
+    }))
+}
+
+fn fixed_denominator(tokens: &[FracToken]) -> Option<f64> {
+    let digits: String = tokens
+        .iter()
+        .filter_map(|token| match token {
+            FracToken::Number(n) => Some(n.to_string()),
+            FracToken::Digit(d) => Some(d.to_string()),
+            _ => None,
+        })
+        .collect();
+    digits.parse().ok()
+}
+
+fn datetime_pieces(datetime: &DatetimeTuple, pieces: &mut Vec<Piece>) -> Result<(), FormatError> {
+    if datetime.1.is_some() {
+        return Err(FormatError::UnsupportedFormat(
+            "General inside a date cannot be read back".to_string(),
+        ));
+    }
+    let names = |names: &[&str], len: Option<usize>| {
+        names
+            .iter()
+            .map(|name| match len {
+                Some(len) => name[..len].to_string(),
+                None => name.to_string(),
+            })
+            .collect::<Vec<_>>()
+    };
+    for component in datetime
+        .0
+        .iter()
+        .chain(&datetime.2)
+        .flat_map(|dt| &dt.components)
+    {
+        let piece = match component {
+            NFDatetimeComponent::Token(token) => match token {
+                NFDateTimeToken::Year(YearFormat::TwoDigit) => {
+                    Piece::digits(Field::ShortYear, 2, 2)
+                }
+                NFDateTimeToken::Year(YearFormat::FourDigit) => Piece::digits(Field::Year, 4, 4),
+                NFDateTimeToken::Month(MonthFormat(1)) => Piece::digits(Field::Month, 1, 2),
+                NFDateTimeToken::Month(MonthFormat(2)) => Piece::digits(Field::Month, 2, 2),
+                NFDateTimeToken::Month(MonthFormat(3)) => {
+                    Piece::Name(Field::MonthName, names(&MONTHS, Some(3)))
+                }
+                NFDateTimeToken::Month(MonthFormat(4)) => {
+                    Piece::Name(Field::MonthName, names(&MONTHS, None))
+                }
+                NFDateTimeToken::Month(_) => {
+                    return Err(FormatError::UnsupportedFormat(
+                        "months shown by their initial cannot be read back".to_string(),
+                    ));
+                }
+                NFDateTimeToken::Day(DayFormat(1)) => Piece::digits(Field::Day, 1, 2),
+                NFDateTimeToken::Day(DayFormat(2)) => Piece::digits(Field::Day, 2, 2),
+                NFDateTimeToken::Day(DayFormat(3)) => {
+                    Piece::Name(Field::Weekday, names(&WEEKDAYS, Some(3)))
+                }
+                NFDateTimeToken::Day(_) => Piece::Name(Field::Weekday, names(&WEEKDAYS, None)),
+                NFDateTimeToken::Hour(HourFormat::OneChar) => Piece::digits(Field::Hour, 1, 2),
+                NFDateTimeToken::Hour(HourFormat::TwoChar) => Piece::digits(Field::Hour, 2, 2),
+                NFDateTimeToken::Minute(MinuteFormat::OneChar) => {
+                    Piece::digits(Field::Minute, 1, 2)
+                }
+                NFDateTimeToken::Minute(MinuteFormat::TwoChar) => {
+                    Piece::digits(Field::Minute, 2, 2)
+                }
+                NFDateTimeToken::Second(SecondFormat::OneChar) => {
+                    Piece::digits(Field::Second, 1, 2)
+                }
+                NFDateTimeToken::Second(SecondFormat::TwoChar) => {
+                    Piece::digits(Field::Second, 2, 2)
+                }
+                NFDateTimeToken::SubSecond(SubSecondFormat(digits)) => {
+                    let digits = (*digits).clamp(1, 3) as usize;
+                    pieces.push(Piece::Literal(".".to_string()));
+                    Piece::digits(Field::SubSecond, digits, digits)
+                }
+                NFDateTimeToken::Abs(AbsTimeToken::AbsHour(AbsHourFormat(digits))) => {
+                    Piece::digits(Field::ElapsedHours, *digits as usize, usize::MAX)
+                }
+                NFDateTimeToken::Abs(AbsTimeToken::AbsMinute(AbsMinuteFormat(digits))) => {
+                    Piece::digits(Field::ElapsedMinutes, *digits as usize, usize::MAX)
+                }
+                NFDateTimeToken::Abs(AbsTimeToken::AbsSecond(AbsSecondFormat(digits))) => {
+                    Piece::digits(Field::ElapsedSeconds, *digits as usize, usize::MAX)
+                }
+                NFDateTimeToken::EraG(_)
+                | NFDateTimeToken::EraYear(_)
+                | NFDateTimeToken::CalendarB(_) => {
+                    return Err(FormatError::UnsupportedFormat(
+                        "eras cannot be read back".to_string(),
+                    ));
+                }
+            },
+            NFDatetimeComponent::DateSeparator(c) | NFDatetimeComponent::TimeSeparator(c) => {
+                Piece::Literal(c.to_string())
+            }
+            NFDatetimeComponent::AMPM(ampm) => {
+                Piece::Name(Field::AmPm, vec![ampm.marker(false), ampm.marker(true)])
+            }
+            NFDatetimeComponent::Literal(text) => Piece::Literal(text.clone()),
+        };
+        pieces.push(piece);
+    }
+    Ok(())
+}
+
+fn datetime_value(captures: &[Capture]) -> Option<Reading> {
+    let number = |field: Field| -> Option<Option<u32>> {
+        match digits_of(captures, field) {
+            digits if digits.is_empty() => Some(None),
+            digits => digits.parse::<u32>().ok().map(Some),
+        }
+    };
+    let name = |field: Field| {
+        captures
+            .iter()
+            .find(|capture| capture.field == field)
+            .map(|capture| capture.name)
+    };
+
+    let year = match (number(Field::Year)?, has(captures, Field::ShortYear)) {
+        (Some(year), _) => Some(year as i32),
+        (None, true) => Some(full_year(&digits_of(captures, Field::ShortYear))?),
+        (None, false) => None,
+    };
+    let month = number(Field::Month)?.or(name(Field::MonthName).map(|i| i as u32 + 1));
+    let day = number(Field::Day)?;
+    let weekday = name(Field::Weekday);
+    // A weekday alone does not tell which date it was
+    if weekday.is_some() && (month.is_none() || day.is_none()) {
+        return None;
+    }
+    let date = if year.is_some() || month.is_some() || day.is_some() {
+        let year = year.unwrap_or_else(|| chrono::Local::now().year());
+        let date = NaiveDate::from_ymd_opt(year, month.unwrap_or(1), day.unwrap_or(1))?;
+        if weekday.is_some_and(|weekday| weekday as u32 != date.weekday().num_days_from_monday()) {
+            return None;
+        }
+        datetime_to_excel_serial(date.and_hms_opt(0, 0, 0)?)
+    } else {
+        0.0
+    };
+
+    let mut hours = number(Field::Hour)?.unwrap_or(0);
+    let minutes = number(Field::Minute)?.unwrap_or(0);
+    let seconds = number(Field::Second)?.unwrap_or(0);
+    if let Some(pm) = name(Field::AmPm) {
+        if !(1..=12).contains(&hours) {
+            return None;
+        }
+        hours = hours % 12 + if pm == 1 { 12 } else { 0 };
+    }
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let elapsed = number(Field::ElapsedHours)?.unwrap_or(0) as f64 * 3600.0
+        + number(Field::ElapsedMinutes)?.unwrap_or(0) as f64 * 60.0
+        + number(Field::ElapsedSeconds)?.unwrap_or(0) as f64;
+    let fraction = match digits_of(captures, Field::SubSecond) {
+        digits if digits.is_empty() => 0.0,
+        digits => format!("0.{}", digits).parse::<f64>().ok()?,
+    };
+    let seconds = (hours * 3600 + minutes * 60 + seconds) as f64 + elapsed + fraction;
+    Some(Reading::Number(date + seconds / 86400.0))
+}
+
+fn text_pieces(text: &NFText, field: Field, pieces: &mut Vec<Piece>) {
+    for element in &text.elements {
+        pieces.push(match element {
+            TextFormatElement::AtPlaceholder | TextFormatElement::General => Piece::Any(field),
+            TextFormatElement::AmPm(ampm) => Piece::Literal(ampm.marker(false)),
+            TextFormatElement::LiteralString(s) => Piece::Literal(s.clone()),
+            TextFormatElement::LiteralCharSpace(_) => Piece::Repeat(' '),
+            TextFormatElement::FillChar(c) => Piece::Repeat(*c),
+            TextFormatElement::EscapedChar(c) | TextFormatElement::BareChar(c) => {
+                Piece::Literal(c.to_string())
+            }
+        });
+    }
+}
+
+/// A number as General shows it, such as `-1234.5` or `1.23457E+11`
+fn general_value(captures: &[Capture], locale: &LocaleConfig) -> Option<Reading> {
+    let shown = captures
+        .iter()
+        .find(|capture| capture.field == Field::General)?
+        .text;
+    let decimal_separator = locale.decimal_separator.unwrap_or('.');
+    if shown.is_empty()
+        || !shown.chars().all(|c| {
+            c.is_ascii_digit() || matches!(c, '-' | '+' | 'E' | 'e') || c == decimal_separator
+        })
+    {
+        return None;
+    }
+    let value = shown.replace(decimal_separator, ".").parse::<f64>().ok()?;
+    Some(Reading::Number(value))
+}
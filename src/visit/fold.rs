@@ -0,0 +1,356 @@
+//! Traversal of the AST by value, rebuilding every node
+//!
+//! Showing every section in red:
+//!
+//! ```
+//! use luscinia::types::{DefinedColor, NFPartColor};
+//! use luscinia::visit::Fold;
+//! use luscinia::NumFormat;
+//!
+//! struct Red;
+//!
+//! impl Fold for Red {
+//!     fn fold_color(&mut self, _color: NFPartColor) -> NFPartColor {
+//!         NFPartColor::Intl(DefinedColor::Red)
+//!     }
+//! }
+//!
+//! let format: NumFormat = "[Blue]0;[Color10]-0".parse().unwrap();
+//! assert_eq!(Red.fold_num_format(format).to_string(), "[Red]0;[Red]-0");
+//! ```
+
+use crate::types::*;
+
+/// Traversal of the AST by value, see [`Visit`](super::Visit). Every method
+/// returns the node to put in place of the one it was given.
+pub trait Fold {
+    fn fold_num_format(&mut self, node: NumFormat) -> NumFormat {
+        fold_num_format(self, node)
+    }
+
+    /// A section holding only a condition, such as `[>100]`
+    fn fold_conditional_general(
+        &mut self,
+        node: SectionWrapper<(NFPartCondition, NFGeneral)>,
+    ) -> SectionWrapper<(NFPartCondition, NFGeneral)> {
+        fold_conditional_general(self, node)
+    }
+
+    /// A section that can hold a condition
+    fn fold_any(&mut self, node: Any) -> Any {
+        fold_any(self, node)
+    }
+
+    /// A section that cannot hold a condition
+    fn fold_any_no_cond(&mut self, node: AnyNoCond) -> AnyNoCond {
+        fold_any_no_cond(self, node)
+    }
+
+    fn fold_any_inner(&mut self, node: AnyInner) -> AnyInner {
+        fold_any_inner(self, node)
+    }
+
+    fn fold_section_modifier(&mut self, node: SectionModifier) -> SectionModifier {
+        node
+    }
+
+    /// A `[$€-407]` locale, either before a section or within a number
+    fn fold_locale(&mut self, node: PartLocaleID) -> PartLocaleID {
+        node
+    }
+
+    fn fold_color(&mut self, node: NFPartColor) -> NFPartColor {
+        node
+    }
+
+    fn fold_condition(&mut self, node: NFPartCondition) -> NFPartCondition {
+        node
+    }
+
+    /// `General` wherever it is written, see [`super::Visit::visit_general`]
+    fn fold_general(&mut self, node: NFGeneral) -> NFGeneral {
+        node
+    }
+
+    fn fold_format_component(&mut self, node: FormatComponent) -> FormatComponent {
+        fold_format_component(self, node)
+    }
+
+    fn fold_number(&mut self, node: NFNumber) -> NFNumber {
+        fold_number(self, node)
+    }
+
+    fn fold_digit_pos_or_other(
+        &mut self,
+        node: DigitPosOrOther<Percent>,
+    ) -> DigitPosOrOther<Percent> {
+        fold_digit_pos_or_other(self, node)
+    }
+
+    fn fold_digit_pos(&mut self, node: DigitPos) -> DigitPos {
+        node
+    }
+
+    fn fold_percent(&mut self, node: Percent) -> Percent {
+        node
+    }
+
+    fn fold_fraction(&mut self, node: NFFraction) -> NFFraction {
+        fold_fraction(self, node)
+    }
+
+    fn fold_frac_token(&mut self, node: FracToken) -> FracToken {
+        node
+    }
+
+    fn fold_datetime_tuple(&mut self, node: DatetimeTuple) -> DatetimeTuple {
+        fold_datetime_tuple(self, node)
+    }
+
+    fn fold_datetime(&mut self, node: NFDatetime) -> NFDatetime {
+        fold_datetime(self, node)
+    }
+
+    fn fold_datetime_component(&mut self, node: NFDatetimeComponent) -> NFDatetimeComponent {
+        fold_datetime_component(self, node)
+    }
+
+    fn fold_datetime_token(&mut self, node: NFDateTimeToken) -> NFDateTimeToken {
+        node
+    }
+
+    fn fold_am_pm(&mut self, node: AmPm) -> AmPm {
+        node
+    }
+
+    fn fold_text(&mut self, node: NFText) -> NFText {
+        fold_text(self, node)
+    }
+
+    fn fold_text_element(&mut self, node: TextFormatElement) -> TextFormatElement {
+        fold_text_element(self, node)
+    }
+}
+
+pub fn fold_num_format<F: Fold + ?Sized>(f: &mut F, node: NumFormat) -> NumFormat {
+    match node {
+        NumFormat::ConditionalGeneral(section) => {
+            NumFormat::ConditionalGeneral(f.fold_conditional_general(section))
+        }
+        NumFormat::AnyNoCond(section) => NumFormat::AnyNoCond(f.fold_any_no_cond(section)),
+        NumFormat::TwoParts(first, second) => {
+            NumFormat::TwoParts(f.fold_any(first), f.fold_any(second))
+        }
+        NumFormat::ThreeParts(first, second, third) => NumFormat::ThreeParts(
+            f.fold_any(first),
+            f.fold_any(second),
+            f.fold_any_no_cond(third),
+        ),
+        NumFormat::FourParts(first, second, third, text) => NumFormat::FourParts(
+            f.fold_any(first),
+            f.fold_any(second),
+            f.fold_any_no_cond(third),
+            text.map(|text| f.fold_format_component(text)),
+        ),
+    }
+}
+
+/// Fold the modifiers, locale and color written before a section, keeping
+/// its content
+fn fold_section_prefixes<F: Fold + ?Sized, T>(
+    f: &mut F,
+    node: SectionWrapper<T>,
+) -> SectionWrapper<T> {
+    SectionWrapper {
+        special_prefix: node
+            .special_prefix
+            .into_iter()
+            .map(|modifier| f.fold_section_modifier(modifier))
+            .collect(),
+        locale: node.locale.map(|locale| f.fold_locale(locale)),
+        color: node.color.map(|color| f.fold_color(color)),
+        inner: node.inner,
+    }
+}
+
+pub fn fold_conditional_general<F: Fold + ?Sized>(
+    f: &mut F,
+    node: SectionWrapper<(NFPartCondition, NFGeneral)>,
+) -> SectionWrapper<(NFPartCondition, NFGeneral)> {
+    let mut node = fold_section_prefixes(f, node);
+    let (condition, general) = node.inner;
+    node.inner = (f.fold_condition(condition), f.fold_general(general));
+    node
+}
+
+pub fn fold_any<F: Fold + ?Sized>(f: &mut F, node: Any) -> Any {
+    let SectionWrapper {
+        locale,
+        color,
+        special_prefix,
+        inner,
+    } = fold_section_prefixes(f, node);
+    SectionWrapper {
+        locale,
+        color,
+        special_prefix,
+        inner: f.fold_any_inner(inner),
+    }
+}
+
+pub fn fold_any_no_cond<F: Fold + ?Sized>(f: &mut F, node: AnyNoCond) -> AnyNoCond {
+    let SectionWrapper {
+        locale,
+        color,
+        special_prefix,
+        inner,
+    } = fold_section_prefixes(f, node);
+    SectionWrapper {
+        locale,
+        color,
+        special_prefix,
+        inner: f.fold_format_component(inner),
+    }
+}
+
+pub fn fold_any_inner<F: Fold + ?Sized>(f: &mut F, node: AnyInner) -> AnyInner {
+    let AnyInner::ConditionalData(condition, component) = node;
+    AnyInner::ConditionalData(
+        condition.map(|condition| f.fold_condition(condition)),
+        f.fold_format_component(component),
+    )
+}
+
+pub fn fold_format_component<F: Fold + ?Sized>(
+    f: &mut F,
+    node: FormatComponent,
+) -> FormatComponent {
+    match node {
+        FormatComponent::General() => {
+            f.fold_general(NFGeneral);
+            FormatComponent::General()
+        }
+        FormatComponent::Number(number) => FormatComponent::Number(f.fold_number(number)),
+        FormatComponent::Fraction(fraction) => FormatComponent::Fraction(f.fold_fraction(fraction)),
+        FormatComponent::Datetime(datetime) => {
+            FormatComponent::Datetime(f.fold_datetime_tuple(datetime))
+        }
+        FormatComponent::Text(text) => FormatComponent::Text(f.fold_text(text)),
+    }
+}
+
+/// Fold every part of a run of number tokens
+fn fold_parts<F: Fold + ?Sized>(
+    f: &mut F,
+    parts: Vec<DigitPosOrOther<Percent>>,
+) -> Vec<DigitPosOrOther<Percent>> {
+    parts
+        .into_iter()
+        .map(|part| f.fold_digit_pos_or_other(part))
+        .collect()
+}
+
+pub fn fold_number<F: Fold + ?Sized>(f: &mut F, node: NFNumber) -> NFNumber {
+    NFNumber {
+        num_part: fold_parts(f, node.num_part),
+        exp_part: node
+            .exp_part
+            .map(|(sign, exp_part)| (sign, fold_parts(f, exp_part))),
+        has_percent: node.has_percent,
+    }
+}
+
+pub fn fold_digit_pos_or_other<F: Fold + ?Sized>(
+    f: &mut F,
+    node: DigitPosOrOther<Percent>,
+) -> DigitPosOrOther<Percent> {
+    match node {
+        DigitPosOrOther::Digit(digit) => DigitPosOrOther::Digit(f.fold_digit_pos(digit)),
+        DigitPosOrOther::Other(percent) => DigitPosOrOther::Other(f.fold_percent(percent)),
+        DigitPosOrOther::Currency(locale) => DigitPosOrOther::Currency(f.fold_locale(locale)),
+        other @ (DigitPosOrOther::LiteralCharSpace(_)
+        | DigitPosOrOther::LiteralString(_)
+        | DigitPosOrOther::FillChar(_)
+        | DigitPosOrOther::EscapedChar(_)) => other,
+    }
+}
+
+pub fn fold_fraction<F: Fold + ?Sized>(f: &mut F, node: NFFraction) -> NFFraction {
+    let fold_tokens = |f: &mut F, tokens: Vec<FracToken>| {
+        tokens
+            .into_iter()
+            .map(|token| f.fold_frac_token(token))
+            .collect()
+    };
+    NFFraction {
+        prefix: fold_parts(f, node.prefix),
+        integer_part: node.integer_part.map(|parts| fold_parts(f, parts)),
+        separator: node.separator.map(|parts| fold_parts(f, parts)),
+        numerator: fold_tokens(f, node.numerator),
+        denominator: fold_tokens(f, node.denominator),
+        suffix: fold_parts(f, node.suffix),
+        ampm_part: node
+            .ampm_part
+            .into_iter()
+            .map(|ampm| f.fold_am_pm(ampm))
+            .collect(),
+    }
+}
+
+pub fn fold_datetime_tuple<F: Fold + ?Sized>(f: &mut F, node: DatetimeTuple) -> DatetimeTuple {
+    let DatetimeTuple(first, general, second) = node;
+    DatetimeTuple(
+        first.map(|datetime| f.fold_datetime(datetime)),
+        general.map(|general| f.fold_general(general)),
+        second.map(|datetime| f.fold_datetime(datetime)),
+    )
+}
+
+pub fn fold_datetime<F: Fold + ?Sized>(f: &mut F, node: NFDatetime) -> NFDatetime {
+    NFDatetime {
+        components: node
+            .components
+            .into_iter()
+            .map(|component| f.fold_datetime_component(component))
+            .collect(),
+    }
+}
+
+pub fn fold_datetime_component<F: Fold + ?Sized>(
+    f: &mut F,
+    node: NFDatetimeComponent,
+) -> NFDatetimeComponent {
+    match node {
+        NFDatetimeComponent::Token(token) => {
+            NFDatetimeComponent::Token(f.fold_datetime_token(token))
+        }
+        NFDatetimeComponent::AMPM(ampm) => NFDatetimeComponent::AMPM(f.fold_am_pm(ampm)),
+        other @ (NFDatetimeComponent::DateSeparator(_)
+        | NFDatetimeComponent::TimeSeparator(_)
+        | NFDatetimeComponent::Literal(_)) => other,
+    }
+}
+
+pub fn fold_text<F: Fold + ?Sized>(f: &mut F, node: NFText) -> NFText {
+    NFText {
+        elements: node
+            .elements
+            .into_iter()
+            .map(|element| f.fold_text_element(element))
+            .collect(),
+    }
+}
+
+pub fn fold_text_element<F: Fold + ?Sized>(
+    f: &mut F,
+    node: TextFormatElement,
+) -> TextFormatElement {
+    match node {
+        TextFormatElement::AmPm(ampm) => TextFormatElement::AmPm(f.fold_am_pm(ampm)),
+        TextFormatElement::General => {
+            f.fold_general(NFGeneral);
+            TextFormatElement::General
+        }
+        other => other,
+    }
+}
@@ -0,0 +1,301 @@
+//! Traversal of the AST
+//!
+//! [`Visit`] walks a format by reference, [`VisitMut`] edits it in place and
+//! [`Fold`] rebuilds it by value. Every node type has a method whose default
+//! implementation calls the matching `walk_*` function, which visits the
+//! children of the node. An implementation overrides the methods for the
+//! nodes it cares about and calls the walk function itself when it still
+//! wants the children visited. Nodes without children, such as colors or
+//! datetime tokens, have no walk function.
+//!
+//! Whether a format shows a date:
+//!
+//! ```
+//! use luscinia::types::NFDateTimeToken;
+//! use luscinia::visit::Visit;
+//! use luscinia::NumFormat;
+//!
+//! #[derive(Default)]
+//! struct HasDate(bool);
+//!
+//! impl<'ast> Visit<'ast> for HasDate {
+//!     fn visit_datetime_token(&mut self, token: &'ast NFDateTimeToken) {
+//!         self.0 |= matches!(
+//!             token,
+//!             NFDateTimeToken::Year(_) | NFDateTimeToken::Month(_) | NFDateTimeToken::Day(_)
+//!         );
+//!     }
+//! }
+//!
+//! let format: NumFormat = "0.00;[Red]yyyy-mm-dd".parse().unwrap();
+//! let mut has_date = HasDate::default();
+//! has_date.visit_num_format(&format);
+//! assert!(has_date.0);
+//! ```
+
+pub mod fold;
+pub mod visit_mut;
+
+pub use fold::Fold;
+pub use visit_mut::VisitMut;
+
+use crate::types::*;
+
+/// Read-only traversal of the AST, see the [module documentation](self)
+pub trait Visit<'ast> {
+    fn visit_num_format(&mut self, node: &'ast NumFormat) {
+        walk_num_format(self, node);
+    }
+
+    /// A section holding only a condition, such as `[>100]`
+    fn visit_conditional_general(
+        &mut self,
+        node: &'ast SectionWrapper<(NFPartCondition, NFGeneral)>,
+    ) {
+        walk_conditional_general(self, node);
+    }
+
+    /// A section that can hold a condition
+    fn visit_any(&mut self, node: &'ast Any) {
+        walk_any(self, node);
+    }
+
+    /// A section that cannot hold a condition
+    fn visit_any_no_cond(&mut self, node: &'ast AnyNoCond) {
+        walk_any_no_cond(self, node);
+    }
+
+    fn visit_any_inner(&mut self, node: &'ast AnyInner) {
+        walk_any_inner(self, node);
+    }
+
+    fn visit_section_modifier(&mut self, _node: &'ast SectionModifier) {}
+
+    /// A `[$€-407]` locale, either before a section or within a number
+    fn visit_locale(&mut self, _node: &'ast PartLocaleID) {}
+
+    fn visit_color(&mut self, _node: &'ast NFPartColor) {}
+
+    fn visit_condition(&mut self, _node: &'ast NFPartCondition) {}
+
+    /// `General` wherever it is written: as a section of its own, after a
+    /// condition, or within a date or text section
+    fn visit_general(&mut self, _node: &'ast NFGeneral) {}
+
+    fn visit_format_component(&mut self, node: &'ast FormatComponent) {
+        walk_format_component(self, node);
+    }
+
+    fn visit_number(&mut self, node: &'ast NFNumber) {
+        walk_number(self, node);
+    }
+
+    fn visit_digit_pos_or_other(&mut self, node: &'ast DigitPosOrOther<Percent>) {
+        walk_digit_pos_or_other(self, node);
+    }
+
+    fn visit_digit_pos(&mut self, _node: &'ast DigitPos) {}
+
+    fn visit_percent(&mut self, _node: &'ast Percent) {}
+
+    fn visit_fraction(&mut self, node: &'ast NFFraction) {
+        walk_fraction(self, node);
+    }
+
+    fn visit_frac_token(&mut self, _node: &'ast FracToken) {}
+
+    fn visit_datetime_tuple(&mut self, node: &'ast DatetimeTuple) {
+        walk_datetime_tuple(self, node);
+    }
+
+    fn visit_datetime(&mut self, node: &'ast NFDatetime) {
+        walk_datetime(self, node);
+    }
+
+    fn visit_datetime_component(&mut self, node: &'ast NFDatetimeComponent) {
+        walk_datetime_component(self, node);
+    }
+
+    fn visit_datetime_token(&mut self, _node: &'ast NFDateTimeToken) {}
+
+    fn visit_am_pm(&mut self, _node: &'ast AmPm) {}
+
+    fn visit_text(&mut self, node: &'ast NFText) {
+        walk_text(self, node);
+    }
+
+    fn visit_text_element(&mut self, node: &'ast TextFormatElement) {
+        walk_text_element(self, node);
+    }
+}
+
+pub fn walk_num_format<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast NumFormat) {
+    match node {
+        NumFormat::ConditionalGeneral(section) => v.visit_conditional_general(section),
+        NumFormat::AnyNoCond(section) => v.visit_any_no_cond(section),
+        NumFormat::TwoParts(first, second) => {
+            v.visit_any(first);
+            v.visit_any(second);
+        }
+        NumFormat::ThreeParts(first, second, third) => {
+            v.visit_any(first);
+            v.visit_any(second);
+            v.visit_any_no_cond(third);
+        }
+        NumFormat::FourParts(first, second, third, text) => {
+            v.visit_any(first);
+            v.visit_any(second);
+            v.visit_any_no_cond(third);
+            if let Some(text) = text {
+                v.visit_format_component(text);
+            }
+        }
+    }
+}
+
+/// Visit the modifiers, locale and color written before a section
+fn walk_section_prefixes<'ast, V: Visit<'ast> + ?Sized, T>(
+    v: &mut V,
+    node: &'ast SectionWrapper<T>,
+) {
+    for modifier in &node.special_prefix {
+        v.visit_section_modifier(modifier);
+    }
+    if let Some(locale) = &node.locale {
+        v.visit_locale(locale);
+    }
+    if let Some(color) = &node.color {
+        v.visit_color(color);
+    }
+}
+
+pub fn walk_conditional_general<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast SectionWrapper<(NFPartCondition, NFGeneral)>,
+) {
+    walk_section_prefixes(v, node);
+    v.visit_condition(&node.inner.0);
+    v.visit_general(&node.inner.1);
+}
+
+pub fn walk_any<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Any) {
+    walk_section_prefixes(v, node);
+    v.visit_any_inner(&node.inner);
+}
+
+pub fn walk_any_no_cond<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast AnyNoCond) {
+    walk_section_prefixes(v, node);
+    v.visit_format_component(&node.inner);
+}
+
+pub fn walk_any_inner<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast AnyInner) {
+    let AnyInner::ConditionalData(condition, component) = node;
+    if let Some(condition) = condition {
+        v.visit_condition(condition);
+    }
+    v.visit_format_component(component);
+}
+
+pub fn walk_format_component<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast FormatComponent,
+) {
+    match node {
+        FormatComponent::General() => v.visit_general(&NFGeneral),
+        FormatComponent::Number(number) => v.visit_number(number),
+        FormatComponent::Fraction(fraction) => v.visit_fraction(fraction),
+        FormatComponent::Datetime(datetime) => v.visit_datetime_tuple(datetime),
+        FormatComponent::Text(text) => v.visit_text(text),
+    }
+}
+
+pub fn walk_number<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast NFNumber) {
+    for part in &node.num_part {
+        v.visit_digit_pos_or_other(part);
+    }
+    if let Some((_, exp_part)) = &node.exp_part {
+        for part in exp_part {
+            v.visit_digit_pos_or_other(part);
+        }
+    }
+}
+
+pub fn walk_digit_pos_or_other<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast DigitPosOrOther<Percent>,
+) {
+    match node {
+        DigitPosOrOther::Digit(digit) => v.visit_digit_pos(digit),
+        DigitPosOrOther::Other(percent) => v.visit_percent(percent),
+        DigitPosOrOther::Currency(locale) => v.visit_locale(locale),
+        DigitPosOrOther::LiteralCharSpace(_)
+        | DigitPosOrOther::LiteralString(_)
+        | DigitPosOrOther::FillChar(_)
+        | DigitPosOrOther::EscapedChar(_) => {}
+    }
+}
+
+pub fn walk_fraction<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast NFFraction) {
+    let parts = node
+        .prefix
+        .iter()
+        .chain(node.integer_part.iter().flatten())
+        .chain(node.separator.iter().flatten());
+    for part in parts {
+        v.visit_digit_pos_or_other(part);
+    }
+    for token in node.numerator.iter().chain(&node.denominator) {
+        v.visit_frac_token(token);
+    }
+    for part in &node.suffix {
+        v.visit_digit_pos_or_other(part);
+    }
+    for ampm in &node.ampm_part {
+        v.visit_am_pm(ampm);
+    }
+}
+
+pub fn walk_datetime_tuple<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast DatetimeTuple) {
+    if let Some(datetime) = &node.0 {
+        v.visit_datetime(datetime);
+    }
+    if let Some(general) = &node.1 {
+        v.visit_general(general);
+    }
+    if let Some(datetime) = &node.2 {
+        v.visit_datetime(datetime);
+    }
+}
+
+pub fn walk_datetime<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast NFDatetime) {
+    for component in &node.components {
+        v.visit_datetime_component(component);
+    }
+}
+
+pub fn walk_datetime_component<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast NFDatetimeComponent,
+) {
+    match node {
+        NFDatetimeComponent::Token(token) => v.visit_datetime_token(token),
+        NFDatetimeComponent::AMPM(ampm) => v.visit_am_pm(ampm),
+        NFDatetimeComponent::DateSeparator(_)
+        | NFDatetimeComponent::TimeSeparator(_)
+        | NFDatetimeComponent::Literal(_) => {}
+    }
+}
+
+pub fn walk_text<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast NFText) {
+    for element in &node.elements {
+        v.visit_text_element(element);
+    }
+}
+
+pub fn walk_text_element<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast TextFormatElement) {
+    match node {
+        TextFormatElement::AmPm(ampm) => v.visit_am_pm(ampm),
+        TextFormatElement::General => v.visit_general(&NFGeneral),
+        _ => {}
+    }
+}
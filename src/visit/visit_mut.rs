@@ -0,0 +1,277 @@
+//! In-place traversal of the AST
+//!
+//! Replacing the currency of every section and number:
+//!
+//! ```
+//! use luscinia::types::PartLocaleID;
+//! use luscinia::visit::VisitMut;
+//! use luscinia::NumFormat;
+//!
+//! struct Euro;
+//!
+//! impl VisitMut for Euro {
+//!     fn visit_locale_mut(&mut self, locale: &mut PartLocaleID) {
+//!         if !locale.currency_symbol.is_empty() {
+//!             locale.currency_symbol = "€".to_string();
+//!         }
+//!     }
+//! }
+//!
+//! let mut format: NumFormat = "[$$-409]#,##0.00;-[$$-409]#,##0.00".parse().unwrap();
+//! Euro.visit_num_format_mut(&mut format);
+//! assert_eq!(format.to_string(), "[$€-409]#,##0.00;-[$€-409]#,##0.00");
+//! ```
+
+use crate::types::*;
+
+/// Traversal of the AST by mutable reference, see [`Visit`](super::Visit)
+pub trait VisitMut {
+    fn visit_num_format_mut(&mut self, node: &mut NumFormat) {
+        walk_num_format_mut(self, node);
+    }
+
+    /// A section holding only a condition, such as `[>100]`
+    fn visit_conditional_general_mut(
+        &mut self,
+        node: &mut SectionWrapper<(NFPartCondition, NFGeneral)>,
+    ) {
+        walk_conditional_general_mut(self, node);
+    }
+
+    /// A section that can hold a condition
+    fn visit_any_mut(&mut self, node: &mut Any) {
+        walk_any_mut(self, node);
+    }
+
+    /// A section that cannot hold a condition
+    fn visit_any_no_cond_mut(&mut self, node: &mut AnyNoCond) {
+        walk_any_no_cond_mut(self, node);
+    }
+
+    fn visit_any_inner_mut(&mut self, node: &mut AnyInner) {
+        walk_any_inner_mut(self, node);
+    }
+
+    fn visit_section_modifier_mut(&mut self, _node: &mut SectionModifier) {}
+
+    /// A `[$€-407]` locale, either before a section or within a number
+    fn visit_locale_mut(&mut self, _node: &mut PartLocaleID) {}
+
+    fn visit_color_mut(&mut self, _node: &mut NFPartColor) {}
+
+    fn visit_condition_mut(&mut self, _node: &mut NFPartCondition) {}
+
+    /// `General` wherever it is written, see [`super::Visit::visit_general`]
+    fn visit_general_mut(&mut self, _node: &mut NFGeneral) {}
+
+    fn visit_format_component_mut(&mut self, node: &mut FormatComponent) {
+        walk_format_component_mut(self, node);
+    }
+
+    fn visit_number_mut(&mut self, node: &mut NFNumber) {
+        walk_number_mut(self, node);
+    }
+
+    fn visit_digit_pos_or_other_mut(&mut self, node: &mut DigitPosOrOther<Percent>) {
+        walk_digit_pos_or_other_mut(self, node);
+    }
+
+    fn visit_digit_pos_mut(&mut self, _node: &mut DigitPos) {}
+
+    fn visit_percent_mut(&mut self, _node: &mut Percent) {}
+
+    fn visit_fraction_mut(&mut self, node: &mut NFFraction) {
+        walk_fraction_mut(self, node);
+    }
+
+    fn visit_frac_token_mut(&mut self, _node: &mut FracToken) {}
+
+    fn visit_datetime_tuple_mut(&mut self, node: &mut DatetimeTuple) {
+        walk_datetime_tuple_mut(self, node);
+    }
+
+    fn visit_datetime_mut(&mut self, node: &mut NFDatetime) {
+        walk_datetime_mut(self, node);
+    }
+
+    fn visit_datetime_component_mut(&mut self, node: &mut NFDatetimeComponent) {
+        walk_datetime_component_mut(self, node);
+    }
+
+    fn visit_datetime_token_mut(&mut self, _node: &mut NFDateTimeToken) {}
+
+    fn visit_am_pm_mut(&mut self, _node: &mut AmPm) {}
+
+    fn visit_text_mut(&mut self, node: &mut NFText) {
+        walk_text_mut(self, node);
+    }
+
+    fn visit_text_element_mut(&mut self, node: &mut TextFormatElement) {
+        walk_text_element_mut(self, node);
+    }
+}
+
+pub fn walk_num_format_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut NumFormat) {
+    match node {
+        NumFormat::ConditionalGeneral(section) => v.visit_conditional_general_mut(section),
+        NumFormat::AnyNoCond(section) => v.visit_any_no_cond_mut(section),
+        NumFormat::TwoParts(first, second) => {
+            v.visit_any_mut(first);
+            v.visit_any_mut(second);
+        }
+        NumFormat::ThreeParts(first, second, third) => {
+            v.visit_any_mut(first);
+            v.visit_any_mut(second);
+            v.visit_any_no_cond_mut(third);
+        }
+        NumFormat::FourParts(first, second, third, text) => {
+            v.visit_any_mut(first);
+            v.visit_any_mut(second);
+            v.visit_any_no_cond_mut(third);
+            if let Some(text) = text {
+                v.visit_format_component_mut(text);
+            }
+        }
+    }
+}
+
+/// Visit the modifiers, locale and color written before a section
+fn walk_section_prefixes_mut<V: VisitMut + ?Sized, T>(v: &mut V, node: &mut SectionWrapper<T>) {
+    for modifier in &mut node.special_prefix {
+        v.visit_section_modifier_mut(modifier);
+    }
+    if let Some(locale) = &mut node.locale {
+        v.visit_locale_mut(locale);
+    }
+    if let Some(color) = &mut node.color {
+        v.visit_color_mut(color);
+    }
+}
+
+pub fn walk_conditional_general_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut SectionWrapper<(NFPartCondition, NFGeneral)>,
+) {
+    walk_section_prefixes_mut(v, node);
+    v.visit_condition_mut(&mut node.inner.0);
+    v.visit_general_mut(&mut node.inner.1);
+}
+
+pub fn walk_any_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Any) {
+    walk_section_prefixes_mut(v, node);
+    v.visit_any_inner_mut(&mut node.inner);
+}
+
+pub fn walk_any_no_cond_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut AnyNoCond) {
+    walk_section_prefixes_mut(v, node);
+    v.visit_format_component_mut(&mut node.inner);
+}
+
+pub fn walk_any_inner_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut AnyInner) {
+    let AnyInner::ConditionalData(condition, component) = node;
+    if let Some(condition) = condition {
+        v.visit_condition_mut(condition);
+    }
+    v.visit_format_component_mut(component);
+}
+
+pub fn walk_format_component_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut FormatComponent) {
+    match node {
+        FormatComponent::General() => v.visit_general_mut(&mut NFGeneral),
+        FormatComponent::Number(number) => v.visit_number_mut(number),
+        FormatComponent::Fraction(fraction) => v.visit_fraction_mut(fraction),
+        FormatComponent::Datetime(datetime) => v.visit_datetime_tuple_mut(datetime),
+        FormatComponent::Text(text) => v.visit_text_mut(text),
+    }
+}
+
+pub fn walk_number_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut NFNumber) {
+    for part in &mut node.num_part {
+        v.visit_digit_pos_or_other_mut(part);
+    }
+    if let Some((_, exp_part)) = &mut node.exp_part {
+        for part in exp_part {
+            v.visit_digit_pos_or_other_mut(part);
+        }
+    }
+}
+
+pub fn walk_digit_pos_or_other_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut DigitPosOrOther<Percent>,
+) {
+    match node {
+        DigitPosOrOther::Digit(digit) => v.visit_digit_pos_mut(digit),
+        DigitPosOrOther::Other(percent) => v.visit_percent_mut(percent),
+        DigitPosOrOther::Currency(locale) => v.visit_locale_mut(locale),
+        DigitPosOrOther::LiteralCharSpace(_)
+        | DigitPosOrOther::LiteralString(_)
+        | DigitPosOrOther::FillChar(_)
+        | DigitPosOrOther::EscapedChar(_) => {}
+    }
+}
+
+pub fn walk_fraction_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut NFFraction) {
+    let parts = node
+        .prefix
+        .iter_mut()
+        .chain(node.integer_part.iter_mut().flatten())
+        .chain(node.separator.iter_mut().flatten());
+    for part in parts {
+        v.visit_digit_pos_or_other_mut(part);
+    }
+    for token in node.numerator.iter_mut().chain(&mut node.denominator) {
+        v.visit_frac_token_mut(token);
+    }
+    for part in &mut node.suffix {
+        v.visit_digit_pos_or_other_mut(part);
+    }
+    for ampm in &mut node.ampm_part {
+        v.visit_am_pm_mut(ampm);
+    }
+}
+
+pub fn walk_datetime_tuple_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut DatetimeTuple) {
+    if let Some(datetime) = &mut node.0 {
+        v.visit_datetime_mut(datetime);
+    }
+    if let Some(general) = &mut node.1 {
+        v.visit_general_mut(general);
+    }
+    if let Some(datetime) = &mut node.2 {
+        v.visit_datetime_mut(datetime);
+    }
+}
+
+pub fn walk_datetime_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut NFDatetime) {
+    for component in &mut node.components {
+        v.visit_datetime_component_mut(component);
+    }
+}
+
+pub fn walk_datetime_component_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut NFDatetimeComponent,
+) {
+    match node {
+        NFDatetimeComponent::Token(token) => v.visit_datetime_token_mut(token),
+        NFDatetimeComponent::AMPM(ampm) => v.visit_am_pm_mut(ampm),
+        NFDatetimeComponent::DateSeparator(_)
+        | NFDatetimeComponent::TimeSeparator(_)
+        | NFDatetimeComponent::Literal(_) => {}
+    }
+}
+
+pub fn walk_text_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut NFText) {
+    for element in &mut node.elements {
+        v.visit_text_element_mut(element);
+    }
+}
+
+pub fn walk_text_element_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut TextFormatElement) {
+    match node {
+        TextFormatElement::AmPm(ampm) => v.visit_am_pm_mut(ampm),
+        TextFormatElement::General => v.visit_general_mut(&mut NFGeneral),
+        _ => {}
+    }
+}